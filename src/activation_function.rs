@@ -1,35 +1,414 @@
-use crate::activation_function::ActivationFunction::{RELU, SIGMOID, TANH};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::mem::discriminant;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+use ndarray::Array2;
+use crate::activation_function::ActivationFunction::{CUSTOM, ELU, GELU, HARD_SIGMOID, LEAKY_RELU, LINEAR, MISH, PRELU, RELU,
+                                                     SELU, SIGMOID, SOFTMAX, SOFTPLUS, SOFTSIGN, SWISH, TANH};
+use crate::error::{GraymatError, Result};
+use crate::utilities::{array2_utils, math_utils};
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+/// A user-defined element-wise activation function
+///
+/// Custom activations are saved by name together with their parameters. To load a network that
+/// uses one, register a constructor for the name with [`register_activation`] first.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use ndarray::Array2;
+/// # use graymat::activation_function::{register_activation, Activation, ActivationFunction};
+/// struct Scaled { scale: f32 }
+///
+/// impl Activation for Scaled {
+///     fn forward(&self, x: &mut Array2<f32>) { x.mapv_inplace(|v| self.scale * v.tanh()); }
+///     fn derivative(&self, x: &mut Array2<f32>) { x.mapv_inplace(|v| self.scale * (1.0 - v.tanh().powi(2))); }
+///     fn name(&self) -> &str { "ScaledTanh" }
+///     fn parameters(&self) -> Vec<f32> { vec![self.scale] }
+/// }
+///
+/// register_activation("ScaledTanh", |parameters| Arc::new(Scaled { scale: parameters[0] })).unwrap();
+/// let function = ActivationFunction::custom(Scaled { scale: 1.7 });
+/// assert_eq!(function.to_string().parse::<ActivationFunction>().unwrap(), function);
+/// ```
+pub trait Activation: Send + Sync {
+    /// Apply the function in place
+    ///
+    /// * `x` - Function input, overwritten with the output
+    fn forward(&self, x: &mut Array2<f32>);
+
+    /// Replace every input with the derivative of the function at that input
+    ///
+    /// * `x` - Function input, overwritten with the derivative
+    fn derivative(&self, x: &mut Array2<f32>);
+
+    /// Stable name, used to save the function and to find its constructor when loading
+    fn name(&self) -> &str;
+
+    /// Parameters saved with the function and passed to its constructor when loading
+    fn parameters(&self) -> Vec<f32> {
+        return Vec::new();
+    }
+}
+
+impl Debug for dyn Activation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        return write!(f, "{}{:?}", self.name(), self.parameters());
+    }
+}
+
+/// Custom activations are equal if they have the same name and parameters
+impl PartialEq for dyn Activation {
+    fn eq(&self, other: &Self) -> bool {
+        return self.name() == other.name() && self.parameters() == other.parameters();
+    }
+}
+
+/// Builds a custom activation function from the parameters it was saved with
+pub type ActivationConstructor = fn(&[f32]) -> Arc<dyn Activation>;
+
+/// Constructors of custom activation functions by name
+static CUSTOM_ACTIVATIONS: RwLock<BTreeMap<String, ActivationConstructor>> = RwLock::new(BTreeMap::new());
+
+/// Register a custom activation function so networks that use it can be loaded. Registering a
+/// name again replaces its constructor.
+///
+/// * `name` - Name returned by [`Activation::name`]
+/// * `constructor` - Builds the function from its saved parameters
+/// * `returns` - Error if the name is taken by a built-in activation function
+pub fn register_activation(name: &str, constructor: ActivationConstructor) -> Result<()> {
+    if ActivationFunction::from_name(name, None).is_some() {
+        return Err(GraymatError::BuiltInActivationName(name.to_owned()));
+    }
+    CUSTOM_ACTIVATIONS.write().unwrap().insert(name.to_owned(), constructor);
+    return Ok(());
+}
+
+/// Get the names of all registered custom activation functions
+pub fn registered_activations() -> Vec<String> {
+    return CUSTOM_ACTIVATIONS.read().unwrap().keys().cloned().collect();
+}
+
+/// True if a custom activation function is registered under the name
+fn is_registered(name: &str) -> bool {
+    return CUSTOM_ACTIVATIONS.read().unwrap().contains_key(name);
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ActivationFunction {
-    SIGMOID = 1,
-    TANH = 2,
-    RELU = 3,
+    SIGMOID,
+    TANH,
+    RELU,
+    LINEAR,
+    SOFTMAX,
+    /// `x` for positive inputs, `slope * x` otherwise
+    LEAKY_RELU(f32),
+    /// Leaky ReLU whose slope is learned. The value is the current slope.
+    PRELU(f32),
+    /// `x` for positive inputs, `alpha * (e^x - 1)` otherwise
+    ELU(f32),
+    /// Scaled ELU with the self-normalizing constants of Klambauer et al.
+    SELU,
+    /// Gaussian error linear unit, tanh approximation
+    GELU,
+    /// `x * sigmoid(x)`, also known as SiLU
+    SWISH,
+    /// `x * tanh(softplus(x))`
+    MISH,
+    /// `ln(1 + e^x)`
+    SOFTPLUS,
+    /// `x / (1 + |x|)`
+    SOFTSIGN,
+    /// `clamp(x / 6 + 1 / 2, 0, 1)`
+    HARD_SIGMOID,
+    /// User-defined function, see [`Activation`]
+    CUSTOM(Arc<dyn Activation>),
 }
 
+/// Id written for custom activation functions, which are saved by name
+pub const CUSTOM_ACTIVATION_ID: u8 = 255;
+
+/// Every built-in activation function with its stable id, as written to .gnm files, and its
+/// name. Parametric functions are listed with their default parameter.
+static REGISTRY: [(u8, &str, ActivationFunction); 15] = [
+    (1, "Sigmoid", SIGMOID),
+    (2, "Tanh", TANH),
+    (3, "ReLU", RELU),
+    (4, "Linear", LINEAR),
+    (5, "Softmax", SOFTMAX),
+    (6, "LeakyReLU", LEAKY_RELU(0.01)),
+    (7, "PReLU", PRELU(0.25)),
+    (8, "ELU", ELU(1.0)),
+    (9, "SELU", SELU),
+    (10, "GELU", GELU),
+    (11, "Swish", SWISH),
+    (12, "Mish", MISH),
+    (13, "Softplus", SOFTPLUS),
+    (14, "Softsign", SOFTSIGN),
+    (15, "HardSigmoid", HARD_SIGMOID),
+];
+
+/// Alternative names accepted by [`ActivationFunction::from_name`]
+static ALIASES: [(&str, ActivationFunction); 2] = [
+    ("Identity", LINEAR),
+    ("SiLU", SWISH),
+];
+
 impl ActivationFunction {
-    /// Get activation function from u8
-    ///
-    /// * `val` - u8 value
-    /// * `returns` - Option some if val is matched else None
-    pub fn from_u8(val: u8) -> Option<Self> {
-        let functions = [ SIGMOID, TANH, RELU ];
-        for af in functions {
-            if (af as u8) == val {
-                return Some(af);
-            }
+    /// Identity, the same function as [`ActivationFunction::LINEAR`]
+    pub const IDENTITY: ActivationFunction = LINEAR;
+
+    /// SiLU, the same function as [`ActivationFunction::SWISH`]
+    pub const SILU: ActivationFunction = SWISH;
+
+    /// Wrap a user-defined activation function
+    ///
+    /// * `activation` - Custom activation
+    pub fn custom(activation: impl Activation + 'static) -> Self {
+        return CUSTOM(Arc::new(activation));
+    }
+
+    /// Build a registered custom activation function
+    ///
+    /// * `name` - Name the function was registered under
+    /// * `parameters` - Parameters passed to its constructor
+    pub fn registered(name: &str, parameters: &[f32]) -> Result<Self> {
+        let constructor = *CUSTOM_ACTIVATIONS.read().unwrap().get(name)
+            .ok_or_else(|| GraymatError::UnknownActivationName(name.to_owned()))?;
+        return Ok(CUSTOM(constructor(parameters)));
+    }
+
+    /// Get every built-in activation function, parametric functions with their default parameter
+    pub fn all() -> Vec<ActivationFunction> {
+        return REGISTRY.iter().map(|(_, _, function)| function.clone()).collect();
+    }
+
+    /// Registry entry of a built-in function
+    fn entry(&self) -> Option<&'static (u8, &'static str, ActivationFunction)> {
+        return REGISTRY.iter().find(|(_, _, function)| discriminant(function) == discriminant(self));
+    }
+
+    ///
+    /// Get the stable id of the function, as written to .gnm files
+    ///
+    pub fn id(&self) -> u8 {
+        self.entry().map_or(CUSTOM_ACTIVATION_ID, |entry| entry.0)
+    }
+
+    ///
+    /// Get the name of the function, without its parameters
+    ///
+    pub fn name(&self) -> &str {
+        match self {
+            CUSTOM(activation) => activation.name(),
+            _ => self.entry().expect("Every built-in activation function is registered").1
+        }
+    }
+
+    ///
+    /// Get the parameter of a parametric built-in function
+    ///
+    pub fn parameter(&self) -> Option<f32> {
+        return match *self {
+            LEAKY_RELU(parameter) | PRELU(parameter) | ELU(parameter) => Some(parameter),
+            _ => None
+        };
+    }
+
+    ///
+    /// Get the parameters of the function, as shown by [`Display`]
+    ///
+    pub fn parameters(&self) -> Vec<f32> {
+        return match self {
+            CUSTOM(activation) => activation.parameters(),
+            _ => self.parameter().into_iter().collect()
+        };
+    }
+
+    /// Copy of a parametric function with another parameter. Other functions are returned as is.
+    ///
+    /// * `parameter` - New parameter
+    pub fn with_parameter(&self, parameter: f32) -> Self {
+        return match self {
+            LEAKY_RELU(_) => LEAKY_RELU(parameter),
+            PRELU(_) => PRELU(parameter),
+            ELU(_) => ELU(parameter),
+            function => function.clone()
+        };
+    }
+
+    /// Get a built-in activation function from its id
+    ///
+    /// * `id` - Id returned by [`ActivationFunction::id`]
+    /// * `parameter` - Parameter of a parametric function, the default if None
+    /// * `returns` - Option some if the id is registered else None
+    pub fn from_id(id: u8, parameter: Option<f32>) -> Option<Self> {
+        let function = &REGISTRY.iter().find(|(entry_id, _, _)| *entry_id == id)?.2;
+        return Some(parameter.map_or(function.clone(), |parameter| function.with_parameter(parameter)));
+    }
+
+    /// Get a built-in activation function from its name or an alias, ignoring case
+    ///
+    /// * `name` - Name returned by [`ActivationFunction::name`]
+    /// * `parameter` - Parameter of a parametric function, the default if None
+    /// * `returns` - Option some if the name is registered else None
+    pub fn from_name(name: &str, parameter: Option<f32>) -> Option<Self> {
+        let registered = REGISTRY.iter().map(|(_, entry_name, function)| (*entry_name, function));
+        let aliases = ALIASES.iter().map(|(entry_name, function)| (*entry_name, function));
+        let function = registered.chain(aliases).find(|(entry_name, _)| entry_name.eq_ignore_ascii_case(name))?.1;
+        return Some(parameter.map_or(function.clone(), |parameter| function.with_parameter(parameter)));
+    }
+
+    /// Apply the function in place. Softmax normalizes every column.
+    ///
+    /// * `x` - Function input, overwritten with the output
+    pub fn apply_inplace(&self, x: &mut Array2<f32>) {
+        match *self {
+            SIGMOID => array2_utils::math::sig_inplace(x),
+            TANH => array2_utils::math::tanh_inplace(x),
+            RELU => array2_utils::math::relu_inplace(x),
+            LINEAR => array2_utils::math::linear_inplace(x),
+            SOFTMAX => array2_utils::math::softmax_inplace(x),
+            LEAKY_RELU(slope) | PRELU(slope) => x.mapv_inplace(|v| math_utils::leaky_relu(v, slope)),
+            ELU(alpha) => x.mapv_inplace(|v| math_utils::elu(v, alpha)),
+            SELU => x.mapv_inplace(math_utils::selu),
+            GELU => x.mapv_inplace(math_utils::gelu),
+            SWISH => x.mapv_inplace(math_utils::swish),
+            MISH => x.mapv_inplace(math_utils::mish),
+            SOFTPLUS => x.mapv_inplace(math_utils::softplus),
+            SOFTSIGN => x.mapv_inplace(math_utils::softsign),
+            HARD_SIGMOID => x.mapv_inplace(math_utils::hard_sigmoid),
+            CUSTOM(ref activation) => activation.forward(x)
         }
-        None
     }
 
-    /// Get the activation function as a string
+    /// Replace every input with the derivative of the function at that input. For softmax this is
+    /// only the diagonal of the Jacobian, see [`array2_utils::math::softmax_backward`].
     ///
-    /// * `activation` - activation function
-    pub fn convert_to_string(activation: ActivationFunction) -> String {
-        return match activation {
-            SIGMOID => "Sigmoid".to_owned(),
-            TANH => "Tanh".to_owned(),
-            RELU => "ReLU".to_owned()
+    /// * `x` - Function input, overwritten with the derivative
+    pub fn derivative_inplace(&self, x: &mut Array2<f32>) {
+        match *self {
+            SIGMOID => array2_utils::math::sig_prime_inplace(x),
+            TANH => array2_utils::math::tanh_prime_inplace(x),
+            RELU => array2_utils::math::relu_prime_inplace(x),
+            LINEAR => array2_utils::math::linear_prime_inplace(x),
+            SOFTMAX => array2_utils::math::softmax_prime_inplace(x),
+            LEAKY_RELU(slope) | PRELU(slope) => x.mapv_inplace(|v| math_utils::leaky_relu_prime(v, slope)),
+            ELU(alpha) => x.mapv_inplace(|v| math_utils::elu_prime(v, alpha)),
+            SELU => x.mapv_inplace(math_utils::selu_prime),
+            GELU => x.mapv_inplace(math_utils::gelu_prime),
+            SWISH => x.mapv_inplace(math_utils::swish_prime),
+            MISH => x.mapv_inplace(math_utils::mish_prime),
+            SOFTPLUS => x.mapv_inplace(math_utils::softplus_prime),
+            SOFTSIGN => x.mapv_inplace(math_utils::softsign_prime),
+            HARD_SIGMOID => x.mapv_inplace(math_utils::hard_sigmoid_prime),
+            CUSTOM(ref activation) => activation.derivative(x)
+        }
+    }
+
+    /// Whether the derivative can be computed from the function output alone, see
+    /// [`ActivationFunction::derivative_from_output_inplace`]. Layers that train with such a
+    /// function only cache the output for back propagation. Softmax layers back propagate the
+    /// Jacobian product of their output.
+    pub fn derivative_uses_output(&self) -> bool {
+        return match *self {
+            SIGMOID | TANH | RELU | LINEAR | SOFTMAX | SELU | SOFTPLUS | SOFTSIGN | HARD_SIGMOID => true,
+            LEAKY_RELU(slope) => slope >= 0.0,
+            ELU(alpha) => alpha >= 0.0,
+            PRELU(_) | GELU | SWISH | MISH | CUSTOM(_) => false
         };
     }
+
+    /// Replace every output with the derivative of the function at the input that produced it,
+    /// which avoids evaluating the function again during back propagation.
+    ///
+    /// * `y` - Function output, overwritten with the derivative
+    /// * `returns` - False if [`ActivationFunction::derivative_uses_output`] is false. `y` is left
+    ///               unchanged and the derivative has to be computed from the input with
+    ///               [`ActivationFunction::derivative_inplace`]. Also false for softmax, whose
+    ///               gradient is a full Jacobian product rather than an element-wise derivative.
+    pub fn derivative_from_output_inplace(&self, y: &mut Array2<f32>) -> bool {
+        match *self {
+            SIGMOID => array2_utils::math::sig_prime_from_output_inplace(y),
+            TANH => array2_utils::math::tanh_prime_from_output_inplace(y),
+            RELU => array2_utils::math::relu_prime_inplace(y),
+            LINEAR => array2_utils::math::linear_prime_inplace(y),
+            LEAKY_RELU(slope) if slope >= 0.0 => y.mapv_inplace(|v| math_utils::leaky_relu_prime_from_output(v, slope)),
+            ELU(alpha) if alpha >= 0.0 => y.mapv_inplace(|v| math_utils::elu_prime_from_output(v, alpha)),
+            SELU => y.mapv_inplace(math_utils::selu_prime_from_output),
+            SOFTPLUS => y.mapv_inplace(math_utils::softplus_prime_from_output),
+            SOFTSIGN => y.mapv_inplace(math_utils::softsign_prime_from_output),
+            HARD_SIGMOID => y.mapv_inplace(math_utils::hard_sigmoid_prime_from_output),
+            LEAKY_RELU(_) | ELU(_) | PRELU(_) | GELU | SWISH | MISH | SOFTMAX | CUSTOM(_) => return false
+        }
+        return true;
+    }
+
+    /// Serialize everything after the id: the parameter of a parametric function, followed by the
+    /// name and parameters of a custom function
+    pub(crate) fn parameters_to_bytes(&self) -> Vec<u8> {
+        let mut bytes = bincode::serialize(&self.parameter().unwrap_or(0.0)).unwrap();
+        if let CUSTOM(activation) = self {
+            bytes.extend(bincode::serialize(&(activation.name(), activation.parameters())).unwrap());
+        }
+        return bytes;
+    }
+
+    /// Restore an activation function from its id and the data written by
+    /// [`ActivationFunction::parameters_to_bytes`]
+    ///
+    /// * `id` - Activation function id
+    /// * `reader` - Remaining serialized data
+    pub(crate) fn from_bytes(id: u8, reader: &mut &[u8]) -> Result<Self> {
+        let parameter: f32 = bincode::deserialize_from(&mut *reader)?;
+        if id == CUSTOM_ACTIVATION_ID {
+            let (name, parameters): (String, Vec<f32>) = bincode::deserialize_from(reader)?;
+            return ActivationFunction::registered(&name, &parameters);
+        }
+        return ActivationFunction::from_id(id, Some(parameter)).ok_or(GraymatError::UnknownActivation(id));
+    }
+}
+
+/// The name, followed by the parameters of a parametric function, for example
+/// `LeakyReLU(0.01)`. Parsed back by [`ActivationFunction::from_str`].
+impl Display for ActivationFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let parameters = self.parameters();
+        if parameters.is_empty() {
+            return write!(f, "{}", self.name());
+        }
+        let parameters: Vec<String> = parameters.iter().map(|p| p.to_string()).collect();
+        return write!(f, "{}({})", self.name(), parameters.join(", "));
+    }
+}
+
+/// Parse a built-in or registered custom activation function, for example `ELU(0.5)`
+impl FromStr for ActivationFunction {
+    type Err = GraymatError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let unknown = || GraymatError::UnknownActivationName(s.to_owned());
+        let s = s.trim();
+        let (name, parameters) = match s.strip_suffix(')').and_then(|s| s.split_once('(')) {
+            Some((name, parameters)) => {
+                let parameters: std::result::Result<Vec<f32>, _> = parameters.split(',').map(|p| p.trim().parse::<f32>()).collect();
+                (name.trim(), parameters.map_err(|_| unknown())?)
+            }
+            None => (s, Vec::new())
+        };
+        if is_registered(name) {
+            return ActivationFunction::registered(name, &parameters);
+        }
+        if parameters.len() > 1 {
+            return Err(unknown());
+        }
+        return ActivationFunction::from_name(name, parameters.first().copied()).ok_or_else(unknown);
+    }
+}
+
+impl From<ActivationFunction> for Vec<ActivationFunction> {
+    fn from(activation: ActivationFunction) -> Self {
+        return vec![activation];
+    }
 }
@@ -0,0 +1,514 @@
+use ndarray::{s, Array2, Axis};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use crate::activation_function::ActivationFunction;
+use crate::error::{GraymatError, Result};
+use crate::initializer::Initializer;
+use crate::layer::{check_declared_size, Layer, LayerType, Parameter};
+use crate::neural_network::NeuralNetworkLayer;
+use crate::normalization::{Normalization, NormalizationType};
+
+/// Reorder sequences (sequence_length * model_size x batch) so every position of every example
+/// is a column (model_size x batch * sequence_length). Position `t` of example `n` is column
+/// `n * sequence_length + t`.
+fn sequences_to_positions(input: &Array2<f32>, model_size: usize, sequence_length: usize) -> Array2<f32> {
+    let examples = input.ncols();
+    let positions = input.as_standard_layout().into_owned()
+        .into_shape((sequence_length, model_size, examples)).unwrap()
+        .permuted_axes([1, 2, 0]);
+    return positions.as_standard_layout().into_owned().into_shape((model_size, examples * sequence_length)).unwrap();
+}
+
+/// Inverse of [`sequences_to_positions`]
+fn positions_to_sequences(positions: &Array2<f32>, model_size: usize, sequence_length: usize) -> Array2<f32> {
+    let examples = positions.ncols() / sequence_length;
+    let sequences = positions.as_standard_layout().into_owned()
+        .into_shape((model_size, examples, sequence_length)).unwrap()
+        .permuted_axes([2, 0, 1]);
+    return sequences.as_standard_layout().into_owned().into_shape((sequence_length * model_size, examples)).unwrap();
+}
+
+/// Softmax of every row in place
+fn softmax_rows(x: &mut Array2<f32>) {
+    for mut row in x.rows_mut() {
+        let max = row.fold(f32::NEG_INFINITY, |m, &v| m.max(v));
+        row.mapv_inplace(|v| (v - max).exp());
+        let sum = row.sum();
+        row /= sum;
+    }
+}
+
+/// Values cached by the forward pass for back propagation
+#[derive(Debug, Clone)]
+struct AttentionCache {
+    input: Array2<f32>,
+    query: Array2<f32>,
+    key: Array2<f32>,
+    value: Array2<f32>,
+    /// Attention weights of every example and head, `example * heads + head`
+    /// (sequence_length x sequence_length, one row per query)
+    attention: Vec<Array2<f32>>,
+    /// Concatenated head outputs
+    context: Array2<f32>
+}
+
+/// Multi-head scaled dot-product self-attention
+///
+/// A sequence of `sequence_length` positions of `model_size` features is stored as one column,
+/// position `t` at rows `t * model_size..(t + 1) * model_size` (see
+/// [`crate::recurrent::sequence_to_column`]). Each position is projected to a query, key and
+/// value, which are split into `heads` heads of `model_size / heads` features. Every head attends
+/// with `softmax(q * k / sqrt(model_size / heads))` over all positions of the same sequence, and
+/// the concatenated head outputs are projected back to `model_size` features.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiHeadAttention {
+    model_size: usize,
+    heads: usize,
+    sequence_length: usize,
+    /// Query, key, value and output projections (model_size x model_size)
+    weights: Vec<Array2<f32>>,
+    /// Query, key, value and output biases (model_size x 1)
+    biases: Vec<Array2<f32>>,
+    #[serde(skip)]
+    cache: Option<AttentionCache>,
+    #[serde(skip)]
+    weight_gradients: Vec<Array2<f32>>,
+    #[serde(skip)]
+    bias_gradients: Vec<Array2<f32>>
+}
+
+const QUERY: usize = 0;
+const KEY: usize = 1;
+const VALUE: usize = 2;
+const OUTPUT: usize = 3;
+
+impl MultiHeadAttention {
+    /// Constructor
+    ///
+    /// * `model_size` - Number of features per position
+    /// * `heads` - Number of heads, must divide `model_size`
+    /// * `sequence_length` - Number of positions per sequence
+    pub fn new(model_size: usize, heads: usize, sequence_length: usize) -> Self {
+        assert!(heads > 0 && model_size > 0 && model_size.is_multiple_of(heads),
+                "{} heads cannot split {} features", heads, model_size);
+        assert!(sequence_length > 0, "Sequence length must be positive");
+        return MultiHeadAttention {
+            model_size,
+            heads,
+            sequence_length,
+            weights: vec![Array2::zeros((model_size, model_size)); 4],
+            biases: vec![Array2::zeros((model_size, 1)); 4],
+            cache: None,
+            weight_gradients: vec![Array2::zeros((model_size, model_size)); 4],
+            bias_gradients: vec![Array2::zeros((model_size, 1)); 4]
+        };
+    }
+
+    ///
+    /// Get the number of features per position
+    ///
+    pub fn model_size(&self) -> usize {
+        self.model_size
+    }
+
+    ///
+    /// Get the number of heads
+    ///
+    pub fn heads(&self) -> usize {
+        self.heads
+    }
+
+    ///
+    /// Get the number of positions per sequence
+    ///
+    pub fn sequence_length(&self) -> usize {
+        self.sequence_length
+    }
+
+    ///
+    /// Get the attention weights of the last training pass, one matrix per example and head
+    /// (`example * heads + head`), one row per query position
+    ///
+    pub fn attention_weights(&self) -> Option<&Vec<Array2<f32>>> {
+        self.cache.as_ref().map(|cache| &cache.attention)
+    }
+
+    /// Attend over positions
+    ///
+    /// * `input` - Positions (model_size x batch * sequence_length)
+    /// * `returns` - Attention output in the same layout and the values needed to back propagate
+    fn attend(&self, input: &Array2<f32>) -> (Array2<f32>, AttentionCache) {
+        let project = |i: usize| self.weights[i].dot(input) + &self.biases[i];
+        let (query, key, value) = (project(QUERY), project(KEY), project(VALUE));
+        let head_size = self.model_size / self.heads;
+        let scale = 1.0 / (head_size as f32).sqrt();
+        let length = self.sequence_length;
+
+        let mut context = Array2::zeros(input.dim());
+        let mut attention = Vec::with_capacity(input.ncols() / length * self.heads);
+        for n in 0..input.ncols() / length {
+            for head in 0..self.heads {
+                let block = s![head * head_size..(head + 1) * head_size, n * length..(n + 1) * length];
+                let mut weights = query.slice(block).t().dot(&key.slice(block)) * scale;
+                softmax_rows(&mut weights);
+                context.slice_mut(block).assign(&value.slice(block).dot(&weights.t()));
+                attention.push(weights);
+            }
+        }
+        let output = self.weights[OUTPUT].dot(&context) + &self.biases[OUTPUT];
+        return (output, AttentionCache { input: input.to_owned(), query, key, value, attention, context });
+    }
+
+    /// Back propagate through the last training pass and store the parameter gradients
+    ///
+    /// * `output_gradient` - Loss gradient with respect to the output positions
+    /// * `returns` - Loss gradient with respect to the input positions
+    fn attend_backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let cache = self.cache.as_ref().expect("Attention must be trained forward before back propagating");
+        let head_size = self.model_size / self.heads;
+        let scale = 1.0 / (head_size as f32).sqrt();
+        let length = self.sequence_length;
+
+        let context_gradient = self.weights[OUTPUT].t().dot(output_gradient);
+        let mut gradients = vec![Array2::zeros(cache.input.dim()); 3];
+        for n in 0..cache.input.ncols() / length {
+            for head in 0..self.heads {
+                let block = s![head * head_size..(head + 1) * head_size, n * length..(n + 1) * length];
+                let weights = &cache.attention[n * self.heads + head];
+                let head_context_gradient = context_gradient.slice(block);
+                gradients[VALUE].slice_mut(block).assign(&head_context_gradient.dot(weights));
+                let weights_gradient = head_context_gradient.t().dot(&cache.value.slice(block));
+                let row_sums = (&weights_gradient * weights).sum_axis(Axis(1)).insert_axis(Axis(1));
+                let scores_gradient = weights * &(&weights_gradient - &row_sums) * scale;
+                gradients[QUERY].slice_mut(block).assign(&cache.key.slice(block).dot(&scores_gradient.t()));
+                gradients[KEY].slice_mut(block).assign(&cache.query.slice(block).dot(&scores_gradient));
+            }
+        }
+
+        let mut input_gradient = Array2::zeros(cache.input.dim());
+        for (i, gradient) in gradients.iter().enumerate() {
+            self.weight_gradients[i] = gradient.dot(&cache.input.t());
+            self.bias_gradients[i] = gradient.sum_axis(Axis(1)).insert_axis(Axis(1));
+            input_gradient += &self.weights[i].t().dot(gradient);
+        }
+        self.weight_gradients[OUTPUT] = output_gradient.dot(&cache.context.t());
+        self.bias_gradients[OUTPUT] = output_gradient.sum_axis(Axis(1)).insert_axis(Axis(1));
+        return input_gradient;
+    }
+
+    /// Restore an attention layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let attention: MultiHeadAttention = bincode::deserialize(bytes)?;
+        let (model_size, heads) = (attention.model_size, attention.heads);
+        check_declared_size("attention input", model_size.checked_mul(attention.sequence_length))?;
+        if heads == 0 || model_size == 0 || !model_size.is_multiple_of(heads) || attention.sequence_length == 0
+            || attention.weights.len() != 4 || attention.biases.len() != 4
+            || attention.weights.iter().any(|w| w.dim() != (model_size, model_size))
+            || attention.biases.iter().any(|b| b.dim() != (model_size, 1)) {
+            return Err(GraymatError::ShapeMismatch(
+                format!("attention parameters do not match {} features in {} heads", model_size, heads)));
+        }
+        let mut restored = MultiHeadAttention::new(model_size, heads, attention.sequence_length);
+        restored.weights = attention.weights;
+        restored.biases = attention.biases;
+        return Ok(restored);
+    }
+}
+
+impl Layer for MultiHeadAttention {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        let positions = sequences_to_positions(input, self.model_size, self.sequence_length);
+        return positions_to_sequences(&self.attend(&positions).0, self.model_size, self.sequence_length);
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        let positions = sequences_to_positions(input, self.model_size, self.sequence_length);
+        let (output, cache) = self.attend(&positions);
+        self.cache = Some(cache);
+        return positions_to_sequences(&output, self.model_size, self.sequence_length);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let positions = sequences_to_positions(output_gradient, self.model_size, self.sequence_length);
+        let input_gradient = self.attend_backward(&positions);
+        return positions_to_sequences(&input_gradient, self.model_size, self.sequence_length);
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return self.weights.iter().zip(self.biases.iter()).flat_map(|(w, b)| [w, b]).collect();
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return self.weight_gradients.iter().zip(self.bias_gradients.iter()).flat_map(|(w, b)| [w, b]).collect();
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        let weights = self.weights.iter_mut().zip(self.weight_gradients.iter_mut());
+        let biases = self.biases.iter_mut().zip(self.bias_gradients.iter_mut());
+        return weights.zip(biases)
+            .flat_map(|((w, dw), (b, db))| [
+                Parameter { value: w, gradient: dw, regularize: true, rows: None },
+                Parameter { value: b, gradient: db, regularize: false, rows: None }
+            ])
+            .collect();
+    }
+
+    fn initialize(&mut self, weight_initializer: Initializer, bias_initializer: Initializer, rng: &mut dyn RngCore) {
+        for (weights, biases) in self.weights.iter_mut().zip(self.biases.iter_mut()) {
+            weight_initializer.initialize(weights, rng);
+            bias_initializer.initialize(biases, rng);
+        }
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return (input_size == self.model_size * self.sequence_length).then_some(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::ATTENTION;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Sinusoidal positional encoding, added to every position of a sequence so attention can tell
+/// positions apart. Feature `2i` of position `t` gets `sin(t / 10000^(2i / model_size))` and
+/// feature `2i + 1` gets `cos(t / 10000^(2i / model_size))`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionalEncoding {
+    model_size: usize,
+    sequence_length: usize,
+    #[serde(skip)]
+    encoding: Array2<f32>
+}
+
+impl PositionalEncoding {
+    /// Constructor
+    ///
+    /// * `model_size` - Number of features per position
+    /// * `sequence_length` - Number of positions per sequence
+    pub fn new(model_size: usize, sequence_length: usize) -> Self {
+        let encoding = Array2::from_shape_fn((sequence_length * model_size, 1), |(row, _)| {
+            let (position, feature) = ((row / model_size) as f32, row % model_size);
+            let angle = position / 10000f32.powf((feature - feature % 2) as f32 / model_size as f32);
+            if feature % 2 == 0 { angle.sin() } else { angle.cos() }
+        });
+        return PositionalEncoding { model_size, sequence_length, encoding };
+    }
+
+    ///
+    /// Get the encoding added to every sequence (sequence_length * model_size x 1)
+    ///
+    pub fn encoding(&self) -> &Array2<f32> {
+        &self.encoding
+    }
+
+    /// Restore a positional encoding serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let encoding: PositionalEncoding = bincode::deserialize(bytes)?;
+        if encoding.model_size == 0 || encoding.sequence_length == 0 {
+            return Err(GraymatError::ShapeMismatch("positional encoding of an empty sequence".to_owned()));
+        }
+        check_declared_size("positional encoding", encoding.model_size.checked_mul(encoding.sequence_length))?;
+        return Ok(PositionalEncoding::new(encoding.model_size, encoding.sequence_length));
+    }
+}
+
+impl Layer for PositionalEncoding {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return input + &self.encoding;
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        return output_gradient.clone();
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return (input_size == self.model_size * self.sequence_length).then_some(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::POSITIONAL_ENCODING;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Transformer encoder block
+///
+/// `h = LayerNorm(x + Attention(x))` followed by `y = LayerNorm(h + W2 * relu(W1 * h + b1) + b2)`,
+/// where the feed forward network is applied to every position on its own.
+pub struct TransformerEncoder {
+    attention: MultiHeadAttention,
+    attention_normalization: Normalization,
+    feed_forward: NeuralNetworkLayer,
+    feed_forward_output: NeuralNetworkLayer,
+    feed_forward_normalization: Normalization
+}
+
+impl TransformerEncoder {
+    /// Constructor
+    ///
+    /// * `model_size` - Number of features per position
+    /// * `heads` - Number of attention heads, must divide `model_size`
+    /// * `feed_forward_size` - Hidden size of the feed forward network
+    /// * `sequence_length` - Number of positions per sequence
+    pub fn new(model_size: usize, heads: usize, feed_forward_size: usize, sequence_length: usize) -> Self {
+        return TransformerEncoder {
+            attention: MultiHeadAttention::new(model_size, heads, sequence_length),
+            attention_normalization: Normalization::layer(model_size),
+            feed_forward: NeuralNetworkLayer::new(model_size, feed_forward_size, ActivationFunction::RELU),
+            feed_forward_output: NeuralNetworkLayer::new(feed_forward_size, model_size, ActivationFunction::LINEAR),
+            feed_forward_normalization: Normalization::layer(model_size)
+        };
+    }
+
+    ///
+    /// Get the self-attention sublayer
+    ///
+    pub fn attention(&self) -> &MultiHeadAttention {
+        &self.attention
+    }
+
+    ///
+    /// Get the feed forward hidden layer
+    ///
+    pub fn feed_forward(&self) -> &NeuralNetworkLayer {
+        &self.feed_forward
+    }
+
+    ///
+    /// Get the feed forward output layer
+    ///
+    pub fn feed_forward_output(&self) -> &NeuralNetworkLayer {
+        &self.feed_forward_output
+    }
+
+    /// Reorder sequences into positions
+    fn positions(&self, input: &Array2<f32>) -> Array2<f32> {
+        return sequences_to_positions(input, self.attention.model_size, self.attention.sequence_length);
+    }
+
+    /// Reorder positions into sequences
+    fn sequences(&self, positions: &Array2<f32>) -> Array2<f32> {
+        return positions_to_sequences(positions, self.attention.model_size, self.attention.sequence_length);
+    }
+
+    /// Restore an encoder serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let sublayers: Vec<Vec<u8>> = bincode::deserialize(bytes)?;
+        if sublayers.len() != 5 {
+            return Err(GraymatError::ShapeMismatch(
+                format!("transformer encoder has 5 sublayers, found {}", sublayers.len())));
+        }
+        let encoder = TransformerEncoder {
+            attention: MultiHeadAttention::from_bytes(&sublayers[0])?,
+            attention_normalization: Normalization::from_bytes(&sublayers[1])?,
+            feed_forward: NeuralNetworkLayer::from_bytes(&sublayers[2])?,
+            feed_forward_output: NeuralNetworkLayer::from_bytes(&sublayers[3])?,
+            feed_forward_normalization: Normalization::from_bytes(&sublayers[4])?
+        };
+        let model_size = encoder.attention.model_size;
+        let hidden_size = encoder.feed_forward.output_size(model_size);
+        let fits = encoder.attention_normalization.normalization_type() == NormalizationType::LAYER
+            && encoder.feed_forward_normalization.normalization_type() == NormalizationType::LAYER
+            && encoder.attention_normalization.output_size(model_size).is_some()
+            && encoder.feed_forward_normalization.output_size(model_size).is_some()
+            && hidden_size.and_then(|size| encoder.feed_forward_output.output_size(size)) == Some(model_size);
+        if !fits {
+            return Err(GraymatError::ShapeMismatch(
+                format!("transformer encoder sublayers do not match {} features", model_size)));
+        }
+        return Ok(encoder);
+    }
+}
+
+impl Layer for TransformerEncoder {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        let x = self.positions(input);
+        let h = self.attention_normalization.forward(&(&x + &self.attention.attend(&x).0));
+        let f = self.feed_forward_output.forward(&self.feed_forward.forward(&h));
+        return self.sequences(&self.feed_forward_normalization.forward(&(&h + &f)));
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, rng: &mut dyn RngCore) -> Array2<f32> {
+        let x = self.positions(input);
+        let (attended, cache) = self.attention.attend(&x);
+        self.attention.cache = Some(cache);
+        let h = self.attention_normalization.forward_train(&(&x + &attended), rng);
+        let hidden = self.feed_forward.forward_train(&h, rng);
+        let f = self.feed_forward_output.forward_train(&hidden, rng);
+        let output = self.feed_forward_normalization.forward_train(&(&h + &f), rng);
+        return self.sequences(&output);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let output_gradient = self.positions(output_gradient);
+        let residual_gradient = self.feed_forward_normalization.backward(&output_gradient);
+        let hidden_gradient = self.feed_forward_output.backward(&residual_gradient);
+        let h_gradient = &residual_gradient + &self.feed_forward.backward(&hidden_gradient);
+        let residual_gradient = self.attention_normalization.backward(&h_gradient);
+        let input_gradient = &residual_gradient + &self.attention.attend_backward(&residual_gradient);
+        return self.sequences(&input_gradient);
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        let mut parameters = self.attention.parameters();
+        parameters.extend(self.attention_normalization.parameters());
+        parameters.extend(self.feed_forward.parameters());
+        parameters.extend(self.feed_forward_output.parameters());
+        parameters.extend(self.feed_forward_normalization.parameters());
+        return parameters;
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        let mut gradients = self.attention.gradients();
+        gradients.extend(self.attention_normalization.gradients());
+        gradients.extend(self.feed_forward.gradients());
+        gradients.extend(self.feed_forward_output.gradients());
+        gradients.extend(self.feed_forward_normalization.gradients());
+        return gradients;
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        let mut parameters = self.attention.parameters_mut();
+        parameters.extend(self.attention_normalization.parameters_mut());
+        parameters.extend(self.feed_forward.parameters_mut());
+        parameters.extend(self.feed_forward_output.parameters_mut());
+        parameters.extend(self.feed_forward_normalization.parameters_mut());
+        return parameters;
+    }
+
+    fn initialize(&mut self, weight_initializer: Initializer, bias_initializer: Initializer, rng: &mut dyn RngCore) {
+        self.attention.initialize(weight_initializer, bias_initializer, rng);
+        self.feed_forward.initialize(weight_initializer, bias_initializer, rng);
+        self.feed_forward_output.initialize(weight_initializer, bias_initializer, rng);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return self.attention.output_size(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::TRANSFORMER_ENCODER;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let sublayers = vec![self.attention.to_bytes(),
+                             self.attention_normalization.to_bytes(),
+                             self.feed_forward.to_bytes(),
+                             self.feed_forward_output.to_bytes(),
+                             self.feed_forward_normalization.to_bytes()];
+        return bincode::serialize(&sublayers).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+use std::path::{Path, PathBuf};
+use ndarray::Array2;
+use crate::error::GraymatError;
+use crate::metrics::Metric;
+use crate::neural_network::NeuralNetwork;
+use crate::training_history::{EpochRecord, TrainingHistory};
+
+/// A callback is invoked by [`NeuralNetwork::train`] at the start and end of training, of every
+/// epoch and of every batch. Callbacks have mutable access to the network, so they can inspect
+/// or change it, or end training early with [`NeuralNetwork::stop_training`].
+///
+/// Callbacks are registered with [`NeuralNetwork::add_callback`]. Every method does nothing by
+/// default.
+pub trait Callback {
+    /// Called before the first epoch
+    ///
+    /// * `network` - Network being trained
+    /// * `epochs` - Number of epochs that will run unless training is stopped early
+    fn on_train_start(&mut self, _network: &mut NeuralNetwork, _epochs: usize) {}
+
+    /// Called after the last epoch
+    ///
+    /// * `network` - Network being trained
+    /// * `history` - Training history
+    fn on_train_end(&mut self, _network: &mut NeuralNetwork, _history: &TrainingHistory) {}
+
+    /// Called at the start of every epoch
+    ///
+    /// * `network` - Network being trained
+    /// * `epoch` - Epoch number, starting at 1
+    fn on_epoch_start(&mut self, _network: &mut NeuralNetwork, _epoch: usize) {}
+
+    /// Called at the end of every epoch, after losses and metrics are calculated. Returning an
+    /// error ends training, and [`NeuralNetwork::train`] returns the error.
+    ///
+    /// * `network` - Network being trained
+    /// * `record` - Epoch losses and metrics
+    fn on_epoch_end(&mut self, _network: &mut NeuralNetwork, _record: &EpochRecord) -> Result<(), GraymatError> {
+        return Ok(());
+    }
+
+    /// Called before every batch
+    ///
+    /// * `network` - Network being trained
+    /// * `batch` - Batch index within the epoch
+    fn on_batch_start(&mut self, _network: &mut NeuralNetwork, _batch: usize) {}
+
+    /// Called after every batch
+    ///
+    /// * `network` - Network being trained
+    /// * `batch` - Batch index within the epoch
+    /// * `loss` - Batch loss before the update
+    fn on_batch_end(&mut self, _network: &mut NeuralNetwork, _batch: usize, _loss: f32) {}
+}
+
+/// Quantity watched by [`EarlyStopping`] and [`ModelCheckpoint`]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Monitor {
+    TRAIN_LOSS,
+    VALIDATION_LOSS,
+    METRIC(Metric),
+    VALIDATION_METRIC(Metric),
+}
+
+impl Monitor {
+    /// Get the monitored value from an epoch record
+    ///
+    /// * `record` - Epoch record
+    /// * `returns` - Some value if it was recorded else None
+    pub fn value(&self, record: &EpochRecord) -> Option<f32> {
+        return match *self {
+            Monitor::TRAIN_LOSS => Some(record.train_loss),
+            Monitor::VALIDATION_LOSS => record.validation_loss,
+            Monitor::METRIC(metric) => record.metric(metric),
+            Monitor::VALIDATION_METRIC(metric) => record.validation_metric(metric)
+        };
+    }
+
+    /// True if larger values are better. Losses and the mean absolute error are minimized,
+    /// every other metric is maximized.
+    pub fn higher_is_better(&self) -> bool {
+        return match *self {
+            Monitor::TRAIN_LOSS | Monitor::VALIDATION_LOSS => false,
+            Monitor::METRIC(metric) | Monitor::VALIDATION_METRIC(metric) => metric != Metric::MAE
+        };
+    }
+
+    /// True if `current` improves on `best` by more than `min_delta`
+    ///
+    /// * `current` - Current value
+    /// * `best` - Best value so far, None if there is no value yet
+    /// * `min_delta` - Minimum change that counts as an improvement
+    pub fn is_improvement(&self, current: f32, best: Option<f32>, min_delta: f32) -> bool {
+        return match best {
+            None => !current.is_nan(),
+            Some(best) if self.higher_is_better() => current > best + min_delta,
+            Some(best) => current < best - min_delta
+        };
+    }
+}
+
+/// Stop training once the monitored value has not improved for a number of epochs
+///
+/// Epochs where the monitored value was not recorded, for example the validation loss when
+/// training without validation data, are ignored.
+pub struct EarlyStopping {
+    monitor: Monitor,
+    patience: usize,
+    min_delta: f32,
+    restore_best_weights: bool,
+    best: Option<f32>,
+    best_parameters: Vec<Array2<f32>>,
+    best_buffers: Vec<Array2<f32>>,
+    epochs_without_improvement: usize,
+    stopped_epoch: Option<usize>
+}
+
+impl EarlyStopping {
+    /// Constructor
+    ///
+    /// * `monitor` - Monitored value
+    /// * `patience` - Number of epochs without improvement before training stops
+    /// * `min_delta` - Minimum change that counts as an improvement
+    /// * `restore_best_weights` - Restore the network parameters and buffers, such as batch
+    ///                            normalization statistics, of the best epoch when training stops
+    pub fn new(monitor: Monitor, patience: usize, min_delta: f32, restore_best_weights: bool) -> Self {
+        return EarlyStopping {
+            monitor,
+            patience,
+            min_delta,
+            restore_best_weights,
+            best: None,
+            best_parameters: Vec::new(),
+            best_buffers: Vec::new(),
+            epochs_without_improvement: 0,
+            stopped_epoch: None
+        };
+    }
+
+    ///
+    /// Get the epoch training was stopped at, if it was stopped
+    ///
+    pub fn stopped_epoch(&self) -> Option<usize> {
+        self.stopped_epoch
+    }
+
+    ///
+    /// Get the best monitored value
+    ///
+    pub fn best(&self) -> Option<f32> {
+        self.best
+    }
+}
+
+impl Callback for EarlyStopping {
+    fn on_train_start(&mut self, _network: &mut NeuralNetwork, _epochs: usize) {
+        self.best = None;
+        self.best_parameters.clear();
+        self.best_buffers.clear();
+        self.epochs_without_improvement = 0;
+        self.stopped_epoch = None;
+    }
+
+    fn on_train_end(&mut self, network: &mut NeuralNetwork, _history: &TrainingHistory) {
+        if self.restore_best_weights && !self.best_parameters.is_empty() {
+            network.set_parameters(&self.best_parameters);
+            network.set_buffers(&self.best_buffers);
+        }
+    }
+
+    fn on_epoch_end(&mut self, network: &mut NeuralNetwork, record: &EpochRecord) -> Result<(), GraymatError> {
+        let current = match self.monitor.value(record) {
+            Some(value) => value,
+            None => return Ok(())
+        };
+        if self.monitor.is_improvement(current, self.best, self.min_delta) {
+            self.best = Some(current);
+            self.epochs_without_improvement = 0;
+            if self.restore_best_weights {
+                self.best_parameters = network.parameters();
+                self.best_buffers = network.buffers();
+            }
+            return Ok(());
+        }
+        self.epochs_without_improvement += 1;
+        if self.epochs_without_improvement >= self.patience {
+            self.stopped_epoch = Some(record.epoch);
+            network.stop_training();
+        }
+        return Ok(());
+    }
+}
+
+/// Save the network to a .gnm file whenever the monitored value improves. A failed save ends
+/// training with the error.
+pub struct ModelCheckpoint {
+    path: PathBuf,
+    filename: String,
+    monitor: Monitor,
+    best: Option<f32>
+}
+
+impl ModelCheckpoint {
+    /// Constructor
+    ///
+    /// * `path` - Directory path
+    /// * `filename` - Filename. The .gnm file extension will be automatically added if not already set
+    /// * `monitor` - Monitored value
+    pub fn new(path: impl AsRef<Path>, filename: &str, monitor: Monitor) -> Self {
+        return ModelCheckpoint {
+            path: path.as_ref().to_path_buf(),
+            filename: filename.to_owned(),
+            monitor,
+            best: None
+        };
+    }
+
+    ///
+    /// Get the best monitored value
+    ///
+    pub fn best(&self) -> Option<f32> {
+        self.best
+    }
+}
+
+impl Callback for ModelCheckpoint {
+    fn on_train_start(&mut self, _network: &mut NeuralNetwork, _epochs: usize) {
+        self.best = None;
+    }
+
+    fn on_epoch_end(&mut self, network: &mut NeuralNetwork, record: &EpochRecord) -> Result<(), GraymatError> {
+        let current = match self.monitor.value(record) {
+            Some(value) => value,
+            None => return Ok(())
+        };
+        if !self.monitor.is_improvement(current, self.best, 0.0) {
+            return Ok(());
+        }
+        self.best = Some(current);
+        return network.to_file(&self.path, &self.filename);
+    }
+}
+
+/// Print the losses and metrics of every n-th epoch
+pub struct ProgressLogger {
+    every_n_epochs: usize,
+    epochs: usize
+}
+
+impl ProgressLogger {
+    /// Constructor
+    ///
+    /// * `every_n_epochs` - Print every n-th epoch. The last epoch is always printed.
+    pub fn new(every_n_epochs: usize) -> Self {
+        return ProgressLogger { every_n_epochs: every_n_epochs.max(1), epochs: 0 };
+    }
+
+    /// Format an epoch record as a single line, for example
+    /// `Epoch 10/100 - loss: 0.1532 - validation_loss: 0.1710 - accuracy: 0.9500`
+    ///
+    /// * `record` - Epoch record
+    pub fn format(&self, record: &EpochRecord) -> String {
+        let mut line = format!("Epoch {}/{} - loss: {:.4}", record.epoch, self.epochs, record.train_loss);
+        if let Some(validation_loss) = record.validation_loss {
+            line.push_str(&format!(" - validation_loss: {:.4}", validation_loss));
+        }
+        for (metric, value) in record.metrics.iter() {
+            line.push_str(&format!(" - {}: {:.4}", Metric::convert_to_string(*metric), value));
+        }
+        for (metric, value) in record.validation_metrics.iter() {
+            line.push_str(&format!(" - validation_{}: {:.4}", Metric::convert_to_string(*metric), value));
+        }
+        return line;
+    }
+}
+
+impl Callback for ProgressLogger {
+    fn on_train_start(&mut self, _network: &mut NeuralNetwork, epochs: usize) {
+        self.epochs = epochs;
+    }
+
+    fn on_epoch_end(&mut self, _network: &mut NeuralNetwork, record: &EpochRecord) -> Result<(), GraymatError> {
+        if record.epoch.is_multiple_of(self.every_n_epochs) || record.epoch == self.epochs {
+            println!("{}", self.format(record));
+        }
+        return Ok(());
+    }
+}
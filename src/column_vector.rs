@@ -70,6 +70,17 @@ impl ColumnVector {
         return &mut self.data;
     }
 
+    /// Index of the largest element. Ties resolve to the lowest index.
+    pub fn argmax(&self) -> usize {
+        let mut max_element = (0, f32::MIN);
+        for (i, num) in self.data.iter().enumerate() {
+            if *num > max_element.1 {
+                max_element = (i, *num);
+            }
+        }
+        return max_element.0;
+    }
+
     pub fn sum(self) -> f32 {
         let mut sum = 0.0;
         for element in self.data.iter() {
@@ -93,6 +104,7 @@ impl IndexMut<usize> for ColumnVector {
     }
 }
 
+#[allow(clippy::write_with_newline, clippy::to_string_in_format_args)]
 impl Display for ColumnVector {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}\n", self.data.to_string())
@@ -113,6 +125,7 @@ impl Sub for ColumnVector {
     }
 }
 
+#[allow(clippy::needless_borrow)]
 impl Clone for ColumnVector {
     fn clone(&self) -> Self {
         return ColumnVector::from(&self.get_data());
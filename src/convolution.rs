@@ -0,0 +1,602 @@
+use ndarray::{s, Array2, Axis};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use crate::error::{GraymatError, Result};
+use crate::initializer::Initializer;
+use crate::layer::{check_declared_size, Layer, LayerType, Parameter};
+
+/// Shape of an image, `(channels, height, width)`
+///
+/// Images travel through a network flattened, one example per column. Element `(c, y, x)` of an
+/// image is at row `(c * height + y) * width + x`.
+pub type ImageShape = (usize, usize, usize);
+
+/// Number of values in a flattened image, None on overflow
+///
+/// * `shape` - Image shape
+fn image_size(shape: ImageShape) -> Option<usize> {
+    return shape.0.checked_mul(shape.1)?.checked_mul(shape.2);
+}
+
+/// Geometry of a square window sliding over an image
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+struct Window {
+    input_shape: ImageShape,
+    size: usize,
+    stride: usize,
+    padding: usize
+}
+
+impl Window {
+    fn new(input_shape: ImageShape, size: usize, stride: usize, padding: usize) -> Self {
+        let window = Window { input_shape, size, stride, padding };
+        assert!(window.is_valid(), "A {}x{} window with stride {} and padding {} does not fit a {}x{}x{} image",
+                size, size, stride, padding, input_shape.0, input_shape.1, input_shape.2);
+        return window;
+    }
+
+    /// True if the window fits the padded image at least once
+    fn is_valid(&self) -> bool {
+        let (channels, height, width) = self.input_shape;
+        let padded = |side: usize| self.padding.checked_mul(2).and_then(|padding| side.checked_add(padding));
+        return channels > 0 && self.size > 0 && self.stride > 0
+            && padded(height).is_some_and(|height| height >= self.size)
+            && padded(width).is_some_and(|width| width >= self.size);
+    }
+
+    /// Check the sizes of a window loaded from a file
+    ///
+    /// * `output_channels` - Number of channels of the output image
+    fn check_declared_sizes(&self, output_channels: usize) -> Result<()> {
+        if !self.is_valid() {
+            return Err(GraymatError::ShapeMismatch(
+                format!("{}x{} windows with stride {} and padding {} do not fit {:?} images",
+                        self.size, self.size, self.stride, self.padding, self.input_shape)));
+        }
+        check_declared_size("input image", image_size(self.input_shape))?;
+        check_declared_size("window", image_size((self.input_shape.0, self.size, self.size)))?;
+        check_declared_size("output image", image_size((output_channels, self.output_height(), self.output_width())))?;
+        return Ok(());
+    }
+
+    fn output_height(&self) -> usize {
+        return (self.input_shape.1 + 2 * self.padding - self.size) / self.stride + 1;
+    }
+
+    fn output_width(&self) -> usize {
+        return (self.input_shape.2 + 2 * self.padding - self.size) / self.stride + 1;
+    }
+
+    /// Number of window positions per image
+    fn positions(&self) -> usize {
+        return self.output_height() * self.output_width();
+    }
+
+    /// Number of values in a flattened input image
+    fn input_size(&self) -> usize {
+        return self.input_shape.0 * self.input_shape.1 * self.input_shape.2;
+    }
+
+    /// Index of the input row a window element reads, None if it falls in the padding
+    ///
+    /// * `channel` - Image channel
+    /// * `position` - Window position
+    /// * `offset` - Element within the window, row-major
+    fn input_row(&self, channel: usize, position: usize, offset: usize) -> Option<usize> {
+        let (_, height, width) = self.input_shape;
+        let output_width = self.output_width();
+        let y = (position / output_width * self.stride + offset / self.size).checked_sub(self.padding)?;
+        let x = (position % output_width * self.stride + offset % self.size).checked_sub(self.padding)?;
+        if y >= height || x >= width {
+            return None;
+        }
+        return Some((channel * height + y) * width + x);
+    }
+
+    /// Unfold every window position of every image into a column. Row `(c * size + i) * size + j`
+    /// of column `n * positions + p` holds element `(c, i, j)` of window position `p` of example
+    /// `n`. Padding reads as 0.
+    ///
+    /// * `input` - Images (channels * height * width x batch)
+    /// * `returns` - Columns (channels * size * size x batch * positions)
+    fn im2col(&self, input: &Array2<f32>) -> Array2<f32> {
+        let window_size = self.size * self.size;
+        let positions = self.positions();
+        let mut columns = Array2::zeros((self.input_shape.0 * window_size, input.ncols() * positions));
+        for (n, image) in input.columns().into_iter().enumerate() {
+            for channel in 0..self.input_shape.0 {
+                for offset in 0..window_size {
+                    for position in 0..positions {
+                        if let Some(row) = self.input_row(channel, position, offset) {
+                            columns[[channel * window_size + offset, n * positions + position]] = image[row];
+                        }
+                    }
+                }
+            }
+        }
+        return columns;
+    }
+
+    /// Fold columns back into images, summing the values of overlapping windows. This is the
+    /// transpose of [`Window::im2col`], so it maps gradients of the columns to gradients of the
+    /// images.
+    ///
+    /// * `columns` - Columns (channels * size * size x batch * positions)
+    /// * `returns` - Images (channels * height * width x batch)
+    fn col2im(&self, columns: &Array2<f32>) -> Array2<f32> {
+        let window_size = self.size * self.size;
+        let positions = self.positions();
+        let examples = columns.ncols() / positions;
+        let mut images = Array2::zeros((self.input_size(), examples));
+        for n in 0..examples {
+            for channel in 0..self.input_shape.0 {
+                for offset in 0..window_size {
+                    for position in 0..positions {
+                        if let Some(row) = self.input_row(channel, position, offset) {
+                            images[[row, n]] += columns[[channel * window_size + offset, n * positions + position]];
+                        }
+                    }
+                }
+            }
+        }
+        return images;
+    }
+}
+
+/// Reorder per-position outputs (maps x batch * positions) into flattened images
+/// (maps * positions x batch)
+fn positions_to_images(output: Array2<f32>, positions: usize) -> Array2<f32> {
+    let (maps, columns) = output.dim();
+    let examples = columns / positions;
+    let output = output.into_shape((maps, examples, positions)).unwrap().permuted_axes([0, 2, 1]);
+    return output.as_standard_layout().into_owned().into_shape((maps * positions, examples)).unwrap();
+}
+
+/// Inverse of [`positions_to_images`]
+fn images_to_positions(images: &Array2<f32>, positions: usize) -> Array2<f32> {
+    let (rows, examples) = images.dim();
+    let maps = rows / positions;
+    let images = images.as_standard_layout().into_owned().into_shape((maps, positions, examples)).unwrap();
+    let images = images.permuted_axes([0, 2, 1]);
+    return images.as_standard_layout().into_owned().into_shape((maps, examples * positions)).unwrap();
+}
+
+/// 2D convolution (cross-correlation) of a multi-channel image with a bank of square filters
+///
+/// Each filter spans every input channel and produces one output channel. The convolution is
+/// computed as a single matrix product over the [im2col](Window::im2col) unfolding of the batch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conv2D {
+    window: Window,
+    weights: Array2<f32>,
+    biases: Array2<f32>,
+    #[serde(skip)]
+    columns: Array2<f32>,
+    #[serde(skip)]
+    weight_gradient: Array2<f32>,
+    #[serde(skip)]
+    bias_gradient: Array2<f32>
+}
+
+impl Conv2D {
+    /// Constructor
+    ///
+    /// * `input_shape` - Input image shape, `(channels, height, width)`
+    /// * `filters` - Number of filters, the number of output channels
+    /// * `kernel_size` - Filter height and width
+    /// * `stride` - Distance between window positions
+    /// * `padding` - Zeros added on every side of the input
+    pub fn new(input_shape: ImageShape, filters: usize, kernel_size: usize, stride: usize, padding: usize) -> Self {
+        let window = Window::new(input_shape, kernel_size, stride, padding);
+        let weight_columns = input_shape.0 * kernel_size * kernel_size;
+        return Conv2D {
+            window,
+            weights: Array2::zeros((filters, weight_columns)),
+            biases: Array2::zeros((filters, 1)),
+            columns: Array2::zeros((weight_columns, 0)),
+            weight_gradient: Array2::zeros((filters, weight_columns)),
+            bias_gradient: Array2::zeros((filters, 1))
+        };
+    }
+
+    ///
+    /// Get the input image shape
+    ///
+    pub fn input_shape(&self) -> ImageShape {
+        self.window.input_shape
+    }
+
+    ///
+    /// Get the output image shape, one channel per filter
+    ///
+    pub fn output_shape(&self) -> ImageShape {
+        (self.weights.nrows(), self.window.output_height(), self.window.output_width())
+    }
+
+    ///
+    /// Get the filter weights, one filter per row laid out as `(channel, y, x)`
+    ///
+    pub fn weights(&self) -> &Array2<f32> {
+        &self.weights
+    }
+
+    ///
+    /// Get the filter weights (mutable)
+    ///
+    pub fn weights_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.weights
+    }
+
+    ///
+    /// Get the filter biases
+    ///
+    pub fn biases(&self) -> &Array2<f32> {
+        &self.biases
+    }
+
+    ///
+    /// Get the filter biases (mutable)
+    ///
+    pub fn biases_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.biases
+    }
+
+    /// Convolve unfolded images
+    ///
+    /// * `columns` - im2col unfolding of the input
+    fn convolve(&self, columns: &Array2<f32>) -> Array2<f32> {
+        return positions_to_images(self.weights.dot(columns) + &self.biases, self.window.positions());
+    }
+
+    /// Restore a convolution serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut conv: Conv2D = bincode::deserialize(bytes)?;
+        let window = conv.window;
+        window.check_declared_sizes(conv.weights.nrows())?;
+        let weight_columns = window.input_shape.0 * window.size * window.size;
+        if conv.weights.ncols() != weight_columns || conv.biases.dim() != (conv.weights.nrows(), 1) {
+            return Err(GraymatError::ShapeMismatch(
+                format!("{}x{} filters with {}x{} biases do not fit {}x{} windows over {:?} images",
+                        conv.weights.nrows(), conv.weights.ncols(), conv.biases.nrows(), conv.biases.ncols(),
+                        window.size, window.size, window.input_shape)));
+        }
+        conv.columns = Array2::zeros((weight_columns, 0));
+        conv.weight_gradient = Array2::zeros(conv.weights.dim());
+        conv.bias_gradient = Array2::zeros(conv.biases.dim());
+        return Ok(conv);
+    }
+}
+
+impl Layer for Conv2D {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return self.convolve(&self.window.im2col(input));
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        self.columns = self.window.im2col(input);
+        return self.convolve(&self.columns);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let delta = images_to_positions(output_gradient, self.window.positions());
+        self.weight_gradient = delta.dot(&self.columns.t());
+        self.bias_gradient = delta.sum_axis(Axis(1)).insert_axis(Axis(1));
+        return self.window.col2im(&self.weights.t().dot(&delta));
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.weights, &self.biases];
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.weight_gradient, &self.bias_gradient];
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        return vec![
+            Parameter { value: &mut self.weights, gradient: &mut self.weight_gradient, regularize: true, rows: None },
+            Parameter { value: &mut self.biases, gradient: &mut self.bias_gradient, regularize: false, rows: None }
+        ];
+    }
+
+    fn initialize(&mut self, weight_initializer: Initializer, bias_initializer: Initializer, rng: &mut dyn RngCore) {
+        weight_initializer.initialize(&mut self.weights, rng);
+        bias_initializer.initialize(&mut self.biases, rng);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        let (channels, height, width) = self.output_shape();
+        return (input_size == self.window.input_size()).then_some(channels * height * width);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::CONV2D;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Pooling over square windows of every channel on its own
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+enum Pooling {
+    MAX,
+    AVERAGE
+}
+
+/// Shared implementation of [`MaxPool2D`] and [`AvgPool2D`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Pool2D {
+    pooling: Pooling,
+    window: Window,
+    /// Column row each max pooling output was taken from, in the layout of the pooled
+    /// positions (channels x batch * positions)
+    #[serde(skip)]
+    selected: Array2<usize>
+}
+
+impl Pool2D {
+    fn new(pooling: Pooling, input_shape: ImageShape, pool_size: usize, stride: usize) -> Self {
+        return Pool2D { pooling, window: Window::new(input_shape, pool_size, stride, 0), selected: Array2::zeros((0, 0)) };
+    }
+
+    fn from_bytes(pooling: Pooling, bytes: &[u8]) -> Result<Self> {
+        let pool: Pool2D = bincode::deserialize(bytes)?;
+        pool.window.check_declared_sizes(pool.window.input_shape.0)?;
+        if pool.pooling != pooling || pool.window.padding != 0 {
+            return Err(GraymatError::ShapeMismatch(
+                format!("{}x{} pooling windows do not fit {:?} images", pool.window.size, pool.window.size,
+                        pool.window.input_shape)));
+        }
+        return Ok(pool);
+    }
+
+    fn output_shape(&self) -> ImageShape {
+        (self.window.input_shape.0, self.window.output_height(), self.window.output_width())
+    }
+
+    /// Pool a batch
+    ///
+    /// * `input` - Images (channels * height * width x batch)
+    /// * `returns` - Pooled positions (channels x batch * positions) and, for max pooling, the
+    ///               column row of every maximum
+    fn pool(&self, input: &Array2<f32>) -> (Array2<f32>, Array2<usize>) {
+        let columns = self.window.im2col(input);
+        let window_size = self.window.size * self.window.size;
+        let channels = self.window.input_shape.0;
+        let mut pooled = Array2::zeros((channels, columns.ncols()));
+        let mut selected = Array2::zeros((0, 0));
+        match self.pooling {
+            Pooling::MAX => {
+                selected = Array2::zeros((channels, columns.ncols()));
+                for channel in 0..channels {
+                    for (j, column) in columns.columns().into_iter().enumerate() {
+                        let mut best = channel * window_size;
+                        for row in best + 1..(channel + 1) * window_size {
+                            if column[row] > column[best] {
+                                best = row;
+                            }
+                        }
+                        pooled[[channel, j]] = column[best];
+                        selected[[channel, j]] = best;
+                    }
+                }
+            }
+            Pooling::AVERAGE => {
+                for channel in 0..channels {
+                    let rows = columns.slice(s![channel * window_size..(channel + 1) * window_size, ..]);
+                    pooled.row_mut(channel).assign(&rows.mean_axis(Axis(0)).unwrap());
+                }
+            }
+        }
+        return (pooled, selected);
+    }
+
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return positions_to_images(self.pool(input).0, self.window.positions());
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>) -> Array2<f32> {
+        let (pooled, selected) = self.pool(input);
+        self.selected = selected;
+        return positions_to_images(pooled, self.window.positions());
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let delta = images_to_positions(output_gradient, self.window.positions());
+        let window_size = self.window.size * self.window.size;
+        let mut columns = Array2::zeros((self.window.input_shape.0 * window_size, delta.ncols()));
+        for ((channel, j), gradient) in delta.indexed_iter() {
+            match self.pooling {
+                Pooling::MAX => columns[[self.selected[[channel, j]], j]] = *gradient,
+                Pooling::AVERAGE => {
+                    for row in channel * window_size..(channel + 1) * window_size {
+                        columns[[row, j]] = *gradient / window_size as f32;
+                    }
+                }
+            }
+        }
+        return self.window.col2im(&columns);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        let (channels, height, width) = self.output_shape();
+        return (input_size == self.window.input_size()).then_some(channels * height * width);
+    }
+}
+
+/// Max pooling. Every channel is reduced to the maximum of each window.
+#[derive(Debug, Clone)]
+pub struct MaxPool2D {
+    pool: Pool2D
+}
+
+impl MaxPool2D {
+    /// Constructor
+    ///
+    /// * `input_shape` - Input image shape, `(channels, height, width)`
+    /// * `pool_size` - Window height and width
+    /// * `stride` - Distance between window positions, usually `pool_size`
+    pub fn new(input_shape: ImageShape, pool_size: usize, stride: usize) -> Self {
+        return MaxPool2D { pool: Pool2D::new(Pooling::MAX, input_shape, pool_size, stride) };
+    }
+
+    ///
+    /// Get the output image shape
+    ///
+    pub fn output_shape(&self) -> ImageShape {
+        self.pool.output_shape()
+    }
+
+    /// Restore a max pooling layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        return Ok(MaxPool2D { pool: Pool2D::from_bytes(Pooling::MAX, bytes)? });
+    }
+}
+
+impl Layer for MaxPool2D {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return self.pool.forward(input);
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        return self.pool.forward_train(input);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        return self.pool.backward(output_gradient);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return self.pool.output_size(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::MAX_POOL2D;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(&self.pool).unwrap();
+    }
+}
+
+/// Average pooling. Every channel is reduced to the mean of each window.
+#[derive(Debug, Clone)]
+pub struct AvgPool2D {
+    pool: Pool2D
+}
+
+impl AvgPool2D {
+    /// Constructor
+    ///
+    /// * `input_shape` - Input image shape, `(channels, height, width)`
+    /// * `pool_size` - Window height and width
+    /// * `stride` - Distance between window positions, usually `pool_size`
+    pub fn new(input_shape: ImageShape, pool_size: usize, stride: usize) -> Self {
+        return AvgPool2D { pool: Pool2D::new(Pooling::AVERAGE, input_shape, pool_size, stride) };
+    }
+
+    ///
+    /// Get the output image shape
+    ///
+    pub fn output_shape(&self) -> ImageShape {
+        self.pool.output_shape()
+    }
+
+    /// Restore an average pooling layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        return Ok(AvgPool2D { pool: Pool2D::from_bytes(Pooling::AVERAGE, bytes)? });
+    }
+}
+
+impl Layer for AvgPool2D {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return self.pool.forward(input);
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        return self.pool.forward_train(input);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        return self.pool.backward(output_gradient);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return self.pool.output_size(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::AVG_POOL2D;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(&self.pool).unwrap();
+    }
+}
+
+/// Flatten images into feature vectors
+///
+/// Images already travel through a network flattened, one example per column, so this layer
+/// passes its input through unchanged. It checks the image shape and marks where convolutional
+/// layers hand over to dense layers.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Flatten {
+    input_shape: ImageShape
+}
+
+impl Flatten {
+    /// Constructor
+    ///
+    /// * `input_shape` - Input image shape, `(channels, height, width)`
+    pub fn new(input_shape: ImageShape) -> Self {
+        return Flatten { input_shape };
+    }
+
+    ///
+    /// Get the input image shape
+    ///
+    pub fn input_shape(&self) -> ImageShape {
+        self.input_shape
+    }
+
+    /// Restore a flatten layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let flatten: Flatten = bincode::deserialize(bytes)?;
+        check_declared_size("input image", image_size(flatten.input_shape))?;
+        return Ok(flatten);
+    }
+}
+
+impl Layer for Flatten {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return input.clone();
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        return output_gradient.clone();
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        let (channels, height, width) = self.input_shape;
+        return (input_size == channels * height * width).then_some(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::FLATTEN;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
@@ -0,0 +1,249 @@
+use ndarray::{s, Array2, ArrayView1};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use crate::error::{GraymatError, Result};
+use crate::initializer::Initializer;
+use crate::layer::{check_declared_size, Layer, LayerType, Parameter};
+
+/// Embedding layer mapping integer indices to learned dense vectors
+///
+/// The first `indices` inputs of every example are category indices in
+/// `0..vocabulary_size`, stored as whole numbers. Each one is replaced by its row of the
+/// embedding table, so the output starts with `indices * embedding_size` values, index `i` at
+/// rows `i * embedding_size..(i + 1) * embedding_size`. Any `features` inputs after the indices
+/// are numeric and are passed through unchanged after the embeddings, which lets categorical and
+/// numeric inputs share one network.
+///
+/// Only the rows that were looked up receive a gradient, and only those rows are scaled, clipped
+/// and updated by the optimizer, so a training step costs O(looked up rows) rather than
+/// O(vocabulary_size). The table is not penalized by the network's regularization.
+///
+/// Out of vocabulary or fractional indices are a panic in the forward pass, see
+/// [`Embedding::check_indices`]. Indices at or above 2^24 cannot be stored exactly as f32.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Embedding {
+    vocabulary_size: usize,
+    embedding_size: usize,
+    indices: usize,
+    features: usize,
+    /// One row per index (vocabulary_size x embedding_size)
+    embeddings: Array2<f32>,
+    #[serde(skip)]
+    lookups: Option<Array2<usize>>,
+    #[serde(skip)]
+    gradient: Array2<f32>,
+    #[serde(skip)]
+    rows: Vec<usize>
+}
+
+impl Embedding {
+    /// Constructor
+    ///
+    /// * `vocabulary_size` - Number of distinct indices
+    /// * `embedding_size` - Length of each embedding vector
+    /// * `indices` - Number of indices at the start of every input
+    pub fn new(vocabulary_size: usize, embedding_size: usize, indices: usize) -> Self {
+        assert!(vocabulary_size > 0 && embedding_size > 0, "Embeddings need a vocabulary and a size");
+        return Embedding {
+            vocabulary_size,
+            embedding_size,
+            indices,
+            features: 0,
+            embeddings: Array2::zeros((vocabulary_size, embedding_size)),
+            lookups: None,
+            gradient: Array2::zeros((vocabulary_size, embedding_size)),
+            rows: Vec::new()
+        };
+    }
+
+    /// Set the number of numeric features that follow the indices and are passed through
+    ///
+    /// * `features` - Number of numeric features
+    pub fn set_features(&mut self, features: usize) {
+        self.features = features;
+    }
+
+    ///
+    /// Get the number of numeric features that follow the indices
+    ///
+    pub fn features(&self) -> usize {
+        self.features
+    }
+
+    ///
+    /// Get the number of distinct indices
+    ///
+    pub fn vocabulary_size(&self) -> usize {
+        self.vocabulary_size
+    }
+
+    ///
+    /// Get the length of each embedding vector
+    ///
+    pub fn embedding_size(&self) -> usize {
+        self.embedding_size
+    }
+
+    ///
+    /// Get the number of indices at the start of every input
+    ///
+    pub fn indices(&self) -> usize {
+        self.indices
+    }
+
+    ///
+    /// Get the embedding table, one row per index
+    ///
+    pub fn embeddings(&self) -> &Array2<f32> {
+        &self.embeddings
+    }
+
+    ///
+    /// Get a mutable reference to the embedding table
+    ///
+    pub fn embeddings_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.embeddings
+    }
+
+    /// Get the embedding vector of an index
+    ///
+    /// * `index` - Index in `0..vocabulary_size`
+    pub fn embedding(&self, index: usize) -> ArrayView1<'_, f32> {
+        return self.embeddings.row(index);
+    }
+
+    ///
+    /// Get the rows of the embedding table that received a gradient in the last backward pass
+    ///
+    pub fn updated_rows(&self) -> &Vec<usize> {
+        &self.rows
+    }
+
+    /// Check that the first `indices` inputs of every example are whole numbers in
+    /// `0..vocabulary_size`. [`Layer::forward`] and [`Layer::forward_train`] panic on inputs
+    /// that fail this check, so inputs from untrusted data should be checked first.
+    ///
+    /// Note: Indices are stored as f32, which represents every whole number exactly only up to
+    /// 2^24. Larger indices lose precision and may look up a neighbouring row.
+    ///
+    /// * `input` - Inputs (indices + features x batch)
+    pub fn check_indices(&self, input: &Array2<f32>) -> Result<()> {
+        if input.nrows() != self.indices + self.features {
+            return Err(GraymatError::InvalidArgument(
+                format!("embedding expects {} inputs, got {}", self.indices + self.features, input.nrows())));
+        }
+        for &value in input.slice(s![..self.indices, ..]).iter() {
+            let index = value.round();
+            if !(index >= 0.0 && index < self.vocabulary_size as f32 && (value - index).abs() < 1e-3) {
+                return Err(GraymatError::InvalidArgument(
+                    format!("embedding index {} is outside the vocabulary of {}", value, self.vocabulary_size)));
+            }
+        }
+        return Ok(());
+    }
+
+    /// Read the indices of every example
+    ///
+    /// * `input` - Inputs (indices + features x batch)
+    fn lookups(&self, input: &Array2<f32>) -> Array2<usize> {
+        if let Err(error) = self.check_indices(input) {
+            panic!("{}", error);
+        }
+        return input.slice(s![..self.indices, ..]).mapv(|value| value.round() as usize);
+    }
+
+    /// Look up the embeddings of every example and append the numeric features
+    fn embed(&self, input: &Array2<f32>, lookups: &Array2<usize>) -> Array2<f32> {
+        let embedded = self.indices * self.embedding_size;
+        let mut output = Array2::zeros((embedded + self.features, input.ncols()));
+        for ((i, n), &index) in lookups.indexed_iter() {
+            output.slice_mut(s![i * self.embedding_size..(i + 1) * self.embedding_size, n]).assign(&self.embeddings.row(index));
+        }
+        output.slice_mut(s![embedded.., ..]).assign(&input.slice(s![self.indices.., ..]));
+        return output;
+    }
+
+    /// Restore an embedding layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let embedding: Embedding = bincode::deserialize(bytes)?;
+        let shape = (embedding.vocabulary_size, embedding.embedding_size);
+        if shape.0 == 0 || shape.1 == 0 || embedding.embeddings.dim() != shape {
+            return Err(GraymatError::ShapeMismatch(
+                format!("embedding table is {:?}, expected {:?}", embedding.embeddings.dim(), shape)));
+        }
+        check_declared_size("embedding input", embedding.indices.checked_add(embedding.features))?;
+        check_declared_size("embedding output", embedding.indices.checked_mul(shape.1)
+            .and_then(|embedded| embedded.checked_add(embedding.features)))?;
+        let mut restored = Embedding::new(shape.0, shape.1, embedding.indices);
+        restored.features = embedding.features;
+        restored.embeddings = embedding.embeddings;
+        return Ok(restored);
+    }
+}
+
+impl Layer for Embedding {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return self.embed(input, &self.lookups(input));
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        let lookups = self.lookups(input);
+        let output = self.embed(input, &lookups);
+        self.lookups = Some(lookups);
+        return output;
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let lookups = self.lookups.as_ref().expect("Embedding must be trained forward before back propagating");
+        // Only the rows of the previous step can hold a gradient, the rest of the table stays 0
+        for &row in self.rows.iter() {
+            self.gradient.row_mut(row).fill(0.0);
+        }
+        for ((i, n), &index) in lookups.indexed_iter() {
+            let mut row = self.gradient.row_mut(index);
+            row += &output_gradient.slice(s![i * self.embedding_size..(i + 1) * self.embedding_size, n]);
+        }
+        self.rows.clear();
+        self.rows.extend(lookups.iter().copied());
+        self.rows.sort_unstable();
+        self.rows.dedup();
+
+        // Indices are not differentiable, features pass straight through
+        let mut input_gradient = Array2::zeros((self.indices + self.features, output_gradient.ncols()));
+        input_gradient.slice_mut(s![self.indices.., ..])
+            .assign(&output_gradient.slice(s![self.indices * self.embedding_size.., ..]));
+        return input_gradient;
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.embeddings];
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.gradient];
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        return vec![
+            Parameter { value: &mut self.embeddings, gradient: &mut self.gradient, regularize: false, rows: Some(&self.rows) }
+        ];
+    }
+
+    fn initialize(&mut self, weight_initializer: Initializer, _bias_initializer: Initializer, rng: &mut dyn RngCore) {
+        weight_initializer.initialize(&mut self.embeddings, rng);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return (input_size == self.indices + self.features).then_some(self.indices * self.embedding_size + self.features);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::EMBEDDING;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
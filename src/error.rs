@@ -0,0 +1,95 @@
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::io::ErrorKind;
+
+/// Errors returned by graymat
+#[derive(Debug)]
+pub enum GraymatError {
+    /// A file or directory does not exist
+    FileNotFound(String),
+    /// The data ended before a complete network could be read
+    TruncatedData,
+    /// The data does not start with the graymat file identifier
+    BadMagic,
+    /// The file was written with a format version this build cannot read
+    UnsupportedVersion(u32),
+    /// A declared size is inconsistent or exceeds a sanity limit
+    InvalidHeader(String),
+    /// The stored checksum of a layer does not match its data
+    ChecksumMismatch(usize),
+    /// Declared dimensions do not match the data, or layers do not connect
+    ShapeMismatch(String),
+    /// Unknown activation function id
+    UnknownActivation(u8),
+    /// Unknown activation function name
+    UnknownActivationName(String),
+    /// A custom activation function was registered under the name of a built-in one
+    BuiltInActivationName(String),
+    /// Unknown optimizer id
+    UnknownOptimizer(u8),
+    /// Unknown loss id
+    UnknownLoss(u8),
+    /// Custom loss without a registered constructor
+    UnknownLossName(String),
+    /// Unknown layer id, or a custom layer that cannot be restored
+    UnknownLayer(u8),
+    /// Encoding or decoding failed
+    Serialization(String),
+    /// An argument is outside its valid range
+    InvalidArgument(String),
+    /// Any other I/O error
+    Io(io::Error)
+}
+
+pub type Result<T> = std::result::Result<T, GraymatError>;
+
+impl Display for GraymatError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraymatError::FileNotFound(path) => write!(f, "File not found: {}", path),
+            GraymatError::TruncatedData => write!(f, "Unexpected end of data"),
+            GraymatError::BadMagic => write!(f, "Not a graymat network model"),
+            GraymatError::UnsupportedVersion(version) => write!(f, "Unsupported format version: {:#08x}", version),
+            GraymatError::InvalidHeader(message) => write!(f, "Invalid header: {}", message),
+            GraymatError::ChecksumMismatch(layer) => write!(f, "Checksum mismatch in layer {}", layer),
+            GraymatError::ShapeMismatch(message) => write!(f, "Shape mismatch: {}", message),
+            GraymatError::UnknownActivation(id) => write!(f, "Unknown activation function id: {}", id),
+            GraymatError::UnknownActivationName(name) => write!(f, "Unknown activation function: {}", name),
+            GraymatError::BuiltInActivationName(name) => write!(f, "{} is a built-in activation function", name),
+            GraymatError::UnknownOptimizer(id) => write!(f, "Unknown optimizer id: {}", id),
+            GraymatError::UnknownLoss(id) => write!(f, "Unknown loss id: {}", id),
+            GraymatError::UnknownLossName(name) => write!(f, "Unknown loss: {}", name),
+            GraymatError::UnknownLayer(id) => write!(f, "Unknown layer id: {}", id),
+            GraymatError::Serialization(message) => write!(f, "Serialization error: {}", message),
+            GraymatError::InvalidArgument(message) => write!(f, "Invalid argument: {}", message),
+            GraymatError::Io(error) => write!(f, "I/O error: {}", error)
+        }
+    }
+}
+
+impl std::error::Error for GraymatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraymatError::Io(error) => Some(error),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for GraymatError {
+    fn from(error: io::Error) -> Self {
+        return match error.kind() {
+            ErrorKind::UnexpectedEof => GraymatError::TruncatedData,
+            _ => GraymatError::Io(error)
+        };
+    }
+}
+
+impl From<bincode::Error> for GraymatError {
+    fn from(error: bincode::Error) -> Self {
+        return match *error {
+            bincode::ErrorKind::Io(io_error) => GraymatError::from(io_error),
+            other => GraymatError::Serialization(other.to_string())
+        };
+    }
+}
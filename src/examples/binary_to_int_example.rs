@@ -2,6 +2,7 @@ use std::path::Path;
 use ndarray::array;
 use crate::activation_function::ActivationFunction;
 use crate::column_vector::ColumnVector;
+use crate::loss::CategoricalCrossEntropy;
 use crate::cvec;
 use crate::neural_network::{NeuralNetwork};
 
@@ -32,34 +33,27 @@ pub fn binary_to_int() {
     if Path::new(model_filename).exists() && load_file_if_present {
 
         println!("Loading Network from File: {}", model_filename);
-        nn = NeuralNetwork::from_file(".", model_filename);
+        nn = NeuralNetwork::from_file(".", model_filename)
+            .unwrap_or_else(|error| panic!("Failed to load {}: {}", model_filename, error));
 
     } else {
-        nn = NeuralNetwork::new(4, 16, vec![12], ActivationFunction::SIGMOID);
+        nn = NeuralNetwork::new(4, 16, vec![12], vec![ActivationFunction::SIGMOID, ActivationFunction::SOFTMAX]);
+        nn.set_loss(Box::new(CategoricalCrossEntropy::new()));
 
         println!("Training Network...");
-        nn.train(training_data, 100_000, 6, 0.4);
+        nn.train(training_data, 10_000, 6, 0.4).unwrap();
 
         println!("Saving to file: {}", model_filename);
-        nn.to_file(".", model_filename);
-    }
-
-    let result1 = max(nn.evaluate(cvec![0, 1, 0, 0]));
-    println!("Input: [2] | Network Output: [{}: {:3}]", result1.0, result1.1);
-    let result2 = max(nn.evaluate(cvec![1, 1, 1, 0]));
-    println!("Input: [7] | Network Output: [{}: {:3}]", result2.0, result2.1);
-    let result3 = max(nn.evaluate(cvec![1, 0, 0, 1]));
-    println!("Input: [9] | Network Output: [{}: {:3}]", result3.0, result3.1);
-
-}
-
-fn max(data: ColumnVector) -> (usize, f32) {
-
-    let mut max_element = (0, f32::MIN);
-    for (i, num) in data.get_data().iter().enumerate() {
-        if num > &max_element.1 {
-            max_element = (i, *num);
+        if let Err(error) = nn.to_file(".", model_filename) {
+            println!("Failed to save {}: {}", model_filename, error);
         }
     }
-    return max_element;
+
+    let result1 = nn.evaluate(cvec![0, 1, 0, 0]);
+    println!("Input: [2] | Network Output: [{}: {:3}]", result1.argmax(), result1[result1.argmax()]);
+    let result2 = nn.evaluate(cvec![1, 1, 1, 0]);
+    println!("Input: [7] | Network Output: [{}: {:3}]", result2.argmax(), result2[result2.argmax()]);
+    let result3 = nn.evaluate(cvec![1, 0, 0, 1]);
+    println!("Input: [9] | Network Output: [{}: {:3}]", result3.argmax(), result3[result3.argmax()]);
+    println!("Input: [12] | Predicted Class: [{}]", nn.predict_class(cvec![0, 0, 1, 1]));
 }
@@ -21,16 +21,22 @@ pub fn xor() {
     if Path::new(model_filename).exists() && load_file_if_present {
 
         println!("Loading Network from File: {}", model_filename);
-        nn = NeuralNetwork::from_file(".", model_filename);
+        nn = NeuralNetwork::from_file(".", model_filename)
+            .unwrap_or_else(|error| panic!("Failed to load {}: {}", model_filename, error));
 
     } else {
-        nn = NeuralNetwork::new(2, 1, vec![2], ActivationFunction::RELU);
+        nn = NeuralNetwork::new(2, 1, vec![4], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
 
         println!("Training Network...");
-        nn.train(training_data, 1_000, 2, 0.3);
+        let history = nn.train(training_data, 1_000, 2, 1.0).unwrap();
+        if let Some(last) = history.epochs().last() {
+            println!("Final Training Loss: {}", last.train_loss);
+        }
 
         println!("Saving to file: {}", model_filename);
-        nn.to_file(".", model_filename);
+        if let Err(error) = nn.to_file(".", model_filename) {
+            println!("Failed to save {}: {}", model_filename, error);
+        }
     }
 
     println!("Input: [0, 1] | Network Output: [{}]", nn.evaluate(cvec![0, 1])[0]);
@@ -0,0 +1,123 @@
+use std::f32::consts::PI;
+use ndarray::Array2;
+use rand::Rng;
+
+/// Weight and bias initialization schemes
+///
+/// Fan in is the number of layer inputs (weight columns) and fan out the number of layer
+/// neurons (weight rows).
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Initializer {
+    /// Every element is 0
+    ZEROS,
+    /// Every element is the given value
+    CONSTANT(f32),
+    /// Uniform in [lower, upper]
+    UNIFORM(f32, f32),
+    /// Normal with the given mean and standard deviation
+    NORMAL(f32, f32),
+    /// Glorot uniform, `U(-sqrt(6 / (fan_in + fan_out)), sqrt(6 / (fan_in + fan_out)))`. Suited to
+    /// sigmoid and tanh layers.
+    XAVIER_UNIFORM,
+    /// Glorot normal, `N(0, 2 / (fan_in + fan_out))`
+    XAVIER_NORMAL,
+    /// He uniform, `U(-sqrt(6 / fan_in), sqrt(6 / fan_in))`. Suited to ReLU layers.
+    HE_UNIFORM,
+    /// He normal, `N(0, 2 / fan_in)`
+    HE_NORMAL,
+    /// LeCun uniform, `U(-sqrt(3 / fan_in), sqrt(3 / fan_in))`
+    LECUN_UNIFORM,
+    /// LeCun normal, `N(0, 1 / fan_in)`
+    LECUN_NORMAL,
+    /// Random (semi-)orthogonal matrix. Rows are orthonormal when there are fewer rows than
+    /// columns, otherwise columns are.
+    ORTHOGONAL,
+}
+
+impl Initializer {
+    /// Fill an array according to this initialization scheme
+    ///
+    /// * `arr` - Array to fill, a weight matrix (neurons x inputs) or bias vector (neurons x 1)
+    /// * `rng` - Random number generator
+    pub fn initialize<R: Rng + ?Sized>(&self, arr: &mut Array2<f32>, rng: &mut R) {
+        let fan_out = arr.nrows().max(1) as f32;
+        let fan_in = arr.ncols().max(1) as f32;
+        match *self {
+            Initializer::ZEROS => arr.fill(0.0),
+            Initializer::CONSTANT(value) => arr.fill(value),
+            Initializer::UNIFORM(lower, upper) => fill_uniform(arr, lower, upper, rng),
+            Initializer::NORMAL(mean, std_dev) => fill_normal(arr, mean, std_dev, rng),
+            Initializer::XAVIER_UNIFORM => {
+                let limit = (6.0 / (fan_in + fan_out)).sqrt();
+                fill_uniform(arr, -limit, limit, rng);
+            }
+            Initializer::XAVIER_NORMAL => fill_normal(arr, 0.0, (2.0 / (fan_in + fan_out)).sqrt(), rng),
+            Initializer::HE_UNIFORM => {
+                let limit = (6.0 / fan_in).sqrt();
+                fill_uniform(arr, -limit, limit, rng);
+            }
+            Initializer::HE_NORMAL => fill_normal(arr, 0.0, (2.0 / fan_in).sqrt(), rng),
+            Initializer::LECUN_UNIFORM => {
+                let limit = (3.0 / fan_in).sqrt();
+                fill_uniform(arr, -limit, limit, rng);
+            }
+            Initializer::LECUN_NORMAL => fill_normal(arr, 0.0, (1.0 / fan_in).sqrt(), rng),
+            Initializer::ORTHOGONAL => fill_orthogonal(arr, rng)
+        }
+    }
+}
+
+/// Fill an array with uniform random values in [lower, upper]
+fn fill_uniform<R: Rng + ?Sized>(arr: &mut Array2<f32>, lower: f32, upper: f32, rng: &mut R) {
+    for element in arr.iter_mut() {
+        *element = rng.gen_range(lower..=upper);
+    }
+}
+
+/// Fill an array with normally distributed random values
+fn fill_normal<R: Rng + ?Sized>(arr: &mut Array2<f32>, mean: f32, std_dev: f32, rng: &mut R) {
+    for element in arr.iter_mut() {
+        *element = mean + std_dev * standard_normal(rng);
+    }
+}
+
+/// Sample the standard normal distribution using the Box-Muller transform
+fn standard_normal<R: Rng + ?Sized>(rng: &mut R) -> f32 {
+    // 1 - gen() is in (0, 1], so the logarithm is finite
+    let u1: f32 = 1.0 - rng.gen::<f32>();
+    let u2: f32 = rng.gen::<f32>();
+    return (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos();
+}
+
+/// Fill an array with a random (semi-)orthogonal matrix by orthonormalizing a normal random
+/// matrix with the modified Gram-Schmidt process
+fn fill_orthogonal<R: Rng + ?Sized>(arr: &mut Array2<f32>, rng: &mut R) {
+    // Orthonormalize the vectors along the shorter dimension, each being as long as the longer one
+    let transpose = arr.nrows() < arr.ncols();
+    let (length, count) = if transpose { (arr.ncols(), arr.nrows()) } else { (arr.nrows(), arr.ncols()) };
+    let mut vectors: Array2<f32> = Array2::zeros((length, count));
+
+    let mut j = 0;
+    while j < count {
+        let mut v = vectors.column(j).to_owned();
+        v.iter_mut().for_each(|x| *x = standard_normal(rng));
+        for k in 0..j {
+            let q = vectors.column(k);
+            let projection = q.dot(&v);
+            v.zip_mut_with(&q, |x, &q| *x -= projection * q);
+        }
+        let norm = v.dot(&v).sqrt();
+        // Redraw in the unlikely case the sample is (nearly) linearly dependent
+        if norm > 1e-6 {
+            vectors.column_mut(j).assign(&(v / norm));
+            j += 1;
+        }
+    }
+
+    if transpose {
+        arr.assign(&vectors.t());
+    } else {
+        arr.assign(&vectors);
+    }
+}
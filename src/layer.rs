@@ -0,0 +1,586 @@
+use std::any::Any;
+use ndarray::{Array2, Zip};
+use rand::{Rng, RngCore};
+use serde::{Serialize, Deserialize};
+use crate::activation_function::ActivationFunction;
+use crate::error::{GraymatError, Result};
+use crate::initializer::Initializer;
+use crate::attention::{MultiHeadAttention, PositionalEncoding, TransformerEncoder};
+use crate::embedding::Embedding;
+use crate::convolution::{AvgPool2D, Conv2D, Flatten, MaxPool2D};
+use crate::layer::LayerType::{ACTIVATION, AVG_POOL2D, CONV2D, CUSTOM, DENSE, DROPOUT, FLATTEN, MAX_POOL2D, NORMALIZATION, RECURRENT,
+                        ATTENTION, POSITIONAL_ENCODING, TRANSFORMER_ENCODER, EMBEDDING};
+use crate::neural_network::NeuralNetworkLayer;
+use crate::normalization::Normalization;
+use crate::recurrent::Recurrent;
+use crate::utilities::array2_utils;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LayerType {
+    DENSE = 1,
+    ACTIVATION = 2,
+    DROPOUT = 3,
+    NORMALIZATION = 4,
+    CONV2D = 5,
+    MAX_POOL2D = 6,
+    AVG_POOL2D = 7,
+    FLATTEN = 8,
+    RECURRENT = 9,
+    ATTENTION = 10,
+    POSITIONAL_ENCODING = 11,
+    TRANSFORMER_ENCODER = 12,
+    EMBEDDING = 13,
+    CUSTOM = 255,
+}
+
+impl LayerType {
+    /// Get layer type from u8
+    ///
+    /// * `val` - u8 value
+    /// * `returns` - Option some if val is matched else None
+    pub fn from_u8(val: u8) -> Option<Self> {
+        let layers = [ DENSE, ACTIVATION, DROPOUT, NORMALIZATION, CONV2D, MAX_POOL2D, AVG_POOL2D, FLATTEN, RECURRENT, ATTENTION,
+                       POSITIONAL_ENCODING, TRANSFORMER_ENCODER, EMBEDDING, CUSTOM ];
+        return layers.into_iter().find(|l| (*l as u8) == val);
+    }
+
+    /// Get the layer type as a string
+    ///
+    /// * `layer` - layer type
+    pub fn convert_to_string(layer: LayerType) -> String {
+        return match layer {
+            DENSE => "Dense".to_owned(),
+            ACTIVATION => "Activation".to_owned(),
+            DROPOUT => "Dropout".to_owned(),
+            NORMALIZATION => "Normalization".to_owned(),
+            CONV2D => "Conv2D".to_owned(),
+            MAX_POOL2D => "MaxPool2D".to_owned(),
+            AVG_POOL2D => "AvgPool2D".to_owned(),
+            FLATTEN => "Flatten".to_owned(),
+            RECURRENT => "Recurrent".to_owned(),
+            ATTENTION => "Multi-Head Attention".to_owned(),
+            POSITIONAL_ENCODING => "Positional Encoding".to_owned(),
+            TRANSFORMER_ENCODER => "Transformer Encoder".to_owned(),
+            EMBEDDING => "Embedding".to_owned(),
+            CUSTOM => "Custom".to_owned()
+        };
+    }
+}
+
+/// A trainable parameter paired with its loss gradient from the last backward pass
+pub struct Parameter<'a> {
+    pub value: &'a mut Array2<f32>,
+    pub gradient: &'a mut Array2<f32>,
+    /// True if the network's weight penalty applies to this parameter. Biases and normalization
+    /// parameters are not penalized.
+    pub regularize: bool,
+    /// Rows that received a gradient in the last backward pass of a sparse parameter, such as the
+    /// vectors of an embedding table that were looked up. Only these rows are updated. None for
+    /// dense parameters.
+    pub rows: Option<&'a [usize]>
+}
+
+impl Parameter<'_> {
+    /// Apply a function to the gradient in place. Only the rows that received a gradient are
+    /// touched when the parameter is sparse.
+    ///
+    /// * `f` - Function applied to every gradient element
+    pub fn map_gradient_inplace(&mut self, f: impl Fn(f32) -> f32) {
+        match self.rows {
+            Some(rows) => {
+                for &row in rows {
+                    self.gradient.row_mut(row).mapv_inplace(&f);
+                }
+            }
+            None => self.gradient.mapv_inplace(f)
+        }
+    }
+
+    /// Sum of the squared gradient elements. Only the rows that received a gradient are read
+    /// when the parameter is sparse.
+    pub fn gradient_squared_sum(&self) -> f32 {
+        return match self.rows {
+            Some(rows) => rows.iter().map(|&row| self.gradient.row(row).fold(0.0, |total, g| total + g * g)).sum(),
+            None => self.gradient.fold(0.0, |total, g| total + g * g)
+        };
+    }
+}
+
+/// A layer of a [`crate::neural_network::NeuralNetwork`]. Layers take a batch of inputs, one
+/// example per column, and produce a batch of outputs.
+///
+/// While training, [`Layer::forward_train`] caches whatever [`Layer::backward`] needs, and
+/// `backward` stores the gradient of every parameter, summed over the batch. The network
+/// averages, penalizes, clips and applies those gradients through [`Layer::parameters_mut`].
+///
+/// Only `forward` and `backward` are required, so custom layers without parameters are short.
+/// Custom layers are saved into a .gnm file as [`LayerType::CUSTOM`], which cannot be loaded back.
+pub trait Layer: Any {
+    /// Forward propagate a batch for evaluation
+    ///
+    /// * `input` - Inputs (features x batch)
+    /// * `returns` - Outputs (features x batch)
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32>;
+
+    /// Forward propagate a batch while training, caching the values needed by
+    /// [`Layer::backward`]. Layers that behave the same while training, such as activations,
+    /// need not implement this.
+    ///
+    /// * `input` - Inputs (features x batch)
+    /// * `rng` - Random number generator of the network, used by stochastic layers like dropout
+    /// * `returns` - Outputs (features x batch)
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        return self.forward(input);
+    }
+
+    /// [`Layer::forward_train`] into a buffer owned by the network. The network keeps one buffer
+    /// per layer and reuses it between training steps, so layers that write their outputs in
+    /// place train without allocating while the batch size stays the same. By default the
+    /// buffer is replaced with the result of `forward_train`.
+    ///
+    /// * `input` - Inputs (features x batch)
+    /// * `output` - Buffer for the outputs (features x batch), resized by the layer if needed
+    /// * `rng` - Random number generator of the network
+    fn forward_train_into(&mut self, input: &Array2<f32>, output: &mut Array2<f32>, rng: &mut dyn RngCore) {
+        *output = self.forward_train(input, rng);
+    }
+
+    /// Back propagate the gradient of the last [`Layer::forward_train`] call and store the
+    /// parameter gradients
+    ///
+    /// * `output_gradient` - Loss gradient with respect to the layer outputs
+    /// * `returns` - Loss gradient with respect to the layer inputs
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32>;
+
+    /// [`Layer::backward`] into a buffer owned by the network, see
+    /// [`Layer::forward_train_into`]
+    ///
+    /// * `output_gradient` - Loss gradient with respect to the layer outputs
+    /// * `input_gradient` - Buffer for the loss gradient with respect to the layer inputs
+    fn backward_into(&mut self, output_gradient: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        *input_gradient = self.backward(output_gradient);
+    }
+
+    /// Trainable parameters
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return Vec::new();
+    }
+
+    /// Parameter gradients from the last backward pass, in the same order as
+    /// [`Layer::parameters`]
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return Vec::new();
+    }
+
+    /// Trainable parameters with their gradients, in the same order as [`Layer::parameters`]
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        return Vec::new();
+    }
+
+    /// State that is not trained but changes while training, such as the running statistics of
+    /// batch normalization
+    fn buffers(&self) -> Vec<&Array2<f32>> {
+        return Vec::new();
+    }
+
+    /// Mutable state that is not trained, in the same order as [`Layer::buffers`]
+    fn buffers_mut(&mut self) -> Vec<&mut Array2<f32>> {
+        return Vec::new();
+    }
+
+    /// Initialize the trainable parameters
+    ///
+    /// * `weight_initializer` - Weight initialization scheme
+    /// * `bias_initializer` - Bias initialization scheme
+    /// * `rng` - Random number generator
+    fn initialize(&mut self, _weight_initializer: Initializer, _bias_initializer: Initializer, _rng: &mut dyn RngCore) {}
+
+    /// Number of outputs for a number of inputs
+    ///
+    /// * `input_size` - Number of inputs per example
+    /// * `returns` - Some number of outputs, or None if the layer cannot take `input_size` inputs
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return Some(input_size);
+    }
+
+    /// Activation function applied last by this layer, if any. A softmax output layer is fused
+    /// with the categorical cross-entropy gradient.
+    fn output_activation(&self) -> Option<ActivationFunction> {
+        return None;
+    }
+
+    /// Back propagate a gradient taken with respect to the input of the output activation
+    /// function instead of the layer outputs. Only called on layers with an output activation.
+    ///
+    /// * `delta` - Loss gradient with respect to the pre-activation
+    /// * `returns` - Loss gradient with respect to the layer inputs
+    fn backward_pre_activation(&mut self, delta: &Array2<f32>) -> Array2<f32> {
+        return self.backward(delta);
+    }
+
+    /// [`Layer::backward_pre_activation`] into a buffer owned by the network, see
+    /// [`Layer::forward_train_into`]
+    ///
+    /// * `delta` - Loss gradient with respect to the pre-activation
+    /// * `input_gradient` - Buffer for the loss gradient with respect to the layer inputs
+    fn backward_pre_activation_into(&mut self, delta: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        *input_gradient = self.backward_pre_activation(delta);
+    }
+
+    /// Layer type, used to restore the layer from a .gnm file
+    fn layer_type(&self) -> LayerType {
+        return CUSTOM;
+    }
+
+    /// Serialize the layer configuration and parameters
+    fn to_bytes(&self) -> Vec<u8> {
+        return Vec::new();
+    }
+}
+
+impl dyn Layer {
+    /// Get the layer as a concrete layer type
+    ///
+    /// * `returns` - Some layer if it is a `T` else None
+    pub fn downcast_ref<T: Layer>(&self) -> Option<&T> {
+        return (self as &dyn Any).downcast_ref::<T>();
+    }
+
+    /// Get the layer as a concrete layer type (mutable)
+    ///
+    /// * `returns` - Some layer if it is a `T` else None
+    pub fn downcast_mut<T: Layer>(&mut self) -> Option<&mut T> {
+        return (self as &mut dyn Any).downcast_mut::<T>();
+    }
+}
+
+/// Restore a layer that was serialized with [`Layer::to_bytes`]
+///
+/// * `layer_type` - Layer type
+/// * `bytes` - Serialized layer
+pub fn layer_from_bytes(layer_type: LayerType, bytes: &[u8]) -> Result<Box<dyn Layer>> {
+    return Ok(match layer_type {
+        DENSE => Box::new(NeuralNetworkLayer::from_bytes(bytes)?),
+        ACTIVATION => Box::new(ActivationLayer::from_bytes(bytes)?),
+        DROPOUT => Box::new(Dropout::from_bytes(bytes)?),
+        NORMALIZATION => Box::new(Normalization::from_bytes(bytes)?),
+        CONV2D => Box::new(Conv2D::from_bytes(bytes)?),
+        MAX_POOL2D => Box::new(MaxPool2D::from_bytes(bytes)?),
+        AVG_POOL2D => Box::new(AvgPool2D::from_bytes(bytes)?),
+        FLATTEN => Box::new(Flatten::from_bytes(bytes)?),
+        RECURRENT => Box::new(Recurrent::from_bytes(bytes)?),
+        ATTENTION => Box::new(MultiHeadAttention::from_bytes(bytes)?),
+        POSITIONAL_ENCODING => Box::new(PositionalEncoding::from_bytes(bytes)?),
+        TRANSFORMER_ENCODER => Box::new(TransformerEncoder::from_bytes(bytes)?),
+        EMBEDDING => Box::new(Embedding::from_bytes(bytes)?),
+        CUSTOM => return Err(GraymatError::UnknownLayer(CUSTOM as u8))
+    });
+}
+
+/// Largest number of values any one array, image or output of a loaded layer may declare
+pub(crate) const MAX_LAYER_PARAMETERS: usize = 1 << 28;
+
+/// Check a size declared by a serialized layer before anything is allocated or indexed from it.
+/// Every layer loaded from a file goes through this check.
+///
+/// * `description` - What the size is of, for the error message
+/// * `size` - Size computed from the declared values with checked arithmetic, None on overflow
+/// * `returns` - The size if it is at most [`MAX_LAYER_PARAMETERS`]
+pub(crate) fn check_declared_size(description: &str, size: Option<usize>) -> Result<usize> {
+    return match size {
+        Some(size) if size <= MAX_LAYER_PARAMETERS => Ok(size),
+        _ => Err(GraymatError::InvalidHeader(format!("{} exceeds {} values", description, MAX_LAYER_PARAMETERS)))
+    };
+}
+
+/// Element-wise activation function
+///
+/// A [`ActivationFunction::PRELU`] layer learns one slope for negative inputs, shared by every
+/// unit. The slope is a trainable parameter and [`ActivationLayer::activation_function`] reports
+/// its current value.
+pub struct ActivationLayer {
+    activation_function: ActivationFunction,
+    slope: Array2<f32>,
+    slope_gradient: Array2<f32>,
+    input: Array2<f32>,
+    output: Array2<f32>,
+    /// Derivative of the last training pass, kept apart from the cached input and output so
+    /// back propagating twice gives the same gradient
+    derivative: Array2<f32>
+}
+
+impl ActivationLayer {
+    /// Constructor
+    ///
+    /// * `function` - Activation function type
+    pub fn new(function: ActivationFunction) -> Self {
+        let mut instance = ActivationLayer {
+            activation_function: function.clone(),
+            slope: Array2::zeros((1, 1)),
+            slope_gradient: Array2::zeros((1, 1)),
+            input: Array2::zeros((0, 0)),
+            output: Array2::zeros((0, 0)),
+            derivative: Array2::zeros((0, 0))
+        };
+        instance.set_activation_function(function);
+        return instance;
+    }
+
+    /// Set the activation function
+    ///
+    /// * `function` - Activation function type
+    pub fn set_activation_function(&mut self, function: ActivationFunction) {
+        if let ActivationFunction::PRELU(slope) = function {
+            self.slope.fill(slope);
+        }
+        self.activation_function = function;
+    }
+
+    ///
+    /// Get activation function
+    ///
+    pub fn activation_function(&self) -> ActivationFunction {
+        return match &self.activation_function {
+            ActivationFunction::PRELU(_) => ActivationFunction::PRELU(self.slope[[0, 0]]),
+            function => function.clone()
+        };
+    }
+
+    /// Apply the activation function in place
+    ///
+    /// * `x` - array2 to process
+    pub(crate) fn evaluate_inplace(&self, x: &mut Array2<f32>) {
+        self.activation_function().apply_inplace(x);
+    }
+
+    /// Apply the activation function in place, caching its output, and its input when the
+    /// derivative cannot be computed from the output
+    ///
+    /// * `x` - array2 to process
+    pub(crate) fn train_inplace(&mut self, x: &mut Array2<f32>) {
+        if !self.activation_function.derivative_uses_output() {
+            array2_utils::assign_to_buffer(&mut self.input, x);
+        }
+        self.evaluate_inplace(x);
+        array2_utils::assign_to_buffer(&mut self.output, x);
+    }
+
+    /// Error with respect to the activation input, computed in place
+    ///
+    /// * `error` - Cost gradient with respect to the activation output. Overwritten with the
+    ///             gradient with respect to its input.
+    pub(crate) fn backward_inplace(&mut self, error: &mut Array2<f32>) {
+        let function = self.activation_function();
+        match function {
+            ActivationFunction::SOFTMAX => {
+                array2_utils::math::softmax_backward_inplace(&self.output, error);
+                return;
+            }
+            ActivationFunction::PRELU(_) => {
+                let mut slope_gradient = 0.0;
+                Zip::from(&*error).and(&self.input).for_each(|&e, &x| if x <= 0.0 { slope_gradient += e * x });
+                self.slope_gradient.fill(slope_gradient);
+            }
+            _ => {}
+        }
+        array2_utils::assign_to_buffer(&mut self.derivative, &self.output);
+        if !function.derivative_from_output_inplace(&mut self.derivative) {
+            array2_utils::assign_to_buffer(&mut self.derivative, &self.input);
+            function.derivative_inplace(&mut self.derivative);
+        }
+        *error *= &self.derivative;
+    }
+
+    /// Restore an activation layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut reader = bytes;
+        let id: u8 = bincode::deserialize_from(&mut reader)?;
+        return Ok(ActivationLayer::new(ActivationFunction::from_bytes(id, &mut reader)?));
+    }
+
+    /// Serialize the activation function as its id followed by its parameters
+    pub(crate) fn activation_to_bytes(&self) -> Vec<u8> {
+        let function = self.activation_function();
+        let mut bytes = bincode::serialize(&function.id()).unwrap();
+        bytes.extend(function.parameters_to_bytes());
+        return bytes;
+    }
+}
+
+impl Layer for ActivationLayer {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        let mut output = input.clone();
+        self.evaluate_inplace(&mut output);
+        return output;
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        let mut output = input.clone();
+        self.train_inplace(&mut output);
+        return output;
+    }
+
+    fn forward_train_into(&mut self, input: &Array2<f32>, output: &mut Array2<f32>, _rng: &mut dyn RngCore) {
+        array2_utils::assign_to_buffer(output, input);
+        self.train_inplace(output);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let mut error = output_gradient.clone();
+        self.backward_inplace(&mut error);
+        return error;
+    }
+
+    fn backward_into(&mut self, output_gradient: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        array2_utils::assign_to_buffer(input_gradient, output_gradient);
+        self.backward_inplace(input_gradient);
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return match self.activation_function {
+            ActivationFunction::PRELU(_) => vec![&self.slope],
+            _ => Vec::new()
+        };
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return match self.activation_function {
+            ActivationFunction::PRELU(_) => vec![&self.slope_gradient],
+            _ => Vec::new()
+        };
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        return match self.activation_function {
+            ActivationFunction::PRELU(_) => vec![
+                Parameter { value: &mut self.slope, gradient: &mut self.slope_gradient, regularize: false, rows: None }
+            ],
+            _ => Vec::new()
+        };
+    }
+
+    fn output_activation(&self) -> Option<ActivationFunction> {
+        return Some(self.activation_function());
+    }
+
+    fn backward_pre_activation(&mut self, delta: &Array2<f32>) -> Array2<f32> {
+        return delta.clone();
+    }
+
+    fn backward_pre_activation_into(&mut self, delta: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        array2_utils::assign_to_buffer(input_gradient, delta);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return ACTIVATION;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return self.activation_to_bytes();
+    }
+}
+
+/// Inverted dropout. While training each input is zeroed with probability `rate` and the
+/// remaining inputs are scaled by `1 / (1 - rate)`, so evaluation needs no rescaling and stays
+/// deterministic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dropout {
+    rate: f32,
+    #[serde(skip)]
+    mask: Option<Array2<f32>>
+}
+
+impl Dropout {
+    /// Constructor
+    ///
+    /// * `rate` - Probability of dropping an input, in [0, 1)
+    pub fn new(rate: f32) -> Self {
+        assert!((0.0..1.0).contains(&rate), "Dropout rate must be in [0, 1), got {}", rate);
+        return Dropout { rate, mask: None };
+    }
+
+    ///
+    /// Get dropout rate
+    ///
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Drop inputs in place and remember the mask for back propagation
+    ///
+    /// * `x` - array2 to process
+    /// * `rng` - Random number generator
+    pub(crate) fn train_inplace(&mut self, x: &mut Array2<f32>, rng: &mut dyn RngCore) {
+        if self.rate == 0.0 {
+            self.mask = None;
+            return;
+        }
+        let scale = 1.0 / (1.0 - self.rate);
+        let rate = self.rate;
+        let mask = self.mask.get_or_insert_with(|| Array2::zeros(x.dim()));
+        array2_utils::fit_buffer(mask, x.dim());
+        Zip::from(mask).and(x).for_each(|m, v| {
+            *m = if rng.gen::<f32>() < rate { 0.0 } else { scale };
+            *v *= *m;
+        });
+    }
+
+    /// Apply the mask of the last training pass to a gradient in place
+    ///
+    /// * `error` - Cost gradient with respect to the dropout output
+    pub(crate) fn backward_inplace(&self, error: &mut Array2<f32>) {
+        if let Some(mask) = &self.mask {
+            Zip::from(error).and(mask).for_each(|e, &m| *e *= m);
+        }
+    }
+
+    /// Restore a dropout layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let dropout: Dropout = bincode::deserialize(bytes)?;
+        if !(0.0..1.0).contains(&dropout.rate) {
+            return Err(GraymatError::InvalidHeader(format!("dropout rate {} is outside [0, 1)", dropout.rate)));
+        }
+        return Ok(dropout);
+    }
+}
+
+impl Layer for Dropout {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return input.clone();
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, rng: &mut dyn RngCore) -> Array2<f32> {
+        let mut output = input.clone();
+        self.train_inplace(&mut output, rng);
+        return output;
+    }
+
+    fn forward_train_into(&mut self, input: &Array2<f32>, output: &mut Array2<f32>, rng: &mut dyn RngCore) {
+        array2_utils::assign_to_buffer(output, input);
+        self.train_inplace(output, rng);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let mut error = output_gradient.clone();
+        self.backward_inplace(&mut error);
+        return error;
+    }
+
+    fn backward_into(&mut self, output_gradient: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        array2_utils::assign_to_buffer(input_gradient, output_gradient);
+        self.backward_inplace(input_gradient);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return DROPOUT;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
@@ -0,0 +1,239 @@
+use std::f32::consts::PI;
+use crate::callback::Monitor;
+use crate::training_history::TrainingHistory;
+
+/// A learning rate schedule decides the learning rate of every epoch. The schedule scales the
+/// learning rate passed to [`crate::neural_network::NeuralNetwork::train`], so it can be used
+/// with any optimizer.
+///
+/// Schedules are set with [`crate::neural_network::NeuralNetwork::set_learning_rate_schedule`]
+/// and the learning rate of every epoch is recorded in the training history.
+pub trait LearningRateSchedule {
+    /// Learning rate for an epoch
+    ///
+    /// * `epoch` - Epoch number, starting at 1
+    /// * `base_learning_rate` - Learning rate passed to `train`
+    /// * `history` - Epochs completed so far in this training run
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, history: &TrainingHistory) -> f32;
+}
+
+/// Fixed learning rate. This is the default schedule.
+#[derive(Debug, Clone, Default)]
+pub struct ConstantLearningRate {}
+
+impl ConstantLearningRate {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl LearningRateSchedule for ConstantLearningRate {
+    fn learning_rate(&mut self, _epoch: usize, base_learning_rate: f32, _history: &TrainingHistory) -> f32 {
+        return base_learning_rate;
+    }
+}
+
+/// Multiply the learning rate by `gamma` every `step_size` epochs
+#[derive(Debug, Clone)]
+pub struct StepDecay {
+    step_size: usize,
+    gamma: f32
+}
+
+impl StepDecay {
+    /// * `step_size` - Number of epochs between decays
+    /// * `gamma` - Decay factor
+    pub fn new(step_size: usize, gamma: f32) -> Self {
+        return Self { step_size: step_size.max(1), gamma };
+    }
+}
+
+impl LearningRateSchedule for StepDecay {
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, _history: &TrainingHistory) -> f32 {
+        let steps = (epoch.saturating_sub(1) / self.step_size) as i32;
+        return base_learning_rate * self.gamma.powi(steps);
+    }
+}
+
+/// Multiply the learning rate by `gamma` every epoch
+#[derive(Debug, Clone)]
+pub struct ExponentialDecay {
+    gamma: f32
+}
+
+impl ExponentialDecay {
+    /// * `gamma` - Decay factor
+    pub fn new(gamma: f32) -> Self {
+        return Self { gamma };
+    }
+}
+
+impl LearningRateSchedule for ExponentialDecay {
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, _history: &TrainingHistory) -> f32 {
+        return base_learning_rate * self.gamma.powi(epoch.saturating_sub(1) as i32);
+    }
+}
+
+/// Cosine annealing with warm restarts (SGDR). The learning rate follows half a cosine from the
+/// base learning rate down to `min_learning_rate` over a cycle, then restarts. Every cycle is
+/// `period_multiplier` times longer than the previous one.
+#[derive(Debug, Clone)]
+pub struct CosineAnnealingWarmRestarts {
+    period: usize,
+    period_multiplier: usize,
+    min_learning_rate: f32
+}
+
+impl CosineAnnealingWarmRestarts {
+    /// * `period` - Length of the first cycle in epochs
+    /// * `period_multiplier` - Growth factor of the cycle length after every restart
+    /// * `min_learning_rate` - Learning rate at the end of a cycle
+    pub fn new(period: usize, period_multiplier: usize, min_learning_rate: f32) -> Self {
+        return Self { period: period.max(1), period_multiplier: period_multiplier.max(1), min_learning_rate };
+    }
+}
+
+impl LearningRateSchedule for CosineAnnealingWarmRestarts {
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, _history: &TrainingHistory) -> f32 {
+        let mut position = epoch.saturating_sub(1);
+        let mut period = self.period;
+        while position >= period {
+            position -= period;
+            period *= self.period_multiplier;
+        }
+        let cosine = (1.0 + (PI * position as f32 / period as f32).cos()) / 2.0;
+        return self.min_learning_rate + (base_learning_rate - self.min_learning_rate) * cosine;
+    }
+}
+
+/// Increase the learning rate linearly over the first epochs, then follow another schedule.
+/// Warmup avoids large, destabilizing updates while the network is far from a minimum.
+pub struct LinearWarmup {
+    warmup_epochs: usize,
+    schedule: Box<dyn LearningRateSchedule>
+}
+
+impl LinearWarmup {
+    /// * `warmup_epochs` - Number of warmup epochs. Epoch `i` of the warmup uses
+    ///                     `i / warmup_epochs` times the base learning rate.
+    /// * `schedule` - Schedule used after the warmup, for example [`ConstantLearningRate`]. Its
+    ///                epochs are counted from the end of the warmup.
+    pub fn new(warmup_epochs: usize, schedule: Box<dyn LearningRateSchedule>) -> Self {
+        return Self { warmup_epochs, schedule };
+    }
+}
+
+impl LearningRateSchedule for LinearWarmup {
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, history: &TrainingHistory) -> f32 {
+        if epoch <= self.warmup_epochs {
+            return base_learning_rate * epoch as f32 / self.warmup_epochs as f32;
+        }
+        return self.schedule.learning_rate(epoch - self.warmup_epochs, base_learning_rate, history);
+    }
+}
+
+/// One-cycle policy. The learning rate rises from `base / div_factor` to the base learning rate
+/// over the first `pct_start` of training, then anneals to
+/// `base / (div_factor * final_div_factor)`, both along a cosine.
+#[derive(Debug, Clone)]
+pub struct OneCycle {
+    total_epochs: usize,
+    pct_start: f32,
+    div_factor: f32,
+    final_div_factor: f32
+}
+
+impl OneCycle {
+    /// * `total_epochs` - Length of the cycle, normally the number of training epochs
+    /// * `pct_start` - Fraction of the cycle spent increasing the learning rate
+    /// * `div_factor` - Initial learning rate is the base learning rate divided by this factor
+    /// * `final_div_factor` - Final learning rate is the initial learning rate divided by this
+    ///                        factor
+    pub fn new(total_epochs: usize, pct_start: f32, div_factor: f32, final_div_factor: f32) -> Self {
+        return Self { total_epochs: total_epochs.max(1), pct_start, div_factor, final_div_factor };
+    }
+}
+
+impl Default for OneCycle {
+    fn default() -> Self {
+        return OneCycle::new(100, 0.3, 25.0, 1e4);
+    }
+}
+
+/// Cosine interpolation from `start` to `end`, `fraction` in [0, 1]
+fn cosine_interpolate(start: f32, end: f32, fraction: f32) -> f32 {
+    return end + (start - end) * (1.0 + (PI * fraction.clamp(0.0, 1.0)).cos()) / 2.0;
+}
+
+impl LearningRateSchedule for OneCycle {
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, _history: &TrainingHistory) -> f32 {
+        let initial = base_learning_rate / self.div_factor;
+        let last = initial / self.final_div_factor;
+        let position = epoch.saturating_sub(1) as f32;
+        let last_position = (self.total_epochs - 1) as f32;
+        let rising_epochs = (self.pct_start * last_position).round().max(1.0);
+        let falling_epochs = (last_position - rising_epochs).max(1.0);
+        if position < rising_epochs {
+            return cosine_interpolate(initial, base_learning_rate, position / rising_epochs);
+        }
+        return cosine_interpolate(base_learning_rate, last, (position - rising_epochs) / falling_epochs);
+    }
+}
+
+/// Multiply the learning rate by `factor` whenever the monitored value has not improved for
+/// `patience` epochs
+#[derive(Debug, Clone)]
+pub struct ReduceOnPlateau {
+    monitor: Monitor,
+    factor: f32,
+    patience: usize,
+    min_delta: f32,
+    min_learning_rate: f32,
+    scale: f32,
+    best: Option<f32>,
+    epochs_without_improvement: usize
+}
+
+impl ReduceOnPlateau {
+    /// * `monitor` - Monitored value
+    /// * `factor` - Factor the learning rate is multiplied by on a plateau
+    /// * `patience` - Number of epochs without improvement before the learning rate is reduced
+    /// * `min_delta` - Minimum change that counts as an improvement
+    /// * `min_learning_rate` - Lower bound of the learning rate
+    pub fn new(monitor: Monitor, factor: f32, patience: usize, min_delta: f32, min_learning_rate: f32) -> Self {
+        return Self {
+            monitor,
+            factor,
+            patience,
+            min_delta,
+            min_learning_rate,
+            scale: 1.0,
+            best: None,
+            epochs_without_improvement: 0
+        };
+    }
+}
+
+impl LearningRateSchedule for ReduceOnPlateau {
+    fn learning_rate(&mut self, epoch: usize, base_learning_rate: f32, history: &TrainingHistory) -> f32 {
+        if epoch == 1 {
+            self.scale = 1.0;
+            self.best = None;
+            self.epochs_without_improvement = 0;
+        }
+        let current = history.epochs().last().and_then(|record| self.monitor.value(record));
+        if let Some(current) = current {
+            if self.monitor.is_improvement(current, self.best, self.min_delta) {
+                self.best = Some(current);
+                self.epochs_without_improvement = 0;
+            } else {
+                self.epochs_without_improvement += 1;
+                if self.epochs_without_improvement >= self.patience {
+                    self.scale *= self.factor;
+                    self.epochs_without_improvement = 0;
+                }
+            }
+        }
+        return (base_learning_rate * self.scale).max(self.min_learning_rate);
+    }
+}
@@ -5,3 +5,19 @@ pub mod examples;
 pub mod utilities;
 pub mod neural_network_io;
 pub mod activation_function;
+pub mod initializer;
+pub mod optimizer;
+pub mod loss;
+pub mod metrics;
+pub mod training_history;
+pub mod callback;
+pub mod learning_rate_schedule;
+pub mod regularization;
+pub mod normalization;
+pub mod layer;
+pub mod convolution;
+pub mod recurrent;
+pub mod attention;
+pub mod embedding;
+pub mod sequential;
+pub mod error;
@@ -0,0 +1,418 @@
+use std::collections::BTreeMap;
+use std::sync::RwLock;
+use ndarray::{Array2, Zip};
+use serde::{Serialize, Deserialize};
+use crate::error::{GraymatError, Result};
+use crate::loss::LossType::{BINARY_CROSS_ENTROPY, CATEGORICAL_CROSS_ENTROPY, CUSTOM, HINGE, HUBER, MAE, MSE, QUADRATIC};
+use crate::utilities::array2_utils;
+
+const CROSS_ENTROPY_EPSILON: f32 = 1e-7;
+
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LossType {
+    QUADRATIC = 1,
+    CATEGORICAL_CROSS_ENTROPY = 2,
+    MSE = 3,
+    MAE = 4,
+    HUBER = 5,
+    BINARY_CROSS_ENTROPY = 6,
+    HINGE = 7,
+    CUSTOM = 255,
+}
+
+impl LossType {
+    /// Get loss type from u8
+    ///
+    /// * `val` - u8 value
+    /// * `returns` - Option some if val is matched else None
+    pub fn from_u8(val: u8) -> Option<Self> {
+        let losses = [ QUADRATIC, CATEGORICAL_CROSS_ENTROPY, MSE, MAE, HUBER, BINARY_CROSS_ENTROPY, HINGE, CUSTOM ];
+        return losses.into_iter().find(|l| (*l as u8) == val);
+    }
+
+    /// Get the loss type as a string
+    ///
+    /// * `loss` - loss type
+    pub fn convert_to_string(loss: LossType) -> String {
+        return match loss {
+            QUADRATIC => "Quadratic".to_owned(),
+            CATEGORICAL_CROSS_ENTROPY => "Categorical Cross-Entropy".to_owned(),
+            MSE => "Mean Squared Error".to_owned(),
+            MAE => "Mean Absolute Error".to_owned(),
+            HUBER => "Huber".to_owned(),
+            BINARY_CROSS_ENTROPY => "Binary Cross-Entropy".to_owned(),
+            HINGE => "Hinge".to_owned(),
+            CUSTOM => "Custom".to_owned()
+        };
+    }
+}
+
+/// A loss (cost) function compares network outputs with targets.
+///
+/// Arrays hold one example per column. `value` is the loss averaged over the examples and
+/// `gradient` is the derivative of each example's loss with respect to the network output.
+pub trait Loss {
+    /// Loss value averaged over all examples
+    ///
+    /// * `result` - network output result
+    /// * `target` - target network result
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32;
+
+    /// First derivative of the loss with respect to the network output
+    ///
+    /// * `result` - network output result
+    /// * `target` - target network result
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32>;
+
+    /// [`Loss::gradient`] into a buffer owned by the network, which is reused between training
+    /// steps. The buffer is resized if needed. By default it is replaced with the result of
+    /// `gradient`.
+    ///
+    /// * `result` - network output result
+    /// * `target` - target network result
+    /// * `gradient` - Buffer for the derivative
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        *gradient = self.gradient(result, target);
+    }
+
+    /// Loss type, used to restore the loss from a .gnm file. Custom losses return
+    /// [`LossType::CUSTOM`] and are saved by [`Loss::name`].
+    fn loss_type(&self) -> LossType;
+
+    /// Stable name of a custom loss, used to save it and to find its constructor when loading.
+    /// To load a network that uses a custom loss, register a constructor for the name with
+    /// [`register_loss`] first.
+    fn name(&self) -> String {
+        return LossType::convert_to_string(self.loss_type());
+    }
+
+    /// Serialize the loss parameters
+    fn to_bytes(&self) -> Vec<u8> {
+        return Vec::new();
+    }
+}
+
+/// Builds a custom loss from the parameters written by its [`Loss::to_bytes`]
+pub type LossConstructor = fn(&[u8]) -> Result<Box<dyn Loss>>;
+
+/// Constructors of custom losses by name
+static CUSTOM_LOSSES: RwLock<BTreeMap<String, LossConstructor>> = RwLock::new(BTreeMap::new());
+
+/// Register a custom loss so networks that use it can be loaded. Registering a name again
+/// replaces its constructor.
+///
+/// * `name` - Name returned by [`Loss::name`]
+/// * `constructor` - Builds the loss from its saved parameters
+pub fn register_loss(name: &str, constructor: LossConstructor) {
+    CUSTOM_LOSSES.write().unwrap().insert(name.to_owned(), constructor);
+}
+
+/// Serialize a loss for [`loss_from_bytes`]. Custom losses are written as their name followed by
+/// their parameters.
+///
+/// * `loss` - Loss
+pub fn loss_to_bytes(loss: &dyn Loss) -> Vec<u8> {
+    if loss.loss_type() == CUSTOM {
+        return bincode::serialize(&(loss.name(), loss.to_bytes())).unwrap();
+    }
+    return loss.to_bytes();
+}
+
+/// Restore a loss that was serialized with [`loss_to_bytes`]. Custom losses are built by the
+/// constructor registered for their name.
+///
+/// * `loss_type` - Loss type
+/// * `bytes` - Serialized loss
+pub fn loss_from_bytes(loss_type: LossType, bytes: &[u8]) -> Result<Box<dyn Loss>> {
+    return Ok(match loss_type {
+        CUSTOM => {
+            let (name, parameters): (String, Vec<u8>) = bincode::deserialize(bytes)?;
+            let constructor = *CUSTOM_LOSSES.read().unwrap().get(&name)
+                .ok_or(GraymatError::UnknownLossName(name))?;
+            constructor(&parameters)?
+        }
+        QUADRATIC => Box::new(Quadratic::new()),
+        CATEGORICAL_CROSS_ENTROPY => Box::new(CategoricalCrossEntropy::new()),
+        MSE => Box::new(MeanSquaredError::new()),
+        MAE => Box::new(MeanAbsoluteError::new()),
+        HUBER => Box::new(bincode::deserialize::<Huber>(bytes)?),
+        BINARY_CROSS_ENTROPY => Box::new(BinaryCrossEntropy::new()),
+        HINGE => Box::new(Hinge::new())
+    });
+}
+
+/// Number of examples (columns) in a result array
+fn number_of_examples(result: &Array2<f32>) -> f32 {
+    return result.ncols().max(1) as f32;
+}
+
+/// Number of outputs (rows) per example in a result array
+fn number_of_outputs(result: &Array2<f32>) -> f32 {
+    return result.nrows().max(1) as f32;
+}
+
+/// Write an element-wise derivative of the result and target into a gradient buffer
+///
+/// * `gradient` - Buffer, resized to the shape of `result` if needed
+/// * `derivative` - Derivative of one element given (result, target)
+fn map_gradient_into(result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>, derivative: impl Fn(f32, f32) -> f32) {
+    array2_utils::fit_buffer(gradient, result.dim());
+    Zip::from(gradient).and(result).and(target).for_each(|g, &a, &y| *g = derivative(a, y));
+}
+
+/// [`Loss::gradient`] of a built-in loss, computed with its [`Loss::gradient_into`]
+fn owned_gradient(loss: &dyn Loss, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+    let mut gradient = Array2::zeros(result.dim());
+    loss.gradient_into(result, target, &mut gradient);
+    return gradient;
+}
+
+/// Quadratic cost, `0.5 * sum((result - target)^2)`. This is the default network cost.
+#[derive(Debug, Clone, Default)]
+pub struct Quadratic {}
+
+impl Quadratic {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Loss for Quadratic {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        return 0.5 * (result - target).mapv(|x| x * x).sum() / number_of_examples(result);
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        map_gradient_into(result, target, gradient, |a, y| a - y);
+    }
+
+    fn loss_type(&self) -> LossType {
+        QUADRATIC
+    }
+}
+
+/// Mean squared error, `mean((result - target)^2)`
+#[derive(Debug, Clone, Default)]
+pub struct MeanSquaredError {}
+
+impl MeanSquaredError {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Loss for MeanSquaredError {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        return (result - target).mapv(|x| x * x).sum() / (number_of_outputs(result) * number_of_examples(result));
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        let scale = 2.0 / number_of_outputs(result);
+        map_gradient_into(result, target, gradient, |a, y| (a - y) * scale);
+    }
+
+    fn loss_type(&self) -> LossType {
+        MSE
+    }
+}
+
+/// Mean absolute error, `mean(|result - target|)`
+///
+/// Note: The derivative at `result == target` is taken to be 0.
+#[derive(Debug, Clone, Default)]
+pub struct MeanAbsoluteError {}
+
+impl MeanAbsoluteError {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Loss for MeanAbsoluteError {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        return (result - target).mapv(f32::abs).sum() / (number_of_outputs(result) * number_of_examples(result));
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        let outputs = number_of_outputs(result);
+        map_gradient_into(result, target, gradient, |a, y| {
+            let x = a - y;
+            if x == 0.0 { 0.0 } else { x.signum() / outputs }
+        });
+    }
+
+    fn loss_type(&self) -> LossType {
+        MAE
+    }
+}
+
+/// Huber loss. Quadratic for errors smaller than `delta` and linear beyond, which makes it
+/// less sensitive to outliers than mean squared error.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Huber {
+    delta: f32
+}
+
+impl Huber {
+    /// * `delta` - Error magnitude at which the loss changes from quadratic to linear
+    pub fn new(delta: f32) -> Self {
+        return Self { delta };
+    }
+}
+
+impl Default for Huber {
+    fn default() -> Self {
+        return Huber::new(1.0);
+    }
+}
+
+impl Loss for Huber {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        let delta = self.delta;
+        let total: f32 = (result - target).mapv(|x| {
+            if x.abs() <= delta { 0.5 * x * x } else { delta * (x.abs() - 0.5 * delta) }
+        }).sum();
+        return total / (number_of_outputs(result) * number_of_examples(result));
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        let delta = self.delta;
+        let outputs = number_of_outputs(result);
+        map_gradient_into(result, target, gradient, |a, y| (a - y).clamp(-delta, delta) / outputs);
+    }
+
+    fn loss_type(&self) -> LossType {
+        HUBER
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Binary cross-entropy, `-mean(target * ln(result) + (1 - target) * ln(1 - result))`
+///
+/// Expects outputs in (0, 1), for example from a sigmoid output layer, and targets of 0 or 1.
+#[derive(Debug, Clone, Default)]
+pub struct BinaryCrossEntropy {}
+
+impl BinaryCrossEntropy {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Loss for BinaryCrossEntropy {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        let mut total = 0.0;
+        Zip::from(result).and(target).for_each(|&a, &y| {
+            let a = a.clamp(CROSS_ENTROPY_EPSILON, 1.0 - CROSS_ENTROPY_EPSILON);
+            total -= y * a.ln() + (1.0 - y) * (1.0 - a).ln();
+        });
+        return total / (number_of_outputs(result) * number_of_examples(result));
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        let outputs = number_of_outputs(result);
+        map_gradient_into(result, target, gradient, |a, y| {
+            let a = a.clamp(CROSS_ENTROPY_EPSILON, 1.0 - CROSS_ENTROPY_EPSILON);
+            (a - y) / (a * (1.0 - a) * outputs)
+        });
+    }
+
+    fn loss_type(&self) -> LossType {
+        BINARY_CROSS_ENTROPY
+    }
+}
+
+/// Categorical cross-entropy, `-sum(target * ln(result))`
+///
+/// Expects class probabilities and one-hot targets. When the output layer uses softmax the
+/// network skips [`Loss::gradient`] and uses the fused gradient `result - target`, which is exact
+/// and numerically stable.
+#[derive(Debug, Clone, Default)]
+pub struct CategoricalCrossEntropy {}
+
+impl CategoricalCrossEntropy {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Loss for CategoricalCrossEntropy {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        let mut total = 0.0;
+        Zip::from(result).and(target).for_each(|&a, &y| {
+            total -= y * a.max(CROSS_ENTROPY_EPSILON).ln();
+        });
+        return total / number_of_examples(result);
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        map_gradient_into(result, target, gradient, |a, y| -y / a.max(CROSS_ENTROPY_EPSILON));
+    }
+
+    fn loss_type(&self) -> LossType {
+        CATEGORICAL_CROSS_ENTROPY
+    }
+}
+
+/// Hinge loss, `mean(max(0, 1 - target * result))`
+///
+/// Expects targets of -1 or 1, typically with a linear or tanh output layer.
+#[derive(Debug, Clone, Default)]
+pub struct Hinge {}
+
+impl Hinge {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Loss for Hinge {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        let mut total = 0.0;
+        Zip::from(result).and(target).for_each(|&a, &y| {
+            total += (1.0 - y * a).max(0.0);
+        });
+        return total / (number_of_outputs(result) * number_of_examples(result));
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        return owned_gradient(self, result, target);
+    }
+
+    fn gradient_into(&self, result: &Array2<f32>, target: &Array2<f32>, gradient: &mut Array2<f32>) {
+        let outputs = number_of_outputs(result);
+        map_gradient_into(result, target, gradient, |a, y| if 1.0 - y * a > 0.0 { -y / outputs } else { 0.0 });
+    }
+
+    fn loss_type(&self) -> LossType {
+        HINGE
+    }
+}
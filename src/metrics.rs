@@ -0,0 +1,163 @@
+use ndarray::{Array2, Axis};
+use crate::metrics::Metric::{ACCURACY, F1, MAE, PRECISION, R2, RECALL};
+
+/// Threshold above which a single output is considered the positive class
+const BINARY_THRESHOLD: f32 = 0.5;
+
+/// Metrics reported per epoch in a [`crate::training_history::TrainingHistory`]
+///
+/// Networks with one output are treated as binary classifiers with a 0.5 threshold. Networks
+/// with several outputs are treated as one-hot classifiers, predicting the largest output, and
+/// precision, recall and F1 are macro averaged over the classes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Metric {
+    ACCURACY = 1,
+    PRECISION = 2,
+    RECALL = 3,
+    F1 = 4,
+    MAE = 5,
+    R2 = 6,
+}
+
+impl Metric {
+    /// Get metric from u8
+    ///
+    /// * `val` - u8 value
+    /// * `returns` - Option some if val is matched else None
+    pub fn from_u8(val: u8) -> Option<Self> {
+        let metrics = [ ACCURACY, PRECISION, RECALL, F1, MAE, R2 ];
+        return metrics.into_iter().find(|m| (*m as u8) == val);
+    }
+
+    /// Get the metric as a string, used as the column name when exporting a training history
+    ///
+    /// * `metric` - metric
+    pub fn convert_to_string(metric: Metric) -> String {
+        return match metric {
+            ACCURACY => "accuracy".to_owned(),
+            PRECISION => "precision".to_owned(),
+            RECALL => "recall".to_owned(),
+            F1 => "f1".to_owned(),
+            MAE => "mae".to_owned(),
+            R2 => "r2".to_owned()
+        };
+    }
+
+    /// Calculate the metric
+    ///
+    /// * `result` - Network outputs, one example per column
+    /// * `target` - Targets, one example per column
+    pub fn calculate(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        return match self {
+            ACCURACY => accuracy(result, target),
+            PRECISION => ConfusionCounts::new(result, target).precision(),
+            RECALL => ConfusionCounts::new(result, target).recall(),
+            F1 => ConfusionCounts::new(result, target).f1(),
+            MAE => (result - target).mapv(f32::abs).mean().unwrap_or(0.0),
+            R2 => r_squared(result, target)
+        };
+    }
+}
+
+/// Predicted class of every example
+fn classes(arr: &Array2<f32>) -> Vec<usize> {
+    if arr.nrows() == 1 {
+        return arr.iter().map(|x| (*x >= BINARY_THRESHOLD) as usize).collect();
+    }
+    return arr.columns().into_iter().map(|column| {
+        let mut index = 0;
+        for (i, x) in column.iter().enumerate() {
+            if *x > column[index] {
+                index = i;
+            }
+        }
+        index
+    }).collect();
+}
+
+/// Fraction of examples whose predicted class matches the target class
+fn accuracy(result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+    let predicted = classes(result);
+    if predicted.is_empty() {
+        return 0.0;
+    }
+    let correct = predicted.iter().zip(classes(target).iter()).filter(|(p, t)| p == t).count();
+    return correct as f32 / predicted.len() as f32;
+}
+
+/// Coefficient of determination, `1 - SS_res / SS_tot`, where `SS_tot` is taken around the mean
+/// target of each output
+fn r_squared(result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+    let ss_res = (result - target).mapv(|x| x * x).sum();
+    let ss_tot = match target.mean_axis(Axis(1)) {
+        Some(mean) => (target - &mean.insert_axis(Axis(1))).mapv(|x| x * x).sum(),
+        None => 0.0
+    };
+    if ss_tot == 0.0 {
+        return if ss_res == 0.0 { 1.0 } else { 0.0 };
+    }
+    return 1.0 - ss_res / ss_tot;
+}
+
+/// Per-class true positive, false positive and false negative counts
+struct ConfusionCounts {
+    true_positives: Vec<usize>,
+    false_positives: Vec<usize>,
+    false_negatives: Vec<usize>
+}
+
+impl ConfusionCounts {
+    fn new(result: &Array2<f32>, target: &Array2<f32>) -> Self {
+        // A single output network only reports on the positive class
+        let number_of_classes = result.nrows().max(2);
+        let mut counts = ConfusionCounts {
+            true_positives: vec![0; number_of_classes],
+            false_positives: vec![0; number_of_classes],
+            false_negatives: vec![0; number_of_classes]
+        };
+        for (predicted, actual) in classes(result).into_iter().zip(classes(target)) {
+            if predicted == actual {
+                counts.true_positives[predicted] += 1;
+            } else {
+                counts.false_positives[predicted] += 1;
+                counts.false_negatives[actual] += 1;
+            }
+        }
+        if result.nrows() == 1 {
+            counts.true_positives.remove(0);
+            counts.false_positives.remove(0);
+            counts.false_negatives.remove(0);
+        }
+        return counts;
+    }
+
+    /// Macro averaged precision
+    fn precision(&self) -> f32 {
+        return self.macro_average(|c| ratio(self.true_positives[c], self.true_positives[c] + self.false_positives[c]));
+    }
+
+    /// Macro averaged recall
+    fn recall(&self) -> f32 {
+        return self.macro_average(|c| ratio(self.true_positives[c], self.true_positives[c] + self.false_negatives[c]));
+    }
+
+    /// Macro averaged F1 score
+    fn f1(&self) -> f32 {
+        return self.macro_average(|c| {
+            ratio(2 * self.true_positives[c], 2 * self.true_positives[c] + self.false_positives[c] + self.false_negatives[c])
+        });
+    }
+
+    fn macro_average(&self, per_class: impl Fn(usize) -> f32) -> f32 {
+        let number_of_classes = self.true_positives.len();
+        return (0..number_of_classes).map(per_class).sum::<f32>() / number_of_classes as f32;
+    }
+}
+
+/// `numerator / denominator`, or 0 if the denominator is 0
+fn ratio(numerator: usize, denominator: usize) -> f32 {
+    if denominator == 0 {
+        return 0.0;
+    }
+    return numerator as f32 / denominator as f32;
+}
@@ -1,137 +1,390 @@
 use std::fmt::{Display, Formatter};
-use ndarray::{Array2};
-use std::fmt::Write;
+use ndarray::{concatenate, s, Array2, ArrayView2, Axis, Zip};
+use ndarray::linalg::general_mat_mul;
+use std::fmt::Write as FmtWrite;
+use std::io::{Read, Write};
+use std::path::Path;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
+use rand::{RngCore, SeedableRng};
 use crate::activation_function::ActivationFunction;
+use crate::callback::Callback;
 use crate::column_vector::ColumnVector;
-use crate::neural_network_io::{check_gnm_filepath, from_file, to_file};
+use crate::error::GraymatError;
+use crate::initializer::Initializer;
+use crate::layer::{check_declared_size, ActivationLayer, Dropout, Layer, LayerType, Parameter};
+use crate::learning_rate_schedule::{ConstantLearningRate, LearningRateSchedule};
+use crate::loss::{Loss, LossType, Quadratic};
+use crate::metrics::Metric;
+use crate::normalization::{Normalization, NormalizationType};
+use crate::neural_network_io::{check_gnm_filepath, from_file, read_from, to_file, write_to};
+use crate::optimizer::{Optimizer, StochasticGradientDescent};
+use crate::regularization::{GradientClipping, Regularization};
+use crate::sequential::Sequential;
+use crate::training_history::{EpochRecord, TrainingHistory};
 use crate::utilities::array2_utils;
 
 
-// Cost Expression
-pub type CE = fn(result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32>;
-// Activation Expression
-pub type AE = fn(x: &Array2<f32>) -> Array2<f32>;
-
 pub struct NeuralNetwork {
-    input_neurons: usize,
-    output_neurons: usize,
-    hidden_layer_sizes: Vec<usize>,
-    layers: Vec<NeuralNetworkLayer>,
-    cost_function: CE,
-    activation_function: ActivationFunction,
-    activation_expression: AE,
-    activation_expression_prime: AE
+    input_size: usize,
+    layers: Vec<Box<dyn Layer>>,
+    loss: Box<dyn Loss>,
+    optimizer: Box<dyn Optimizer>,
+    metrics: Vec<Metric>,
+    callbacks: Vec<Box<dyn Callback>>,
+    learning_rate_schedule: Box<dyn LearningRateSchedule>,
+    regularization: Regularization,
+    gradient_clipping: GradientClipping,
+    stop_training: bool,
+    rng: Box<dyn RngCore>,
+    workspace: Workspace
 }
 
 impl NeuralNetwork {
     /// Constructor
     ///
-    /// This constructor produces a neural network with randomly generated weights and biases
+    /// This constructor produces a neural network with Xavier uniform initialized weights and
+    /// zeroed biases (see [`NeuralNetwork::with_initializer`])
     ///
     /// * `input_neurons` - Number of input neurons
     /// * `output_neurons` - Number of output neurons
     /// * `hidden_layer_sizes` - Vector defining how many hidden layers there should be and the
     ///                          size of each hidden layer. An empty vector results in the input
     ///                          neurons being linked directly to the output neurons.
-    /// * `activation` - Activation function
-    pub fn new(input_neurons: usize, output_neurons: usize, hidden_layer_sizes: Vec<usize>, activation: ActivationFunction) -> Self {
-        let number_of_hidden_layers: usize = hidden_layer_sizes.len();
-        let mut instance = NeuralNetwork {
-            input_neurons,
-            output_neurons,
-            hidden_layer_sizes,
-            layers: Vec::with_capacity(number_of_hidden_layers + 1),
-            cost_function: NeuralNetwork::calculate_cost_default,
-            activation_function: ActivationFunction::SIGMOID,
-            activation_expression: array2_utils::math::sig,
-            activation_expression_prime: array2_utils::math::sig_prime
+    /// * `activations` - Activation function for each layer, hidden layers first and the output
+    ///                   layer last. A single activation function is applied to every layer.
+    pub fn new(input_neurons: usize,
+               output_neurons: usize,
+               hidden_layer_sizes: Vec<usize>,
+               activations: impl Into<Vec<ActivationFunction>>) -> Self
+    {
+        return Self::with_initializer(input_neurons, output_neurons, hidden_layer_sizes, activations,
+                                      Initializer::XAVIER_UNIFORM, Initializer::ZEROS);
+    }
+
+    /// Constructor
+    ///
+    /// This constructor produces a neural network with weights and biases initialized by the
+    /// given schemes
+    ///
+    /// * `input_neurons` - Number of input neurons
+    /// * `output_neurons` - Number of output neurons
+    /// * `hidden_layer_sizes` - Vector defining how many hidden layers there should be and the
+    ///                          size of each hidden layer. An empty vector results in the input
+    ///                          neurons being linked directly to the output neurons.
+    /// * `activations` - Activation function for each layer, hidden layers first and the output
+    ///                   layer last. A single activation function is applied to every layer.
+    /// * `weight_initializer` - Weight initialization scheme, for example
+    ///                          [`Initializer::HE_NORMAL`] for ReLU networks
+    /// * `bias_initializer` - Bias initialization scheme, typically [`Initializer::ZEROS`] or
+    ///                        [`Initializer::CONSTANT`]
+    pub fn with_initializer(input_neurons: usize,
+                            output_neurons: usize,
+                            hidden_layer_sizes: Vec<usize>,
+                            activations: impl Into<Vec<ActivationFunction>>,
+                            weight_initializer: Initializer,
+                            bias_initializer: Initializer) -> Self
+    {
+        return Self::with_rng(input_neurons, output_neurons, hidden_layer_sizes, activations,
+                              weight_initializer, bias_initializer, Box::new(StdRng::from_entropy()));
+    }
+
+    /// Constructor
+    ///
+    /// This constructor produces a neural network whose weights and biases are initialized from
+    /// the given random number generator. The generator is kept by the network and also used to
+    /// shuffle training data, so two networks built with identically seeded generators and
+    /// trained on the same data are bit-identical.
+    ///
+    /// ```
+    /// # use rand::SeedableRng;
+    /// # use rand::rngs::StdRng;
+    /// # use graymat::activation_function::ActivationFunction;
+    /// # use graymat::initializer::Initializer;
+    /// # use graymat::neural_network::NeuralNetwork;
+    /// let nn = NeuralNetwork::with_rng(2, 1, vec![4], ActivationFunction::TANH,
+    ///                                  Initializer::XAVIER_UNIFORM, Initializer::ZEROS,
+    ///                                  Box::new(StdRng::seed_from_u64(42)));
+    /// ```
+    ///
+    /// * `input_neurons` - Number of input neurons
+    /// * `output_neurons` - Number of output neurons
+    /// * `hidden_layer_sizes` - Size of each hidden layer
+    /// * `activations` - Activation function for each layer, or a single activation function
+    ///                   applied to every layer
+    /// * `weight_initializer` - Weight initialization scheme
+    /// * `bias_initializer` - Bias initialization scheme
+    /// * `rng` - Random number generator
+    pub fn with_rng(input_neurons: usize,
+                    output_neurons: usize,
+                    hidden_layer_sizes: Vec<usize>,
+                    activations: impl Into<Vec<ActivationFunction>>,
+                    weight_initializer: Initializer,
+                    bias_initializer: Initializer,
+                    rng: Box<dyn RngCore>) -> Self
+    {
+        let activations = Self::expand_activations(activations.into(), hidden_layer_sizes.len() + 1);
+        let mut builder = Sequential::new(input_neurons)
+            .initializer(weight_initializer, bias_initializer)
+            .rng(rng);
+        for (i, layer_size) in hidden_layer_sizes.iter().enumerate() {
+            builder = builder.dense(*layer_size, activations[i].clone());
+        }
+        return builder.dense(output_neurons, activations[activations.len() - 1].clone()).build();
+    }
+
+    /// Build a network from a stack of layers. Use [`Sequential`] to stack layers.
+    ///
+    /// * `input_size` - Number of network inputs
+    /// * `layers` - Layers, input side first
+    /// * `rng` - Random number generator
+    pub(crate) fn from_layers(input_size: usize, layers: Vec<Box<dyn Layer>>, rng: Box<dyn RngCore>) -> Self {
+        assert!(!layers.is_empty(), "A network needs at least one layer");
+        return NeuralNetwork {
+            input_size,
+            layers,
+            loss: Box::new(Quadratic::new()),
+            optimizer: Box::new(StochasticGradientDescent::new()),
+            metrics: Vec::new(),
+            callbacks: Vec::new(),
+            learning_rate_schedule: Box::new(ConstantLearningRate::new()),
+            regularization: Regularization::NONE,
+            gradient_clipping: GradientClipping::NONE,
+            stop_training: false,
+            rng,
+            workspace: Workspace::default()
         };
-        Self::set_activation_function(&mut instance, activation);
-        Self::init_network_layers(&mut instance);
-        Self::randomize_weights_and_biases(&mut instance);
-        return instance;
     }
 
     /// Return a neural network object from a known collection of weights and biases
     ///
     /// * `weights` - Neural Network weights in ascending order
     /// * `biases` - Neural Network biases in ascending order
-    /// * `activation` - Activation function
-    pub fn from(weights: Vec<Array2<f32>>, biases: Vec<Array2<f32>>, activation: ActivationFunction) -> Self {
+    /// * `activations` - Activation function for each layer in ascending order. A single
+    ///                   activation function is applied to every layer.
+    pub fn from(weights: Vec<Array2<f32>>,
+                biases: Vec<Array2<f32>>,
+                activations: impl Into<Vec<ActivationFunction>>) -> Self
+    {
         assert_eq!(weights.len(), biases.len());
-        let number_of_hidden_layers: usize = weights.len();
-        let mut instance = NeuralNetwork {
-            input_neurons: weights[0].dim().1,
-            output_neurons: weights[weights.len() - 1].dim().1,
-            hidden_layer_sizes: Vec::with_capacity(number_of_hidden_layers),
-            layers: Vec::with_capacity(number_of_hidden_layers),
-            cost_function: NeuralNetwork::calculate_cost_default,
-            activation_function: ActivationFunction::SIGMOID,
-            activation_expression: array2_utils::math::sig,
-            activation_expression_prime: array2_utils::math::sig_prime
-        };
-        Self::set_activation_function(&mut instance, activation);
-        for i in 0..instance.layers.capacity() {
-            instance.layers.push(NeuralNetworkLayer {
-                weights: weights[i].clone(),
-                biases: biases[i].clone()
-            })
+        let activations = Self::expand_activations(activations.into(), weights.len());
+        let mut layers: Vec<Box<dyn Layer>> = Vec::with_capacity(weights.len());
+        for i in 0..weights.len() {
+            let mut layer = NeuralNetworkLayer::new(weights[i].dim().1, weights[i].dim().0, activations[i].clone());
+            layer.weights = weights[i].clone();
+            layer.biases = biases[i].clone();
+            layers.push(Box::new(layer));
+        }
+        return Self::from_layers(weights[0].dim().1, layers, Box::new(StdRng::from_entropy()));
+    }
+
+    /// Expand a per-layer activation spec to one activation function per layer
+    ///
+    /// * `activations` - Either a single activation function or one per layer
+    /// * `number_of_layers` - Number of layers in the network
+    fn expand_activations(activations: Vec<ActivationFunction>, number_of_layers: usize) -> Vec<ActivationFunction> {
+        if activations.len() == 1 {
+            return vec![activations[0].clone(); number_of_layers];
         }
-        return instance;
+        assert_eq!(activations.len(), number_of_layers,
+                   "Expected one activation function per layer ({}), got {}", number_of_layers, activations.len());
+        return activations;
     }
 
-    /// Set the activation function for this network
+    /// Set the activation function for every dense layer in this network
     ///
     /// * `function` - Activation function type
     pub fn set_activation_function(&mut self, function: ActivationFunction) {
-        self.activation_function = function.to_owned();
-        match function {
-            ActivationFunction::SIGMOID => {
-                self.activation_expression = array2_utils::math::sig;
-                self.activation_expression_prime = array2_utils::math::sig_prime;
-            }
-            ActivationFunction::TANH => {
-                self.activation_expression = array2_utils::math::tanh;
-                self.activation_expression_prime = array2_utils::math::tanh_prime;
-            }
-            ActivationFunction::RELU => {
-                self.activation_expression = array2_utils::math::relu;
-                self.activation_expression_prime = array2_utils::math::relu_prime;
-            }
+        for layer in self.layers.iter_mut().filter_map(|layer| layer.downcast_mut::<NeuralNetworkLayer>()) {
+            layer.set_activation_function(function.clone());
         }
     }
 
-    /// Set the cost function for this neural network
+    /// Set the activation function of a single dense layer
+    ///
+    /// * `layer_index` - Dense layer index, 0 being the first hidden layer
+    /// * `function` - Activation function type
+    pub fn set_layer_activation_function(&mut self, layer_index: usize, function: ActivationFunction) {
+        self.dense_layer_mut(layer_index).set_activation_function(function);
+    }
+
+    /// Set the dropout rate of a single dense layer. During training each output of the layer is
+    /// zeroed with probability `rate` and the remaining outputs are scaled by `1 / (1 - rate)`
+    /// (inverted dropout), so evaluation needs no rescaling and stays deterministic.
+    ///
+    /// * `layer_index` - Dense layer index, 0 being the first hidden layer. Dropout cannot be
+    ///                   applied to the output layer.
+    /// * `rate` - Probability of dropping an output, in [0, 1)
+    /// * `returns` - An error if there is no such dense layer, it is the output layer or the
+    ///               rate is outside [0, 1)
+    pub fn set_layer_dropout(&mut self, layer_index: usize, rate: f32) -> Result<(), GraymatError> {
+        let dense_layers = self.dense_layers();
+        let layer = *dense_layers.get(layer_index).ok_or_else(|| GraymatError::InvalidArgument(
+            format!("dense layer index {} is out of range for {} dense layers", layer_index, dense_layers.len())))?;
+        let is_output_layer = self.layers.last()
+            .and_then(|output_layer| output_layer.downcast_ref::<NeuralNetworkLayer>())
+            .is_some_and(|output_layer| std::ptr::eq(output_layer, layer));
+        if is_output_layer {
+            return Err(GraymatError::InvalidArgument("dropout cannot be applied to the output layer".to_string()));
+        }
+        if !(0.0..1.0).contains(&rate) {
+            return Err(GraymatError::InvalidArgument(format!("dropout rate {} is outside [0, 1)", rate)));
+        }
+        self.dense_layer_mut(layer_index).set_dropout(rate);
+        return Ok(());
+    }
+
+    /// Set the normalization of a single dense layer's pre-activations, applied before the
+    /// activation function. The normalization's learnable scale and shift are trained with the
+    /// network.
+    ///
+    /// * `layer_index` - Dense layer index, 0 being the first hidden layer
+    /// * `normalization` - Batch or layer normalization, None to remove it
+    pub fn set_layer_normalization(&mut self, layer_index: usize, normalization: Option<Normalization>) {
+        self.dense_layer_mut(layer_index).set_normalization(normalization);
+    }
+
+    /// Get a dense layer by its index among the dense layers
+    ///
+    /// * `layer_index` - Dense layer index
+    fn dense_layer_mut(&mut self, layer_index: usize) -> &mut NeuralNetworkLayer {
+        return self.layers.iter_mut()
+            .filter_map(|layer| layer.downcast_mut::<NeuralNetworkLayer>())
+            .nth(layer_index)
+            .expect("Dense layer index out of range");
+    }
+
+    /// Set the optimizer used to apply gradients during training. Networks use plain
+    /// stochastic gradient descent unless configured otherwise.
+    ///
+    /// * `optimizer` - Optimizer
+    pub fn set_optimizer(&mut self, optimizer: Box<dyn Optimizer>) {
+        self.optimizer = optimizer;
+    }
+
+    ///
+    /// Get network optimizer
+    ///
+    pub fn optimizer(&self) -> &dyn Optimizer {
+        self.optimizer.as_ref()
+    }
+
+    /// Set the loss (cost) function for this neural network. Networks use the quadratic cost
+    /// unless configured otherwise.
+    ///
+    /// When the output layer uses softmax and the loss is categorical cross-entropy, the output
+    /// error is computed with the fused gradient `result - target`.
     ///
     /// # Note:
-    /// Custom cost functions will not be saved into a .gnm file.
+    /// Custom losses are saved into a .gnm file as [`LossType::CUSTOM`] with their name. Loading
+    /// them requires a constructor registered with [`crate::loss::register_loss`].
     ///
-    /// * `expression` - Cost expression
-    pub fn set_cost_function(&mut self, expression: CE) {
-        self.cost_function = expression;
+    /// * `loss` - Loss function
+    pub fn set_loss(&mut self, loss: Box<dyn Loss>) {
+        self.loss = loss;
     }
 
     ///
-    /// Init net work layers
+    /// Get network loss function
     ///
-    fn init_network_layers(instance: &mut NeuralNetwork) {
-        let mut layer_inputs = instance.input_neurons;
-        for layer_size in &instance.hidden_layer_sizes {
-            instance.layers.push(NeuralNetworkLayer::new(layer_inputs, *layer_size));
-            layer_inputs = *layer_size;
-        }
-        instance.layers.push(NeuralNetworkLayer::new(layer_inputs, instance.output_neurons));
+    pub fn loss(&self) -> &dyn Loss {
+        self.loss.as_ref()
+    }
+
+    /// Set the metrics recorded every epoch in the [`TrainingHistory`] returned by
+    /// [`NeuralNetwork::train`]
+    ///
+    /// * `metrics` - Metrics
+    pub fn set_metrics(&mut self, metrics: Vec<Metric>) {
+        self.metrics = metrics;
+    }
+
+    ///
+    /// Get the metrics recorded during training
+    ///
+    pub fn metrics(&self) -> &Vec<Metric> {
+        &self.metrics
     }
 
+    /// Set the learning rate schedule. Networks train with a constant learning rate unless
+    /// configured otherwise.
     ///
-    /// Init the network with random values
+    /// * `schedule` - Learning rate schedule
+    pub fn set_learning_rate_schedule(&mut self, schedule: Box<dyn LearningRateSchedule>) {
+        self.learning_rate_schedule = schedule;
+    }
+
+    /// Set the weight penalty applied during training. The penalty is included in the reported
+    /// training loss.
+    ///
+    /// * `regularization` - Weight penalty
+    pub fn set_regularization(&mut self, regularization: Regularization) {
+        self.regularization = regularization;
+    }
+
+    ///
+    /// Get the weight penalty applied during training
+    ///
+    pub fn regularization(&self) -> Regularization {
+        self.regularization
+    }
+
+    /// Set how gradients are clipped before they are applied by the optimizer
     ///
-    fn randomize_weights_and_biases(instance: &mut NeuralNetwork) {
-        for layer in instance.layers.iter_mut() {
-            array2_utils::randomize_array(layer.weights_mut(), 0.0, 1.0);
-            array2_utils::randomize_array(layer.biases_mut(), 0.0, 1.0);
+    /// * `gradient_clipping` - Gradient clipping
+    pub fn set_gradient_clipping(&mut self, gradient_clipping: GradientClipping) {
+        self.gradient_clipping = gradient_clipping;
+    }
+
+    ///
+    /// Get how gradients are clipped during training
+    ///
+    pub fn gradient_clipping(&self) -> GradientClipping {
+        self.gradient_clipping
+    }
+
+    /// Add a callback invoked during training (see [`Callback`])
+    ///
+    /// * `callback` - Callback
+    pub fn add_callback(&mut self, callback: Box<dyn Callback>) {
+        self.callbacks.push(callback);
+    }
+
+    ///
+    /// Remove all callbacks
+    ///
+    pub fn clear_callbacks(&mut self) {
+        self.callbacks.clear();
+    }
+
+    /// Stop training at the end of the current epoch. Intended to be called by callbacks.
+    pub fn stop_training(&mut self) {
+        self.stop_training = true;
+    }
+
+    /// Set the random number generator used for weight initialization, dropout and shuffling
+    /// training data
+    ///
+    /// * `rng` - Random number generator
+    pub fn set_rng(&mut self, rng: Box<dyn RngCore>) {
+        self.rng = rng;
+    }
+
+    /// Seed the network's random number generator, making subsequent calls to
+    /// [`NeuralNetwork::initialize`] and [`NeuralNetwork::train`] reproducible
+    ///
+    /// * `seed` - Seed
+    pub fn set_seed(&mut self, seed: u64) {
+        self.rng = Box::new(StdRng::seed_from_u64(seed));
+    }
+
+    /// Re-initialize every layer's weights and biases
+    ///
+    /// * `weight_initializer` - Weight initialization scheme
+    /// * `bias_initializer` - Bias initialization scheme
+    pub fn initialize(&mut self, weight_initializer: Initializer, bias_initializer: Initializer) {
+        for layer in self.layers.iter_mut() {
+            layer.initialize(weight_initializer, bias_initializer, &mut *self.rng);
         }
     }
 
@@ -140,218 +393,484 @@ impl NeuralNetwork {
     /// * `inputs` - ColumnVector inputs
     /// * `returns` - ColumnVector outputs
     pub fn evaluate(&self, inputs: ColumnVector) -> ColumnVector {
-        let mut activation: Array2<f32> = inputs.get_data().to_owned();
+        return ColumnVector::from(&self.evaluate_batch(inputs.get_data()));
+    }
+
+    /// Forward propagate a batch of inputs through the network. Each column of `inputs` is one
+    /// example, so every layer is evaluated with a single matrix multiplication.
+    ///
+    /// * `inputs` - Inputs (features x batch)
+    /// * `returns` - Outputs (output neurons x batch)
+    pub fn evaluate_batch(&self, inputs: &Array2<f32>) -> Array2<f32> {
+        let mut activation: Array2<f32> = inputs.to_owned();
         for layer in self.layers.iter() {
-            activation = self.non_linearity(&((layer.weights().dot(&activation)) + layer.biases()));
+            activation = layer.forward(&activation);
         }
-        return ColumnVector::from(&activation);
+        return activation;
+    }
+
+    /// Evaluate the inputs and return the index of the largest output. Useful for
+    /// classification networks with one-hot targets.
+    ///
+    /// * `inputs` - ColumnVector inputs
+    /// * `returns` - Predicted class index
+    pub fn predict_class(&self, inputs: ColumnVector) -> usize {
+        return self.evaluate(inputs).argmax();
     }
 
-    /// Train the network using stochastic gradient descent
+    /// Train the network using mini batch gradient descent. Gradients are applied by the
+    /// network's optimizer (see [`NeuralNetwork::set_optimizer`]).
     ///
     /// * `training_data` - Training data is a list of tuples (x, y) where x is the input data, and
     ///                     y is the target output.
     /// * `iterations` - Number of times to iterate the training data
     /// * `batch_size` - Size of mini batches
-    /// * `learning_rate` - The learning rate
+    /// * `learning_rate` - The learning rate, scaled every epoch by the network's learning rate
+    ///                     schedule (see [`NeuralNetwork::set_learning_rate_schedule`])
+    /// * `returns` - Per-epoch training loss and metrics, or an error if `batch_size` is 0 or
+    ///               larger than the training data, or a callback failed
     pub fn train(&mut self,
-                 mut training_data: Vec<(ColumnVector, ColumnVector)>,
+                 training_data: Vec<(ColumnVector, ColumnVector)>,
                  iterations: u32,
                  batch_size: usize,
-                 learning_rate: f32)
+                 learning_rate: f32) -> Result<TrainingHistory, GraymatError>
     {
-        let mut batch: Vec<(ColumnVector, ColumnVector)> = Vec::with_capacity(batch_size);
-        for _i in 0..iterations {
-            training_data.shuffle(&mut thread_rng());
-            for j in 0..(training_data.len() / batch_size) {
+        return self.train_with_validation(training_data, None, iterations, batch_size, learning_rate);
+    }
+
+    /// Train the network using mini batch gradient descent, evaluating the loss and configured
+    /// metrics (see [`NeuralNetwork::set_metrics`]) on validation data after every epoch.
+    ///
+    /// * `training_data` - Training data is a list of tuples (x, y) where x is the input data, and
+    ///                     y is the target output.
+    /// * `validation_data` - Optional validation data, never trained on
+    /// * `iterations` - Number of times to iterate the training data
+    /// * `batch_size` - Size of mini batches
+    /// * `learning_rate` - The learning rate, scaled every epoch by the network's learning rate
+    ///                     schedule
+    /// * `returns` - Per-epoch training loss, validation loss, learning rate and metrics, or an
+    ///               error if `batch_size` is 0 or larger than the training data, or a callback
+    ///               failed (see [`Callback::on_epoch_end`])
+    pub fn train_with_validation(&mut self,
+                                 mut training_data: Vec<(ColumnVector, ColumnVector)>,
+                                 validation_data: Option<&[(ColumnVector, ColumnVector)]>,
+                                 iterations: u32,
+                                 batch_size: usize,
+                                 learning_rate: f32) -> Result<TrainingHistory, GraymatError>
+    {
+        if batch_size == 0 || batch_size > training_data.len() {
+            return Err(GraymatError::InvalidArgument(
+                format!("batch size {} must be in 1..={}, the number of training examples", batch_size, training_data.len())));
+        }
+
+        let mut history = TrainingHistory::new(self.metrics.clone());
+        let validation = validation_data.filter(|data| !data.is_empty()).map(Self::stack_examples);
+        let number_of_batches = training_data.len() / batch_size;
+        // Training metrics are calculated from the outputs of every batch's forward pass
+        let mut epoch_outputs = Array2::zeros((0, 0));
+        let mut epoch_targets = Array2::zeros((0, 0));
+
+        // Callbacks get mutable access to the network, so they are moved out while training
+        let mut callbacks = std::mem::take(&mut self.callbacks);
+        self.stop_training = false;
+        for callback in callbacks.iter_mut() {
+            callback.on_train_start(self, iterations as usize);
+        }
+
+        let mut result = Ok(());
+        for i in 0..iterations {
+            let epoch = i as usize + 1;
+            let epoch_learning_rate = self.learning_rate_schedule.learning_rate(epoch, learning_rate, &history);
+            for callback in callbacks.iter_mut() {
+                callback.on_epoch_start(self, epoch);
+            }
+
+            training_data.shuffle(&mut *self.rng);
+            let mut total_loss = 0.0;
+            for j in 0..number_of_batches {
+                for callback in callbacks.iter_mut() {
+                    callback.on_batch_start(self, j);
+                }
                 let lower = j * batch_size;
                 let upper = lower + batch_size;
-                training_data.as_slice()[lower..upper]
-                             .clone_into(&mut batch);
-                self.train_batch(&batch, learning_rate);
+                let loss = self.train_batch(&training_data[lower..upper], epoch_learning_rate);
+                total_loss += loss;
+                if !self.metrics.is_empty() {
+                    let trained_examples = number_of_batches * batch_size;
+                    let output = self.workspace.output();
+                    array2_utils::fit_buffer(&mut epoch_outputs, (output.nrows(), trained_examples));
+                    array2_utils::fit_buffer(&mut epoch_targets, (self.workspace.targets.nrows(), trained_examples));
+                    epoch_outputs.slice_mut(s![.., lower..upper]).assign(output);
+                    epoch_targets.slice_mut(s![.., lower..upper]).assign(&self.workspace.targets);
+                }
+                for callback in callbacks.iter_mut() {
+                    callback.on_batch_end(self, j, loss);
+                }
+            }
+
+            let metrics = if self.metrics.is_empty() {
+                Vec::new()
+            } else {
+                self.calculate_metrics(&epoch_outputs, &epoch_targets)
+            };
+            let (validation_loss, validation_metrics) = match &validation {
+                Some((inputs, targets)) => {
+                    let result = self.evaluate_batch(inputs);
+                    (Some(self.loss.value(&result, targets)), self.calculate_metrics(&result, targets))
+                }
+                None => (None, Vec::new())
+            };
+
+            let record = EpochRecord {
+                epoch,
+                train_loss: total_loss / number_of_batches as f32,
+                validation_loss,
+                learning_rate: epoch_learning_rate,
+                metrics,
+                validation_metrics
+            };
+            let callback_result = callbacks.iter_mut().try_for_each(|callback| callback.on_epoch_end(self, &record));
+            history.push(record);
+
+            if let Err(error) = callback_result {
+                result = Err(error);
+                break;
+            }
+            if self.stop_training {
+                break;
             }
         }
+
+        if result.is_ok() {
+            for callback in callbacks.iter_mut() {
+                callback.on_train_end(self, &history);
+            }
+        }
+        callbacks.append(&mut self.callbacks);
+        self.callbacks = callbacks;
+        self.stop_training = false;
+
+        return result.map(|_| history);
+    }
+
+    /// Calculate the configured metrics
+    ///
+    /// * `result` - Network outputs, one example per column
+    /// * `target` - Targets, one example per column
+    fn calculate_metrics(&self, result: &Array2<f32>, target: &Array2<f32>) -> Vec<(Metric, f32)> {
+        return self.metrics.iter().map(|metric| (*metric, metric.calculate(result, target))).collect();
+    }
+
+    /// Stack (input, target) examples into an inputs matrix and a targets matrix with one
+    /// example per column
+    ///
+    /// * `data` - vector of (input, target) tuples
+    /// * `returns` - (inputs, targets)
+    fn stack_examples(data: &[(ColumnVector, ColumnVector)]) -> (Array2<f32>, Array2<f32>) {
+        let inputs: Vec<ArrayView2<f32>> = data.iter().map(|(x, _)| x.get_data().view()).collect();
+        let targets: Vec<ArrayView2<f32>> = data.iter().map(|(_, y)| y.get_data().view()).collect();
+        return (concatenate(Axis(1), &inputs).expect("Inputs must all have the same length"),
+                concatenate(Axis(1), &targets).expect("Targets must all have the same length"));
     }
 
     /// Train the network given a collection of inputs and expected outputs
     ///
-    /// This method will update the networks weights and biases with the averaged result of
-    /// all training data.
+    /// The examples are stacked into one matrix and back propagated together. This method will
+    /// update the networks weights and biases with the averaged result of all training data.
     ///
     /// * `training_data` - vector of (input, target) tuples. Input is the test data and target
     ///                     is the expected result.
     /// * `learning_rate` - learning rate
-    fn train_batch(&mut self, training_data: &Vec<(ColumnVector, ColumnVector)>, learning_rate: f32) {
+    /// * `returns` - Loss of the batch before the update, including the weight penalty
+    fn train_batch(&mut self, training_data: &[(ColumnVector, ColumnVector)], learning_rate: f32) -> f32 {
 
-        let adjustment_vectors = self.init_zeroed_adjustment_matrices();
-        let mut weight_adjustments: Vec<Array2<f32>> = adjustment_vectors.0;
-        let mut bias_adjustments: Vec<Array2<f32>> = adjustment_vectors.1;
+        self.workspace.load(training_data);
+        self.forward_backward();
+        let mut loss = self.loss.value(self.workspace.output(), &self.workspace.targets);
 
-        for i in 0..training_data.len() {
-            let result = self.back_propagate(training_data[i].0.get_data(), training_data[i].1.get_data());
-            for j in 0..self.layers.len() {
-                weight_adjustments[j] += &result.0[j];
-                bias_adjustments[j] += &result.1[j];
+        let number_of_examples = training_data.len() as f32;
+        for layer in self.layers.iter_mut() {
+            for mut parameter in layer.parameters_mut() {
+                parameter.map_gradient_inplace(|g| g / number_of_examples);
+                if parameter.regularize {
+                    loss += self.regularization.penalty(parameter.value);
+                    self.regularization.add_gradient(parameter.value, parameter.gradient);
+                }
             }
         }
-
-        let number_of_examples = training_data.len() as f32;
-        let lr_coef = learning_rate / number_of_examples;
-        for i in 0..self.layers.len() {
-            self.layers[i].weights = &self.layers[i].weights - (lr_coef * &weight_adjustments[i]);
-            self.layers[i].biases = &self.layers[i].biases - (lr_coef * &bias_adjustments[i]);
+        if self.gradient_clipping != GradientClipping::NONE {
+            let mut parameters: Vec<Parameter> = self.layers.iter_mut().flat_map(|layer| layer.parameters_mut()).collect();
+            self.gradient_clipping.clip_parameters(&mut parameters);
         }
-    }
 
-    ///
-    /// Init zeroed adjustment matrices
-    ///
-    fn init_zeroed_adjustment_matrices(&mut self) -> (Vec<Array2<f32>>, Vec<Array2<f32>>) {
-        let mut weight_adjustments: Vec<Array2<f32>> = Vec::with_capacity(self.layers.len());
-        let mut bias_adjustments: Vec<Array2<f32>> = Vec::with_capacity(self.layers.len());
-        for layer in self.layers().iter() {
-            weight_adjustments.push(Array2::zeros(layer.weights.dim()));
-            bias_adjustments.push(Array2::zeros(layer.biases.dim()));
+        let parameters = self.layers.iter_mut().flat_map(|layer| layer.parameters_mut());
+        for (index, parameter) in parameters.enumerate() {
+            match parameter.rows {
+                Some(rows) => self.optimizer.update_rows(index, parameter.value, parameter.gradient, rows, parameter.regularize,
+                                                         learning_rate),
+                None => self.optimizer.update(index, parameter.value, parameter.gradient, parameter.regularize, learning_rate)
+            }
         }
-        return (weight_adjustments, bias_adjustments);
+        return loss;
     }
 
     /// Forward propagate an input vector through the network and evaluate cost of the networks
     /// output with an expected result. Back propagate the test error through the network.
     ///
-    /// * `input` - Input vector
-    /// * `expected` - Expected output vector
-    /// * `returns` - A tuple of vectors. Tuple index 0 is the weight adjustments, tuple index 1
-    ///               is the bias adjustments. // TODO make named tuple or struct
-    pub fn back_propagate(&self, input: &Array2<f32>, expected: &Array2<f32>) -> (Vec<Array2<f32>>, Vec<Array2<f32>>) {
+    /// `input` and `expected` may hold a batch of examples, one per column, in which case the
+    /// returned adjustments are the sum over all examples. The pass runs in training mode, so
+    /// dropout is applied and batch normalization updates its running statistics.
+    ///
+    /// * `input` - Input vector or matrix (features x batch)
+    /// * `expected` - Expected output vector or matrix (outputs x batch)
+    /// * `returns` - A tuple of vectors holding the gradients of every dense layer. Tuple index
+    ///               0 is the weight adjustments, tuple index 1 is the bias adjustments.
+    pub fn back_propagate(&mut self, input: &Array2<f32>, expected: &Array2<f32>) -> (Vec<Array2<f32>>, Vec<Array2<f32>>) {
+        array2_utils::assign_to_buffer(&mut self.workspace.inputs, input);
+        array2_utils::assign_to_buffer(&mut self.workspace.targets, expected);
+        self.forward_backward();
+        let dense_layers = self.dense_layers();
+        return (dense_layers.iter().map(|layer| layer.weight_gradient.clone()).collect(),
+                dense_layers.iter().map(|layer| layer.bias_gradient.clone()).collect());
+    }
 
-        let mut weight_adjustments: Vec<Array2<f32>> = Vec::with_capacity(self.layers.len());
-        let mut bias_adjustments: Vec<Array2<f32>> = Vec::with_capacity(self.layers.len());
+    /// Forward propagate the batch held in the workspace in training mode, then back propagate
+    /// the loss gradient layer by layer from the output. Every layer keeps its parameter
+    /// gradients, summed over the batch. Layer outputs and errors are written into the
+    /// workspace, which is reused between steps.
+    fn forward_backward(&mut self) {
+        let fused = self.is_softmax_cross_entropy();
+        let workspace = &mut self.workspace;
+        let number_of_layers = self.layers.len();
+        workspace.outputs.resize_with(number_of_layers, || Array2::zeros((0, 0)));
+        workspace.input_gradients.resize_with(number_of_layers, || Array2::zeros((0, 0)));
+
+        for (i, layer) in self.layers.iter_mut().enumerate() {
+            let (previous, next) = workspace.outputs.split_at_mut(i);
+            let input = previous.last().unwrap_or(&workspace.inputs);
+            layer.forward_train_into(input, &mut next[0], &mut *self.rng);
+        }
 
-        self.back_prop_recursive(0, &input, &expected, &mut weight_adjustments, &mut bias_adjustments);
+        let output = &workspace.outputs[number_of_layers - 1];
+        let (output_layer, hidden_layers) = self.layers.split_last_mut().unwrap();
+        let output_error = &mut workspace.input_gradients[number_of_layers - 1];
+        if fused {
+            array2_utils::assign_to_buffer(&mut workspace.output_gradient, output);
+            workspace.output_gradient -= &workspace.targets;
+            output_layer.backward_pre_activation_into(&workspace.output_gradient, output_error);
+        } else {
+            self.loss.gradient_into(output, &workspace.targets, &mut workspace.output_gradient);
+            output_layer.backward_into(&workspace.output_gradient, output_error);
+        }
+        for (i, layer) in hidden_layers.iter_mut().enumerate().rev() {
+            let (lower, upper) = workspace.input_gradients.split_at_mut(i + 1);
+            layer.backward_into(&upper[0], &mut lower[i]);
+        }
+    }
 
-        return (weight_adjustments, bias_adjustments);
+    /// True if the output layer is softmax and the cost is categorical cross-entropy, in which
+    /// case the output error is the fused gradient `result - target`
+    fn is_softmax_cross_entropy(&self) -> bool {
+        let output_activation = self.layers[self.layers.len() - 1].output_activation();
+        return output_activation == Some(ActivationFunction::SOFTMAX)
+            && self.loss.loss_type() == LossType::CATEGORICAL_CROSS_ENTROPY;
     }
 
-    /// Back propagate recursively
+    /// Calculate the average loss of the network over a data set
     ///
-    /// * `layer_index` - layer index
-    /// * `x` - layer activations
-    /// * `y` - expected result
-    /// * `wam` - weight adjustment matrix
-    /// * `bam` - bias adjustment matrix
-    /// * `returns` - layer error
-    fn back_prop_recursive(&self, layer_index: usize,
-                           x: &Array2<f32>,
-                           y: &Array2<f32>,
-                           wam: &mut Vec<Array2<f32>>,
-                           bam: &mut Vec<Array2<f32>>) -> Array2<f32>
-    {
-        if layer_index == self.layers.len() {
-            return self.calculate_cost(x, y);
+    /// * `data` - vector of (input, target) tuples
+    /// * `returns` - Loss value averaged over all examples
+    pub fn calculate_loss(&self, data: &[(ColumnVector, ColumnVector)]) -> f32 {
+        if data.is_empty() {
+            return 0.0;
         }
+        let (inputs, targets) = Self::stack_examples(data);
+        return self.loss.value(&self.evaluate_batch(&inputs), &targets);
+    }
 
-        let w: &Array2<f32> = &self.layers[layer_index].weights;
-        let b: &Array2<f32> = &self.layers[layer_index].biases;
-        let z = w.dot(x) + b;
-        let result: Array2<f32> = self.non_linearity(&z);
-
-        let error: Array2<f32> = self.back_prop_recursive(layer_index + 1, &result, y, wam, bam);
-
-        let x_prime: Array2<f32> = self.non_linearity_prime(&z);
-        let delta = &error * x_prime;
-        wam.insert(0, delta.dot(&x.t()));
-        bam.insert(0, delta);
-
-        return self.layers[layer_index].weights.clone().t().dot(&error);
+    /// Copy every trainable parameter. Parameters are ordered by their optimizer index, which
+    /// follows the layers from the input side. A dense layer contributes its weights, its biases
+    /// and, if it is normalized, its normalization scale and shift.
+    pub fn parameters(&self) -> Vec<Array2<f32>> {
+        return self.layers.iter().flat_map(|layer| layer.parameters()).cloned().collect();
     }
 
-    /// Calculate network cost
+    /// Overwrite every trainable parameter, for example with a copy taken by
+    /// [`NeuralNetwork::parameters`]
     ///
-    /// * `result` - network output result
-    /// * `target` - target network result
-    fn calculate_cost(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
-        return (self.cost_function)(result, target);
+    /// * `parameters` - Parameters ordered by their optimizer index
+    pub fn set_parameters(&mut self, parameters: &[Array2<f32>]) {
+        let mut targets: Vec<Parameter> = self.layers.iter_mut().flat_map(|layer| layer.parameters_mut()).collect();
+        assert_eq!(parameters.len(), targets.len(), "Expected {} parameters", targets.len());
+        for (target, parameter) in targets.iter_mut().zip(parameters.iter()) {
+            target.value.assign(parameter);
+        }
+    }
+
+    /// Copy the state of every layer that is not trained but changes while training, such as the
+    /// running statistics of batch normalization. Together with [`NeuralNetwork::parameters`]
+    /// this is everything evaluation depends on.
+    pub fn buffers(&self) -> Vec<Array2<f32>> {
+        return self.layers.iter().flat_map(|layer| layer.buffers()).cloned().collect();
     }
 
-    /// Calculate network cost
+    /// Overwrite the state of every layer with a copy taken by [`NeuralNetwork::buffers`]
     ///
-    /// * `result` - network output result
-    /// * `target` - target network result
-    fn calculate_cost_default(result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
-        return result - target;
+    /// * `buffers` - Buffers ordered like [`NeuralNetwork::buffers`]
+    pub fn set_buffers(&mut self, buffers: &[Array2<f32>]) {
+        let mut targets: Vec<&mut Array2<f32>> = self.layers.iter_mut().flat_map(|layer| layer.buffers_mut()).collect();
+        assert_eq!(buffers.len(), targets.len(), "Expected {} buffers", targets.len());
+        for (target, buffer) in targets.iter_mut().zip(buffers.iter()) {
+            target.assign(buffer);
+        }
     }
 
-    /// Network non-linearity
     ///
-    /// * `x` - array2 to process
-    fn non_linearity(&self, x: &Array2<f32>) -> Array2<f32> {
-        return (self.activation_expression)(x);
+    /// Get neural network layers
+    ///
+    pub fn layers(&self) -> &Vec<Box<dyn Layer>> {
+        &self.layers
     }
 
-    /// Network non-linearity first derivative
     ///
-    /// * `x` - array2 to process
-    fn non_linearity_prime(&self, x: &Array2<f32>) -> Array2<f32> {
-        return (self.activation_expression_prime)(x);
+    /// Get the dense layers of the network, input side first
+    ///
+    pub fn dense_layers(&self) -> Vec<&NeuralNetworkLayer> {
+        return self.layers.iter().filter_map(|layer| layer.downcast_ref::<NeuralNetworkLayer>()).collect();
     }
 
     ///
-    /// Get neural network layers
+    /// Get the number of network inputs
     ///
-    pub fn layers(&self) -> &Vec<NeuralNetworkLayer> {
-        &self.layers
+    pub fn input_size(&self) -> usize {
+        self.input_size
     }
 
     /// Save the current network to a file
     ///
-    /// * `path` - File path
+    /// * `path` - Directory path
     /// * `filename` - Filename. The .gnm file extension will be automatically added if not already set
-    pub fn to_file(&self, path: &str, filename: &str) {
-        let check_file_result = check_gnm_filepath(path, filename);
-        let filepath = match check_file_result {
-            Ok(filepath) => filepath,
-            Err(error) => panic!("Error saving network to file: {:?}", error)
-        };
-        to_file(filepath, &self);
+    pub fn to_file(&self, path: impl AsRef<Path>, filename: &str) -> Result<(), GraymatError> {
+        let filepath = check_gnm_filepath(path, filename)?;
+        return to_file(filepath, self);
     }
 
     /// Load a neural network instance from a file
     ///
-    /// * `path` - File path
+    /// * `path` - Directory path
     /// * `filename` - Filename. The .gnm file extension will be automatically added if not already set
-    pub fn from_file(path: &str, filename: &str) -> Self {
-        let check_file_result = check_gnm_filepath(path, filename);
-        let filepath = match check_file_result {
-            Ok(filepath) => filepath,
-            Err(error) => panic!("Error saving network to file: {:?}", error)
-        };
+    pub fn from_file(path: impl AsRef<Path>, filename: &str) -> Result<Self, GraymatError> {
+        let filepath = check_gnm_filepath(path, filename)?;
         return from_file(filepath);
     }
 
+    /// Write the current network in .gnm format
     ///
-    /// Get network activation function
+    /// * `writer` - Destination, for example a file or socket
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<(), GraymatError> {
+        return write_to(writer, self);
+    }
+
+    /// Read a neural network instance in .gnm format
     ///
-    pub fn activation_function(&self) -> ActivationFunction {
-        self.activation_function
+    /// * `reader` - Source, for example a file or socket
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, GraymatError> {
+        return read_from(reader);
+    }
+
+    ///
+    /// Serialize the current network to .gnm bytes
+    ///
+    pub fn to_bytes(&self) -> Result<Vec<u8>, GraymatError> {
+        let mut bytes: Vec<u8> = Vec::new();
+        write_to(&mut bytes, self)?;
+        return Ok(bytes);
+    }
+
+    /// Load a neural network instance from .gnm bytes, for example a model embedded with
+    /// `include_bytes!`
+    ///
+    /// * `bytes` - Serialized network
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GraymatError> {
+        return read_from(bytes);
     }
 }
 
 impl Display for NeuralNetwork {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         let mut s = "".to_string();
-        write!(s, "Activation Function: {}\n", ActivationFunction::convert_to_string(self.activation_function)).unwrap();
         for (i, layer) in self.layers.iter().enumerate() {
-            write!(s, "Layer {} ({}x{})\n", i + 1, layer.weights.shape()[0], layer.weights.shape()[1]).unwrap();
-            write!(s, "{}", *layer).unwrap();
+            let dense = match layer.downcast_ref::<NeuralNetworkLayer>() {
+                Some(dense) => dense,
+                None => {
+                    writeln!(s, "Layer {} {}", i + 1, LayerType::convert_to_string(layer.layer_type())).unwrap();
+                    continue;
+                }
+            };
+            write!(s, "Layer {} ({}x{}) {}", i + 1, dense.weights.shape()[0], dense.weights.shape()[1],
+                   dense.activation_function()).unwrap();
+            if let Some(normalization) = &dense.normalization {
+                write!(s, ", {}", NormalizationType::convert_to_string(normalization.normalization_type())).unwrap();
+            }
+            writeln!(s).unwrap();
+            write!(s, "{}", *dense).unwrap();
         }
         write!(f, "{}", s)
     }
 }
 
+/// Buffers reused between training steps so back propagation does not allocate. Buffers are
+/// reallocated only when the batch size or the network shape changes.
+#[derive(Default)]
+struct Workspace {
+    /// Inputs (features x batch)
+    inputs: Array2<f32>,
+    /// Targets (outputs x batch)
+    targets: Array2<f32>,
+    /// Output of every layer (features x batch)
+    outputs: Vec<Array2<f32>>,
+    /// Loss gradient with respect to the network outputs
+    output_gradient: Array2<f32>,
+    /// Loss gradient with respect to the inputs of every layer
+    input_gradients: Vec<Array2<f32>>
+}
+
+impl Workspace {
+    /// Copy a batch of examples into the input and target buffers, one example per column
+    ///
+    /// * `data` - vector of (input, target) tuples
+    fn load(&mut self, data: &[(ColumnVector, ColumnVector)]) {
+        let (input, target) = &data[0];
+        array2_utils::fit_buffer(&mut self.inputs, (input.get_data().nrows(), data.len()));
+        array2_utils::fit_buffer(&mut self.targets, (target.get_data().nrows(), data.len()));
+        for (j, (input, target)) in data.iter().enumerate() {
+            assert_eq!(input.get_data().nrows(), self.inputs.nrows(), "Inputs must all have the same length");
+            assert_eq!(target.get_data().nrows(), self.targets.nrows(), "Targets must all have the same length");
+            self.inputs.column_mut(j).assign(&input.get_data().column(0));
+            self.targets.column_mut(j).assign(&target.get_data().column(0));
+        }
+    }
+
+    ///
+    /// Get the network outputs of the last training pass
+    ///
+    fn output(&self) -> &Array2<f32> {
+        &self.outputs[self.outputs.len() - 1]
+    }
+}
+
+/// Fully connected layer, `activation(normalization(W * x + b))`, followed by optional dropout
 pub struct NeuralNetworkLayer {
     weights: Array2<f32>,
-    biases: Array2<f32>
+    biases: Array2<f32>,
+    activation: ActivationLayer,
+    dropout: Dropout,
+    normalization: Option<Normalization>,
+    /// Training buffers, reused between steps of the same batch size
+    input: Array2<f32>,
+    delta: Array2<f32>,
+    weight_gradient: Array2<f32>,
+    bias_gradient: Array2<f32>
 }
 
 impl NeuralNetworkLayer {
@@ -359,13 +878,66 @@ impl NeuralNetworkLayer {
     ///
     /// * `inputs` - Number of inputs
     /// * `neurons` - Number of layer neurons
-    pub fn new(inputs: usize, neurons: usize) -> Self {
+    /// * `activation` - Activation function applied to the layer output
+    pub fn new(inputs: usize, neurons: usize, activation: ActivationFunction) -> Self {
         return NeuralNetworkLayer {
             weights: Array2::zeros((neurons, inputs)),
-            biases: Array2::ones((neurons, 1))
+            biases: Array2::ones((neurons, 1)),
+            activation: ActivationLayer::new(activation),
+            dropout: Dropout::new(0.0),
+            normalization: None,
+            input: Array2::zeros((inputs, 0)),
+            delta: Array2::zeros((neurons, 0)),
+            weight_gradient: Array2::zeros((neurons, inputs)),
+            bias_gradient: Array2::zeros((neurons, 1))
         };
     }
 
+    /// Set the activation function for this layer
+    ///
+    /// * `function` - Activation function type
+    pub fn set_activation_function(&mut self, function: ActivationFunction) {
+        self.activation.set_activation_function(function);
+    }
+
+    ///
+    /// Get layer activation function
+    ///
+    pub fn activation_function(&self) -> ActivationFunction {
+        self.activation.activation_function()
+    }
+
+    /// Set the dropout rate of this layer's outputs during training
+    ///
+    /// * `rate` - Probability of dropping an output, in [0, 1)
+    pub fn set_dropout(&mut self, rate: f32) {
+        self.dropout = Dropout::new(rate);
+    }
+
+    ///
+    /// Get layer dropout rate
+    ///
+    pub fn dropout(&self) -> f32 {
+        self.dropout.rate()
+    }
+
+    /// Set the normalization of this layer's pre-activations
+    ///
+    /// * `normalization` - Batch or layer normalization, None to remove it
+    pub fn set_normalization(&mut self, normalization: Option<Normalization>) {
+        if let Some(normalization) = &normalization {
+            assert!(normalization.fits(self.weights.nrows()), "Normalization must have one value per neuron");
+        }
+        self.normalization = normalization;
+    }
+
+    ///
+    /// Get layer normalization
+    ///
+    pub fn normalization(&self) -> Option<&Normalization> {
+        self.normalization.as_ref()
+    }
+
     ///
     /// Layer weights
     ///
@@ -393,6 +965,163 @@ impl NeuralNetworkLayer {
     pub fn biases_mut(&mut self) -> &mut Array2<f32> {
         &mut self.biases
     }
+
+    /// Restore a dense layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, GraymatError> {
+        let mut reader = bytes;
+        let (activation, dropout, weights, biases, normalization): (u8, f32, Array2<f32>, Array2<f32>, Option<Normalization>)
+            = bincode::deserialize_from(&mut reader)?;
+        let activation = ActivationFunction::from_bytes(activation, &mut reader)?;
+        if !(0.0..1.0).contains(&dropout) {
+            return Err(GraymatError::InvalidHeader(format!("dropout rate {} is outside [0, 1)", dropout)));
+        }
+        if weights.is_empty() || biases.dim() != (weights.nrows(), 1) {
+            return Err(GraymatError::ShapeMismatch(
+                format!("{}x{} weights with {}x{} biases", weights.nrows(), weights.ncols(), biases.nrows(), biases.ncols())));
+        }
+        check_declared_size("dense layer", Some(weights.len()))?;
+        if let Some(normalization) = &normalization {
+            normalization.validate(weights.nrows())?;
+        }
+        let mut layer = NeuralNetworkLayer::new(weights.ncols(), weights.nrows(), activation);
+        layer.weights = weights;
+        layer.biases = biases;
+        layer.set_dropout(dropout);
+        layer.normalization = normalization;
+        return Ok(layer);
+    }
+
+    /// Back propagate the pre-activation error held in `self.delta`, storing the parameter
+    /// gradients and writing the error with respect to the layer inputs into `input_gradient`
+    fn backward_delta_into(&mut self, input_gradient: &mut Array2<f32>) {
+        if let Some(normalization) = &mut self.normalization {
+            normalization.backward_inplace(&mut self.delta);
+        }
+        general_mat_mul(1.0, &self.delta, &self.input.t(), 0.0, &mut self.weight_gradient);
+        Zip::from(self.bias_gradient.rows_mut()).and(self.delta.rows()).for_each(|mut bias, row| {
+            bias[0] = row.sum();
+        });
+        array2_utils::fit_buffer(input_gradient, self.input.dim());
+        general_mat_mul(1.0, &self.weights.t(), &self.delta, 0.0, input_gradient);
+    }
+}
+
+impl Layer for NeuralNetworkLayer {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        let mut z = self.weights.dot(input) + &self.biases;
+        if let Some(normalization) = &self.normalization {
+            normalization.evaluate_inplace(&mut z);
+        }
+        self.activation.evaluate_inplace(&mut z);
+        return z;
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, rng: &mut dyn RngCore) -> Array2<f32> {
+        let mut output = Array2::zeros((self.weights.nrows(), input.ncols()));
+        self.forward_train_into(input, &mut output, rng);
+        return output;
+    }
+
+    fn forward_train_into(&mut self, input: &Array2<f32>, output: &mut Array2<f32>, rng: &mut dyn RngCore) {
+        array2_utils::assign_to_buffer(&mut self.input, input);
+        array2_utils::fit_buffer(output, (self.weights.nrows(), input.ncols()));
+        general_mat_mul(1.0, &self.weights, input, 0.0, output);
+        *output += &self.biases;
+        if let Some(normalization) = &mut self.normalization {
+            normalization.train_inplace(output);
+        }
+        self.activation.train_inplace(output);
+        self.dropout.train_inplace(output, rng);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let mut input_gradient = Array2::zeros(self.input.dim());
+        self.backward_into(output_gradient, &mut input_gradient);
+        return input_gradient;
+    }
+
+    fn backward_into(&mut self, output_gradient: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        array2_utils::assign_to_buffer(&mut self.delta, output_gradient);
+        self.dropout.backward_inplace(&mut self.delta);
+        self.activation.backward_inplace(&mut self.delta);
+        self.backward_delta_into(input_gradient);
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        let mut parameters = vec![&self.weights, &self.biases];
+        if let Some(normalization) = &self.normalization {
+            parameters.extend(normalization.parameters());
+        }
+        parameters.extend(self.activation.parameters());
+        return parameters;
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        let mut gradients = vec![&self.weight_gradient, &self.bias_gradient];
+        if let Some(normalization) = &self.normalization {
+            gradients.extend(normalization.gradients());
+        }
+        gradients.extend(self.activation.gradients());
+        return gradients;
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        let mut parameters = vec![
+            Parameter { value: &mut self.weights, gradient: &mut self.weight_gradient, regularize: true, rows: None },
+            Parameter { value: &mut self.biases, gradient: &mut self.bias_gradient, regularize: false, rows: None }
+        ];
+        if let Some(normalization) = &mut self.normalization {
+            parameters.extend(normalization.parameters_mut());
+        }
+        parameters.extend(self.activation.parameters_mut());
+        return parameters;
+    }
+
+    fn buffers(&self) -> Vec<&Array2<f32>> {
+        return self.normalization.as_ref().map_or_else(Vec::new, |normalization| normalization.buffers());
+    }
+
+    fn buffers_mut(&mut self) -> Vec<&mut Array2<f32>> {
+        return self.normalization.as_mut().map_or_else(Vec::new, |normalization| normalization.buffers_mut());
+    }
+
+    fn initialize(&mut self, weight_initializer: Initializer, bias_initializer: Initializer, rng: &mut dyn RngCore) {
+        weight_initializer.initialize(&mut self.weights, rng);
+        bias_initializer.initialize(&mut self.biases, rng);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return (input_size == self.weights.ncols()).then_some(self.weights.nrows());
+    }
+
+    fn output_activation(&self) -> Option<ActivationFunction> {
+        return Some(self.activation_function());
+    }
+
+    fn backward_pre_activation(&mut self, delta: &Array2<f32>) -> Array2<f32> {
+        let mut input_gradient = Array2::zeros(self.input.dim());
+        self.backward_pre_activation_into(delta, &mut input_gradient);
+        return input_gradient;
+    }
+
+    fn backward_pre_activation_into(&mut self, delta: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        array2_utils::assign_to_buffer(&mut self.delta, delta);
+        self.backward_delta_into(input_gradient);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::DENSE;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let function = self.activation_function();
+        let mut bytes = bincode::serialize(&(function.id(), self.dropout(), &self.weights, &self.biases,
+                                             &self.normalization)).unwrap();
+        bytes.extend(function.parameters_to_bytes());
+        return bytes;
+    }
 }
 
 impl Display for NeuralNetworkLayer {
@@ -404,10 +1133,8 @@ impl Display for NeuralNetworkLayer {
             for j in 0..cols {
                 write!(s, "{:2.4}(w{}:{}) ", self.weights[[i, j]], i, j).unwrap();
             }
-            write!(s, "| {:2.4}(b{})\n", self.biases[[i, 0]], i).unwrap();
+            writeln!(s, "| {:2.4}(b{})", self.biases[[i, 0]], i).unwrap();
         }
         write!(f, "{}", s)
     }
 }
-
-
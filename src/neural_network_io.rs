@@ -1,61 +1,108 @@
 use std::fs::File;
-use std::io;
-use std::io::{ErrorKind, Read, Write};
-use std::path::Path;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 
 use ndarray::{Array2};
-use crate::neural_network::{NeuralNetwork};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use crate::neural_network::{NeuralNetwork, NeuralNetworkLayer};
 use serde::{Serialize, Deserialize};
 use crate::activation_function::ActivationFunction;
+use crate::error::{GraymatError, Result};
+use crate::layer::{check_declared_size, layer_from_bytes, Layer, LayerType};
+use crate::loss::{loss_from_bytes, loss_to_bytes, Loss, LossType};
+use crate::optimizer::{optimizer_from_bytes, Optimizer, OptimizerType};
+use crate::utilities::checksum::crc32;
 use crate::utilities::string_utils::copy_string_into_byte_array;
 
-const FILE_HEADER_SIZE_BYTES: u64 = 37;
-const LAYER_HEADER_SIZE_BYTES: u64 = 28;
+const FILE_HEADER_SIZE_BYTES: u64 = 36;
+const INPUT_SIZE_BYTES: u64 = 4;
+const LAYER_HEADER_SIZE_BYTES: u64 = 13;
+const OPTIMIZER_HEADER_SIZE_BYTES: u64 = 9;
+const LOSS_HEADER_SIZE_BYTES: u64 = 9;
 const META_SIZE: usize = 12;
+const META: &str = "GrayMay(0_0)";
 pub const GRAYMAT_NETWORK_FILE_EXTENSION: &str = ".gnm"; // GrayMat Network Model
 
+// Format versions. Every version listed here can still be loaded.
+const VERSION_1_1: u32 = 0x01_01_00; // Dense layers with a network-wide activation function
+const VERSION_1_2: u32 = 0x01_02_00; // Typed layers with checksums, network input size, optimizer and loss sections
+const FORMAT_VERSION: u32 = VERSION_1_2;
+
+// Sanity limits on sizes declared in a file. These are checked before any buffer is allocated.
+// Sizes declared inside a layer are checked by the layer with
+// crate::layer::check_declared_size.
+const MAX_LAYERS: u32 = 4096;
+const MAX_SECTION_SIZE_BYTES: u64 = 1 << 30;
+const BINCODE_VEC_LENGTH_BYTES: u64 = 8;
+const F32_SIZE_BYTES: u64 = 4;
+
 #[derive(Debug, Serialize, Deserialize)]
 struct FileHeader {
     pub version: u32,
     meta: [u8; META_SIZE],
     pub header_size_bytes: u64,
     pub layer_header_size_bytes: u64,
-    pub number_of_layers: u32,
-    pub activation_function: u8
+    pub number_of_layers: u32
 }
 
 impl FileHeader {
-    pub fn new(number_of_layers: u32, activation_function: u8) -> Self {
+    pub fn new(number_of_layers: u32) -> Self {
         let mut ca: [u8; META_SIZE] = [0; META_SIZE];
-        copy_string_into_byte_array("GrayMay(0_0)", &mut ca);
+        copy_string_into_byte_array(META, &mut ca);
         return Self {
-            version: 0x01_01_00, // v1.01.00
+            version: FORMAT_VERSION,
             meta: ca,
-            header_size_bytes: FILE_HEADER_SIZE_BYTES,
+            header_size_bytes: FILE_HEADER_SIZE_BYTES + INPUT_SIZE_BYTES,
             layer_header_size_bytes: LAYER_HEADER_SIZE_BYTES,
-            number_of_layers,
-            activation_function
+            number_of_layers
         };
     }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 struct LayerHeader {
-    weight_rows: u32,
-    weight_cols: u32,
-    biases: u32,
-    weights_size_bytes: u64,
-    biases_size_bytes: u64,
+    layer_type: u8,
+    size_bytes: u64,
+    checksum: u32
 }
 
 impl LayerHeader {
-    pub fn new(weight_rows: u32, weight_cols: u32, biases: u32, weights_size_bytes: u64, biases_size_bytes: u64) -> Self {
+    pub fn new(layer_type: u8, size_bytes: u64, checksum: u32) -> Self {
+        return Self {
+            layer_type,
+            size_bytes,
+            checksum
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OptimizerHeader {
+    optimizer_type: u8,
+    state_size_bytes: u64
+}
+
+impl OptimizerHeader {
+    pub fn new(optimizer_type: u8, state_size_bytes: u64) -> Self {
         return Self {
-            weight_rows,
-            weight_cols,
-            biases,
-            weights_size_bytes,
-            biases_size_bytes
+            optimizer_type,
+            state_size_bytes
+        };
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LossHeader {
+    loss_type: u8,
+    parameters_size_bytes: u64
+}
+
+impl LossHeader {
+    pub fn new(loss_type: u8, parameters_size_bytes: u64) -> Self {
+        return Self {
+            loss_type,
+            parameters_size_bytes
         };
     }
 }
@@ -66,11 +113,12 @@ impl LayerHeader {
 /// # Arguments
 /// * `path` - File path
 /// * `filename` - Filename. The .gnm file extension will be automatically added if not already set
-/// * `returns` - Result string if path exists, else Error
-pub fn check_gnm_filepath(path: &str, filename: &str) -> Result<String, io::Error> {
+/// * `returns` - Result path if the directory exists, else Error
+pub fn check_gnm_filepath(path: impl AsRef<Path>, filename: &str) -> Result<PathBuf> {
 
-    if !Path::new(path).exists() {
-        return Err(io::Error::new(ErrorKind::NotFound, "Invalid Filepath: Path does not exist"));
+    let path = path.as_ref();
+    if !path.exists() {
+        return Err(GraymatError::FileNotFound(path.display().to_string()));
     }
 
     let mut full_filename = filename.to_owned();
@@ -78,120 +126,342 @@ pub fn check_gnm_filepath(path: &str, filename: &str) -> Result<String, io::Erro
         full_filename.push_str(GRAYMAT_NETWORK_FILE_EXTENSION);
     }
 
-    let mut full_path = path.to_owned();
-    if !full_path.ends_with("/") {
-        full_path.push_str("/"); // TODO make platform independent
-    }
-
-    full_path.push_str(full_filename.as_str());
-    return Ok(full_path);
+    return Ok(path.join(full_filename));
 }
 
 /// Write a NeuralNetwork to a file
-
+///
 /// # Arguments
 /// * `path` - Full filepath with filename and extension
 /// * `network` - The NeuralNetwork to save
-pub fn to_file(path: String, network: &NeuralNetwork) {
+pub fn to_file(path: impl AsRef<Path>, network: &NeuralNetwork) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_to(&mut writer, network)?;
+    writer.flush()?;
+    return Ok(());
+}
 
-    let mut file = File::create(path).unwrap();
+/// Read a NeuralNetwork from a file
+///
+/// # Arguments
+/// * `path` - Filepath to network file
+/// * `returns` - NeuralNetwork
+pub fn from_file(path: impl AsRef<Path>) -> Result<NeuralNetwork> {
+    let path = path.as_ref();
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            return Err(GraymatError::FileNotFound(path.display().to_string()));
+        }
+        Err(error) => return Err(GraymatError::from(error))
+    };
+    return read_from(BufReader::new(file));
+}
 
-    let file_header = FileHeader::new(network.layers().len() as u32,
-                                      network.activation_function() as u8);
-    let file_header_bytes = bincode::serialize(&file_header).unwrap();
+/// Write a NeuralNetwork in .gnm format
+///
+/// # Arguments
+/// * `writer` - Destination, for example a file, socket or `Vec<u8>`
+/// * `network` - The NeuralNetwork to save
+pub fn write_to<W: Write>(mut writer: W, network: &NeuralNetwork) -> Result<()> {
 
-    file.write(&file_header_bytes).unwrap();
+    let file_header = FileHeader::new(network.layers().len() as u32);
+    let file_header_bytes = bincode::serialize(&file_header)?;
 
-    for layer in network.layers() {
+    writer.write_all(&file_header_bytes)?;
+    writer.write_all(&(network.input_size() as u32).to_le_bytes())?;
 
-        let weights = layer.weights();
-        let biases = layer.biases();
-        let serialized_weights = bincode::serialize(&weights.to_owned().into_raw_vec()).unwrap();
-        let serialized_biases = bincode::serialize(&biases.to_owned().into_raw_vec()).unwrap();
+    for layer in network.layers() {
 
-        let layer_header = LayerHeader::new(weights.shape()[0] as u32,
-                                            weights.shape()[1] as u32,
-                                            biases.shape()[0] as u32,
-                                            serialized_weights.len() as u64,
-                                            serialized_biases.len() as u64);
-        let layer_header_bytes = bincode::serialize(&layer_header).unwrap();
+        let serialized_layer = layer.to_bytes();
+        let layer_header = LayerHeader::new(layer.layer_type() as u8,
+                                            serialized_layer.len() as u64,
+                                            crc32(&serialized_layer));
+        let layer_header_bytes = bincode::serialize(&layer_header)?;
 
-        file.write(&layer_header_bytes).unwrap();
-        file.write(&serialized_weights).unwrap();
-        file.write(&serialized_biases).unwrap();
+        writer.write_all(&layer_header_bytes)?;
+        writer.write_all(&serialized_layer)?;
     }
+
+    let optimizer = network.optimizer();
+    let serialized_optimizer = optimizer.to_bytes();
+    let optimizer_header = OptimizerHeader::new(optimizer.optimizer_type() as u8,
+                                                serialized_optimizer.len() as u64);
+    let optimizer_header_bytes = bincode::serialize(&optimizer_header)?;
+
+    writer.write_all(&optimizer_header_bytes)?;
+    writer.write_all(&serialized_optimizer)?;
+
+    let loss = network.loss();
+    let serialized_loss = loss_to_bytes(loss);
+    let loss_header = LossHeader::new(loss.loss_type() as u8, serialized_loss.len() as u64);
+    let loss_header_bytes = bincode::serialize(&loss_header)?;
+
+    writer.write_all(&loss_header_bytes)?;
+    writer.write_all(&serialized_loss)?;
+    return Ok(());
 }
 
-/// Read a NeuralNetwork from a file
+/// Read a NeuralNetwork in .gnm format
+///
+/// Data written by older versions of graymat is migrated to the current format as it is read.
+/// Data written by newer versions is rejected with [`GraymatError::UnsupportedVersion`].
 ///
 /// # Arguments
-/// * `path` - Filepath to network file
+/// * `reader` - Source, for example a file, socket or `&[u8]`
 /// * `returns` - NeuralNetwork
-pub fn from_file(path: String) -> NeuralNetwork {
+pub fn read_from<R: Read>(mut reader: R) -> Result<NeuralNetwork> {
 
-    let mut file = File::open(path).unwrap();
+    let file_header = load_file_header(&mut reader)?;
 
-    let mut file_header_buffer: [u8; FILE_HEADER_SIZE_BYTES as usize] = [0; FILE_HEADER_SIZE_BYTES as usize];
-    file.read_exact(&mut file_header_buffer).unwrap();
-    let file_header: FileHeader = bincode::deserialize(&file_header_buffer).unwrap();
+    if file_header.version == VERSION_1_1 {
+        return load_version_1_1_layers(&mut reader, &file_header);
+    }
+
+    let mut network = load_layers(&mut reader, &file_header)?;
+    network.set_optimizer(load_optimizer(&mut reader)?);
+    network.set_loss(load_loss(&mut reader)?);
+    return Ok(network);
+}
+
+/// Load the network input size and the typed layers
+///
+/// * `reader` - Source
+/// * `file_header` - File header
+fn load_layers<R: Read>(reader: &mut R, file_header: &FileHeader) -> Result<NeuralNetwork> {
+    let mut input_size_buffer: [u8; INPUT_SIZE_BYTES as usize] = [0; INPUT_SIZE_BYTES as usize];
+    reader.read_exact(&mut input_size_buffer)?;
+    let input_size = check_declared_size("network input", Some(u32::from_le_bytes(input_size_buffer) as usize))?;
+
+    let number_of_layers = file_header.number_of_layers as usize;
+    let mut layers: Vec<Box<dyn Layer>> = Vec::with_capacity(number_of_layers);
+    let mut layer_inputs = input_size;
+
+    for i in 0..number_of_layers {
+
+        let mut layer_header_buffer: [u8; LAYER_HEADER_SIZE_BYTES as usize] = [0; LAYER_HEADER_SIZE_BYTES as usize];
+        reader.read_exact(&mut layer_header_buffer)?;
+        let layer_header: LayerHeader = bincode::deserialize(&layer_header_buffer)?;
+
+        let layer_buffer = read_exact_bytes(reader, layer_header.size_bytes)?;
+        if crc32(&layer_buffer) != layer_header.checksum {
+            return Err(GraymatError::ChecksumMismatch(i));
+        }
+
+        let layer_type = LayerType::from_u8(layer_header.layer_type)
+            .ok_or(GraymatError::UnknownLayer(layer_header.layer_type))?;
+        let layer = layer_from_bytes(layer_type, &layer_buffer)?;
+
+        let output_size = layer.output_size(layer_inputs).ok_or_else(|| GraymatError::ShapeMismatch(
+            format!("layer {} cannot take {} inputs", i, layer_inputs)))?;
+        layer_inputs = check_declared_size(&format!("layer {} output", i), Some(output_size))?;
+        layers.push(layer);
+    }
+
+    let output_layer = layers[number_of_layers - 1].downcast_ref::<NeuralNetworkLayer>();
+    if output_layer.is_some_and(|layer| layer.dropout() != 0.0) {
+        return Err(GraymatError::InvalidHeader("dropout set on the output layer".to_owned()));
+    }
+
+    return Ok(NeuralNetwork::from_layers(input_size, layers, Box::new(StdRng::from_entropy())));
+}
+
+/// Load the dense layers of v1.1 files
+///
+/// * `reader` - Source
+/// * `file_header` - File header
+fn load_version_1_1_layers<R: Read>(reader: &mut R, file_header: &FileHeader) -> Result<NeuralNetwork> {
+    // The file header ends with the activation function shared by every layer
+    let mut activation_buffer: [u8; 1] = [0; 1];
+    reader.read_exact(&mut activation_buffer)?;
+    let activation = ActivationFunction::from_id(activation_buffer[0], None)
+        .ok_or(GraymatError::UnknownActivation(activation_buffer[0]))?;
 
-    let mut loaded_weights: Vec<Array2<f32>> = Vec::with_capacity(file_header.number_of_layers as usize);
-    let mut loaded_biases: Vec<Array2<f32>> = Vec::with_capacity(file_header.number_of_layers as usize);
+    let number_of_layers = file_header.number_of_layers as usize;
+    let mut loaded_weights: Vec<Array2<f32>> = Vec::with_capacity(number_of_layers);
+    let mut loaded_biases: Vec<Array2<f32>> = Vec::with_capacity(number_of_layers);
+
+    for _ in 0..number_of_layers {
+
+        let mut layer_header_buffer: [u8; LEGACY_LAYER_HEADER_SIZE_BYTES as usize] = [0; LEGACY_LAYER_HEADER_SIZE_BYTES as usize];
+        reader.read_exact(&mut layer_header_buffer)?;
+        let layer_header: LegacyLayerHeader = bincode::deserialize(&layer_header_buffer)?;
+        check_layer_header(&layer_header)?;
+
+        let weights_buffer = read_exact_bytes(reader, layer_header.weights_size_bytes)?;
+        let biases_buffer = read_exact_bytes(reader, layer_header.biases_size_bytes)?;
+
+        load_layer_weights(&weights_buffer, &mut loaded_weights, &layer_header)?;
+
+        load_layer_biases(&biases_buffer, &mut loaded_biases, &layer_header)?;
+    }
 
-    for _i in 0..file_header.number_of_layers {
+    check_layer_shapes(&loaded_weights, &loaded_biases)?;
 
-        let layer_header = load_layer_header(&mut file);
+    return Ok(NeuralNetwork::from(loaded_weights, loaded_biases, activation));
+}
 
-        load_layer_weights(&mut file, &mut loaded_weights, &layer_header);
+/// Load the file header and check the file identifier, format version and declared sizes
+///
+/// * `reader` - Source
+fn load_file_header<R: Read>(reader: &mut R) -> Result<FileHeader> {
+    let mut file_header_buffer: [u8; FILE_HEADER_SIZE_BYTES as usize] = [0; FILE_HEADER_SIZE_BYTES as usize];
+    reader.read_exact(&mut file_header_buffer)?;
+    let file_header: FileHeader = bincode::deserialize(&file_header_buffer)?;
 
-        load_layer_biases(&mut file, &mut loaded_biases, &layer_header);
+    let mut expected_meta: [u8; META_SIZE] = [0; META_SIZE];
+    copy_string_into_byte_array(META, &mut expected_meta);
+    if file_header.meta != expected_meta {
+        return Err(GraymatError::BadMagic);
     }
 
-    let af_option = ActivationFunction::from_u8(file_header.activation_function); // TODO handle this error case
-    let mut activation: ActivationFunction = ActivationFunction::SIGMOID;
-    if !af_option.is_none() {
-       activation = af_option.unwrap();
+    let (header_size_bytes, layer_header_size_bytes) = match file_header.version {
+        VERSION_1_1 => (FILE_HEADER_SIZE_BYTES + 1, LEGACY_LAYER_HEADER_SIZE_BYTES),
+        VERSION_1_2 => (FILE_HEADER_SIZE_BYTES + INPUT_SIZE_BYTES, LAYER_HEADER_SIZE_BYTES),
+        version => return Err(GraymatError::UnsupportedVersion(version))
+    };
+    if file_header.header_size_bytes != header_size_bytes || file_header.layer_header_size_bytes != layer_header_size_bytes {
+        return Err(GraymatError::InvalidHeader(
+            format!("header sizes {}/{} do not match version {:#08x}",
+                    file_header.header_size_bytes, file_header.layer_header_size_bytes, file_header.version)));
+    }
+    if file_header.number_of_layers == 0 || file_header.number_of_layers > MAX_LAYERS {
+        return Err(GraymatError::InvalidHeader(
+            format!("{} layers declared, expected 1 to {}", file_header.number_of_layers, MAX_LAYERS)));
     }
+    return Ok(file_header);
+}
 
-    return NeuralNetwork::from(loaded_weights, loaded_biases, activation);
+/// Check the sizes declared in a v1.1 layer header before any layer data is read
+///
+/// * `layer_header` - Layer header
+fn check_layer_header(layer_header: &LegacyLayerHeader) -> Result<()> {
+    let weights = check_declared_size("layer weights", (layer_header.weight_rows as usize)
+        .checked_mul(layer_header.weight_cols as usize))? as u64;
+    check_declared_size("layer biases", Some(layer_header.biases as usize))?;
+    if layer_header.weights_size_bytes != BINCODE_VEC_LENGTH_BYTES + weights * F32_SIZE_BYTES
+        || layer_header.biases_size_bytes != BINCODE_VEC_LENGTH_BYTES + layer_header.biases as u64 * F32_SIZE_BYTES {
+        return Err(GraymatError::InvalidHeader(
+            format!("layer of {}x{} declares {} weight bytes and {} bias bytes",
+                    layer_header.weight_rows, layer_header.weight_cols,
+                    layer_header.weights_size_bytes, layer_header.biases_size_bytes)));
+    }
+    return Ok(());
 }
 
-/// Load Layer Network
+/// Check that each layer's biases match its weights and that each layer's inputs match the
+/// previous layer's outputs
 ///
-/// * `file` - Open file
-fn load_layer_header(file: &mut File) -> LayerHeader {
-    let mut layer_header_buffer: [u8; LAYER_HEADER_SIZE_BYTES as usize] = [0; LAYER_HEADER_SIZE_BYTES as usize];
-    file.read_exact(&mut layer_header_buffer).unwrap();
-    let layer_header: LayerHeader = bincode::deserialize(&layer_header_buffer).unwrap();
-    layer_header
+/// * `weights` - Loaded weights
+/// * `biases` - Loaded biases
+fn check_layer_shapes(weights: &[Array2<f32>], biases: &[Array2<f32>]) -> Result<()> {
+    for i in 0..weights.len() {
+        if biases[i].nrows() != weights[i].nrows() {
+            return Err(GraymatError::ShapeMismatch(
+                format!("layer {} has {} neurons but {} biases", i, weights[i].nrows(), biases[i].nrows())));
+        }
+        if i > 0 && weights[i].ncols() != weights[i - 1].nrows() {
+            return Err(GraymatError::ShapeMismatch(
+                format!("layer {} expects {} inputs but layer {} has {} neurons",
+                        i, weights[i].ncols(), i - 1, weights[i - 1].nrows())));
+        }
+    }
+    return Ok(());
+}
+
+/// Read a block of declared size. The buffer grows only as data is actually read, so a corrupted
+/// size on short data fails with [`GraymatError::TruncatedData`] instead of allocating the
+/// declared amount up front.
+///
+/// * `reader` - Source
+/// * `size_bytes` - Declared size
+fn read_exact_bytes<R: Read>(reader: &mut R, size_bytes: u64) -> Result<Vec<u8>> {
+    if size_bytes > MAX_SECTION_SIZE_BYTES {
+        return Err(GraymatError::InvalidHeader(
+            format!("{} byte block exceeds the {} byte limit", size_bytes, MAX_SECTION_SIZE_BYTES)));
+    }
+    let mut buffer: Vec<u8> = Vec::new();
+    reader.take(size_bytes).read_to_end(&mut buffer)?;
+    if (buffer.len() as u64) < size_bytes {
+        return Err(GraymatError::TruncatedData);
+    }
+    return Ok(buffer);
+}
+
+/// Load the optimizer section
+///
+/// * `reader` - Source
+fn load_optimizer<R: Read>(reader: &mut R) -> Result<Box<dyn Optimizer>> {
+    let mut optimizer_header_buffer: [u8; OPTIMIZER_HEADER_SIZE_BYTES as usize] = [0; OPTIMIZER_HEADER_SIZE_BYTES as usize];
+    reader.read_exact(&mut optimizer_header_buffer)?;
+    let optimizer_header: OptimizerHeader = bincode::deserialize(&optimizer_header_buffer)?;
+
+    let optimizer_type = OptimizerType::from_u8(optimizer_header.optimizer_type)
+        .ok_or(GraymatError::UnknownOptimizer(optimizer_header.optimizer_type))?;
+    let optimizer_buffer = read_exact_bytes(reader, optimizer_header.state_size_bytes)?;
+
+    return Ok(optimizer_from_bytes(optimizer_type, &optimizer_buffer)?);
+}
+
+/// Load the loss section
+///
+/// * `reader` - Source
+fn load_loss<R: Read>(reader: &mut R) -> Result<Box<dyn Loss>> {
+    let mut loss_header_buffer: [u8; LOSS_HEADER_SIZE_BYTES as usize] = [0; LOSS_HEADER_SIZE_BYTES as usize];
+    reader.read_exact(&mut loss_header_buffer)?;
+    let loss_header: LossHeader = bincode::deserialize(&loss_header_buffer)?;
+
+    let loss_type = LossType::from_u8(loss_header.loss_type)
+        .ok_or(GraymatError::UnknownLoss(loss_header.loss_type))?;
+    let loss_buffer = read_exact_bytes(reader, loss_header.parameters_size_bytes)?;
+
+    return loss_from_bytes(loss_type, &loss_buffer);
 }
 
 /// Load layer biases
 ///
-/// * `file` - Open file
+/// * `biases_buffer` - Serialized biases
 /// * `loaded_biases` - Vector of biases matrices that are loaded from file
 /// * `layer_header` - Layer header
-fn load_layer_biases(file: &mut File, loaded_biases: &mut Vec<Array2<f32>>, layer_header: &LayerHeader) {
-    let biases_bytes: usize = layer_header.biases_size_bytes as usize;
-    let mut biases_buffer: Vec<u8> = vec![0; biases_bytes];
-    file.read_exact(&mut biases_buffer).unwrap();
-
+fn load_layer_biases(biases_buffer: &[u8], loaded_biases: &mut Vec<Array2<f32>>, layer_header: &LegacyLayerHeader) -> Result<()> {
     let biases_shape: [usize; 2] = [layer_header.biases as usize, 1];
-    let biases_vec: Vec<f32> = bincode::deserialize(&biases_buffer).unwrap();
-    loaded_biases.push(Array2::from_shape_vec(biases_shape, biases_vec).unwrap());
+    let biases_vec: Vec<f32> = bincode::deserialize(biases_buffer)?;
+    loaded_biases.push(to_array2(biases_shape, biases_vec)?);
+    return Ok(());
 }
 
 /// Load layer weights
 ///
-/// * `file` - Open file
+/// * `weights_buffer` - Serialized weights
 /// * `loaded_weights` - Vector of weights matrices that are loaded from file
 /// * `layer_header` - Layer header
-fn load_layer_weights(file: &mut File, loaded_weights: &mut Vec<Array2<f32>>, layer_header: &LayerHeader) {
-    let weights_bytes: usize = layer_header.weights_size_bytes as usize;
-    let mut weights_buffer: Vec<u8> = vec![0; weights_bytes];
-    file.read_exact(&mut weights_buffer).unwrap();
-
+fn load_layer_weights(weights_buffer: &[u8], loaded_weights: &mut Vec<Array2<f32>>, layer_header: &LegacyLayerHeader) -> Result<()> {
     let weights_shape: [usize; 2] = [layer_header.weight_rows as usize, layer_header.weight_cols as usize];
-    let weights_vec: Vec<f32> = bincode::deserialize(&weights_buffer).unwrap();
-    loaded_weights.push(Array2::from_shape_vec(weights_shape, weights_vec).unwrap());
+    let weights_vec: Vec<f32> = bincode::deserialize(weights_buffer)?;
+    loaded_weights.push(to_array2(weights_shape, weights_vec)?);
+    return Ok(());
+}
+
+/// Build an Array2 from loaded values, checking the number of values against the declared shape
+///
+/// * `shape` - Declared shape
+/// * `values` - Loaded values
+fn to_array2(shape: [usize; 2], values: Vec<f32>) -> Result<Array2<f32>> {
+    let number_of_values = values.len();
+    return Array2::from_shape_vec(shape, values).map_err(|_| GraymatError::ShapeMismatch(
+        format!("declared {}x{} but found {} values", shape[0], shape[1], number_of_values)));
+}
+
+// Legacy layer header
+
+const LEGACY_LAYER_HEADER_SIZE_BYTES: u64 = 28;
+
+/// Layer header of v1.1 files
+#[derive(Debug, Serialize, Deserialize)]
+struct LegacyLayerHeader {
+    weight_rows: u32,
+    weight_cols: u32,
+    biases: u32,
+    weights_size_bytes: u64,
+    biases_size_bytes: u64
 }
@@ -0,0 +1,396 @@
+use ndarray::{Array2, Axis, Zip};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use crate::error::{GraymatError, Result};
+use crate::layer::{check_declared_size, Layer, LayerType, Parameter};
+use crate::normalization::NormalizationType::{BATCH, LAYER};
+use crate::utilities::array2_utils;
+
+const DEFAULT_MOMENTUM: f32 = 0.9;
+const DEFAULT_EPSILON: f32 = 1e-5;
+
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum NormalizationType {
+    BATCH = 1,
+    LAYER = 2,
+}
+
+impl NormalizationType {
+    /// Get normalization type from u8
+    ///
+    /// * `val` - u8 value
+    /// * `returns` - Option some if val is matched else None
+    pub fn from_u8(val: u8) -> Option<Self> {
+        let normalizations = [ BATCH, LAYER ];
+        return normalizations.into_iter().find(|n| (*n as u8) == val);
+    }
+
+    /// Get the normalization type as a string
+    ///
+    /// * `normalization` - normalization type
+    pub fn convert_to_string(normalization: NormalizationType) -> String {
+        return match normalization {
+            BATCH => "Batch Normalization".to_owned(),
+            LAYER => "Layer Normalization".to_owned()
+        };
+    }
+
+    /// Axis statistics are taken over. Batch normalization normalizes every neuron over the
+    /// batch, layer normalization normalizes every example over the neurons.
+    fn axis(&self) -> Axis {
+        return match self {
+            BATCH => Axis(1),
+            LAYER => Axis(0)
+        };
+    }
+}
+
+/// Normalization of a layer's pre-activations, `y = scale * (z - mean) / sqrt(variance + epsilon) + shift`,
+/// with a learnable scale and shift per neuron.
+///
+/// Batch normalization uses the statistics of the current batch while training and keeps a
+/// running mean and variance that replace them when the network is evaluated. Layer
+/// normalization uses the statistics of every example on its own, so training and evaluation
+/// behave the same.
+///
+/// Normalization is either set on a dense layer (see
+/// [`crate::neural_network::NeuralNetwork::set_layer_normalization`]) or used as a layer of its
+/// own.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Normalization {
+    normalization_type: NormalizationType,
+    momentum: f32,
+    epsilon: f32,
+    scale: Array2<f32>,
+    shift: Array2<f32>,
+    running_mean: Array2<f32>,
+    running_variance: Array2<f32>,
+    #[serde(skip)]
+    cache: Option<NormalizationCache>,
+    #[serde(skip)]
+    scale_gradient: Array2<f32>,
+    #[serde(skip)]
+    shift_gradient: Array2<f32>
+}
+
+/// Values cached by the forward pass for back propagation. The buffers are reused by every
+/// training step of the same batch size.
+#[derive(Debug, Clone, Default)]
+struct NormalizationCache {
+    /// Normalized pre-activations, before the scale and shift (neurons x batch)
+    normalized: Array2<f32>,
+    /// `1 / sqrt(variance + epsilon)`, one value per neuron (batch) or per example (layer)
+    inverse_std: Array2<f32>,
+    mean: Array2<f32>,
+    variance: Array2<f32>,
+    /// Means of the back propagated gradient and of its product with the normalized values
+    mean_delta: Array2<f32>,
+    mean_delta_x: Array2<f32>
+}
+
+impl Normalization {
+    /// Batch normalization with a momentum of 0.9 and an epsilon of 1e-5
+    ///
+    /// * `neurons` - Number of layer neurons
+    pub fn batch(neurons: usize) -> Self {
+        return Normalization::new(BATCH, neurons, DEFAULT_MOMENTUM, DEFAULT_EPSILON);
+    }
+
+    /// Layer normalization with an epsilon of 1e-5
+    ///
+    /// * `neurons` - Number of layer neurons
+    pub fn layer(neurons: usize) -> Self {
+        return Normalization::new(LAYER, neurons, DEFAULT_MOMENTUM, DEFAULT_EPSILON);
+    }
+
+    /// Constructor. The scale starts at 1, the shift at 0, the running mean at 0 and the
+    /// running variance at 1.
+    ///
+    /// * `normalization_type` - Batch or layer normalization
+    /// * `neurons` - Number of layer neurons
+    /// * `momentum` - Fraction of the running statistics kept after every training batch. Only
+    ///                used by batch normalization.
+    /// * `epsilon` - Added to the variance to avoid dividing by 0
+    pub fn new(normalization_type: NormalizationType, neurons: usize, momentum: f32, epsilon: f32) -> Self {
+        return Normalization {
+            normalization_type,
+            momentum,
+            epsilon,
+            scale: Array2::ones((neurons, 1)),
+            shift: Array2::zeros((neurons, 1)),
+            running_mean: Array2::zeros((neurons, 1)),
+            running_variance: Array2::ones((neurons, 1)),
+            cache: None,
+            scale_gradient: Array2::zeros((neurons, 1)),
+            shift_gradient: Array2::zeros((neurons, 1))
+        };
+    }
+
+    /// Normalize pre-activations for evaluation, in place. Batch normalization uses the running
+    /// statistics.
+    ///
+    /// * `z` - Pre-activations (neurons x batch)
+    pub(crate) fn evaluate_inplace(&self, z: &mut Array2<f32>) {
+        if self.normalization_type == BATCH {
+            let inverse_std = self.running_variance.mapv(|v| 1.0 / (v + self.epsilon).sqrt());
+            *z -= &self.running_mean;
+            *z *= &inverse_std;
+        } else {
+            let mut statistics = NormalizationCache::default();
+            self.normalize(z, &mut statistics);
+        }
+        *z *= &self.scale;
+        *z += &self.shift;
+    }
+
+    /// Normalize pre-activations for training, in place, using the statistics of the batch.
+    /// Batch normalization blends the batch statistics into its running statistics.
+    ///
+    /// * `z` - Pre-activations (neurons x batch)
+    pub(crate) fn train_inplace(&mut self, z: &mut Array2<f32>) {
+        let mut cache = self.cache.take().unwrap_or_default();
+        self.normalize(z, &mut cache);
+        array2_utils::assign_to_buffer(&mut cache.normalized, z);
+        self.update_running_statistics(&cache);
+        self.cache = Some(cache);
+        *z *= &self.scale;
+        *z += &self.shift;
+    }
+
+    /// Shape of the statistics of `z`, one value per neuron (batch) or per example (layer)
+    ///
+    /// * `z` - Pre-activations (neurons x batch)
+    fn statistics_shape(&self, z: &Array2<f32>) -> (usize, usize) {
+        return match self.normalization_type {
+            BATCH => (z.nrows(), 1),
+            LAYER => (1, z.ncols())
+        };
+    }
+
+    /// Normalize `z` in place with its own statistics along the normalization axis. The mean,
+    /// variance and inverse standard deviation are written into the buffers of `statistics`.
+    fn normalize(&self, z: &mut Array2<f32>, statistics: &mut NormalizationCache) {
+        let axis = self.normalization_type.axis();
+        let shape = self.statistics_shape(z);
+        array2_utils::fit_buffer(&mut statistics.mean, shape);
+        array2_utils::fit_buffer(&mut statistics.variance, shape);
+        array2_utils::fit_buffer(&mut statistics.inverse_std, shape);
+
+        Zip::from(z.lanes(axis)).and(statistics.mean.lanes_mut(axis)).for_each(|lane, mut mean| {
+            mean[0] = lane.sum() / lane.len() as f32;
+        });
+        *z -= &statistics.mean;
+        Zip::from(z.lanes(axis)).and(statistics.variance.lanes_mut(axis)).for_each(|lane, mut variance| {
+            variance[0] = lane.dot(&lane) / lane.len() as f32;
+        });
+        let epsilon = self.epsilon;
+        Zip::from(&mut statistics.inverse_std).and(&statistics.variance).for_each(|s, &v| *s = 1.0 / (v + epsilon).sqrt());
+        *z *= &statistics.inverse_std;
+    }
+
+    /// Back propagate through the normalization of the last training pass. The scale and shift
+    /// gradients are summed over the batch.
+    ///
+    /// * `delta` - Loss gradient with respect to the normalization output. Overwritten with the
+    ///             gradient with respect to its input.
+    pub(crate) fn backward_inplace(&mut self, delta: &mut Array2<f32>) {
+        let cache = self.cache.as_mut().expect("Normalization back propagated before a training pass");
+        array2_utils::fit_buffer(&mut self.scale_gradient, self.scale.dim());
+        array2_utils::fit_buffer(&mut self.shift_gradient, self.shift.dim());
+        Zip::from(self.scale_gradient.rows_mut()).and(self.shift_gradient.rows_mut())
+            .and(delta.rows()).and(cache.normalized.rows())
+            .for_each(|mut scale, mut shift, d, x| {
+                scale[0] = d.dot(&x);
+                shift[0] = d.sum();
+            });
+
+        // dz = inverse_std * (dx - mean(dx) - x * mean(dx * x)), dx being the gradient with
+        // respect to the normalized values and both means taken along the normalization axis
+        let axis = self.normalization_type.axis();
+        let shape = cache.mean.dim();
+        array2_utils::fit_buffer(&mut cache.mean_delta, shape);
+        array2_utils::fit_buffer(&mut cache.mean_delta_x, shape);
+        *delta *= &self.scale;
+        Zip::from(delta.lanes(axis)).and(cache.normalized.lanes(axis))
+            .and(cache.mean_delta.lanes_mut(axis)).and(cache.mean_delta_x.lanes_mut(axis))
+            .for_each(|d, x, mut mean_delta, mut mean_delta_x| {
+                mean_delta[0] = d.sum() / d.len() as f32;
+                mean_delta_x[0] = d.dot(&x) / d.len() as f32;
+            });
+        *delta -= &cache.mean_delta;
+        Zip::from(&mut *delta).and(&cache.normalized).and_broadcast(&cache.mean_delta_x)
+            .for_each(|d, &x, &m| *d -= x * m);
+        *delta *= &cache.inverse_std;
+    }
+
+    /// Blend the statistics of a training batch into the running statistics. Does nothing for
+    /// layer normalization.
+    ///
+    /// * `cache` - Batch statistics
+    fn update_running_statistics(&mut self, cache: &NormalizationCache) {
+        if self.normalization_type != BATCH {
+            return;
+        }
+        let momentum = self.momentum;
+        Zip::from(&mut self.running_mean).and(&cache.mean).for_each(|r, &m| *r = momentum * *r + (1.0 - momentum) * m);
+        Zip::from(&mut self.running_variance).and(&cache.variance).for_each(|r, &v| *r = momentum * *r + (1.0 - momentum) * v);
+    }
+
+    /// True if every array has one row per neuron and one column
+    ///
+    /// * `neurons` - Number of layer neurons
+    pub(crate) fn fits(&self, neurons: usize) -> bool {
+        return [&self.scale, &self.shift, &self.running_mean, &self.running_variance].iter()
+            .all(|arr| arr.dim() == (neurons, 1));
+    }
+
+    ///
+    /// Get normalization type
+    ///
+    pub fn normalization_type(&self) -> NormalizationType {
+        self.normalization_type
+    }
+
+    ///
+    /// Get learnable scale (neurons x 1)
+    ///
+    pub fn scale(&self) -> &Array2<f32> {
+        &self.scale
+    }
+
+    ///
+    /// Get learnable scale (mutable)
+    ///
+    pub fn scale_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.scale
+    }
+
+    ///
+    /// Get learnable shift (neurons x 1)
+    ///
+    pub fn shift(&self) -> &Array2<f32> {
+        &self.shift
+    }
+
+    ///
+    /// Get learnable shift (mutable)
+    ///
+    pub fn shift_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.shift
+    }
+
+    ///
+    /// Get running mean used by batch normalization at evaluation (neurons x 1)
+    ///
+    pub fn running_mean(&self) -> &Array2<f32> {
+        &self.running_mean
+    }
+
+    ///
+    /// Get running variance used by batch normalization at evaluation (neurons x 1)
+    ///
+    pub fn running_variance(&self) -> &Array2<f32> {
+        &self.running_variance
+    }
+
+    /// Check a restored normalization: every array has one value per neuron, the momentum is in
+    /// [0, 1] and epsilon is a positive number
+    ///
+    /// * `neurons` - Number of layer neurons
+    pub(crate) fn validate(&self, neurons: usize) -> Result<()> {
+        check_declared_size("normalization", Some(neurons))?;
+        if !self.fits(neurons) {
+            return Err(GraymatError::ShapeMismatch(
+                format!("normalization parameters do not all have {} values", neurons)));
+        }
+        if !(0.0..=1.0).contains(&self.momentum) {
+            return Err(GraymatError::InvalidHeader(
+                format!("normalization momentum {} is outside [0, 1]", self.momentum)));
+        }
+        if !(self.epsilon > 0.0 && self.epsilon.is_finite()) {
+            return Err(GraymatError::InvalidHeader(
+                format!("normalization epsilon {} is not a positive number", self.epsilon)));
+        }
+        return Ok(());
+    }
+
+    /// Restore a normalization serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized normalization
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut normalization: Normalization = bincode::deserialize(bytes)?;
+        let neurons = normalization.scale.nrows();
+        normalization.validate(neurons)?;
+        normalization.scale_gradient = Array2::zeros((neurons, 1));
+        normalization.shift_gradient = Array2::zeros((neurons, 1));
+        return Ok(normalization);
+    }
+}
+
+impl PartialEq for Normalization {
+    /// Normalizations are equal if their configuration, parameters and running statistics are
+    fn eq(&self, other: &Self) -> bool {
+        return self.normalization_type == other.normalization_type
+            && self.momentum == other.momentum
+            && self.epsilon == other.epsilon
+            && self.scale == other.scale
+            && self.shift == other.shift
+            && self.running_mean == other.running_mean
+            && self.running_variance == other.running_variance;
+    }
+}
+
+impl Layer for Normalization {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        let mut output = input.clone();
+        self.evaluate_inplace(&mut output);
+        return output;
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        let mut output = input.clone();
+        self.train_inplace(&mut output);
+        return output;
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let mut error = output_gradient.clone();
+        self.backward_inplace(&mut error);
+        return error;
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.scale, &self.shift];
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.scale_gradient, &self.shift_gradient];
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        return vec![
+            Parameter { value: &mut self.scale, gradient: &mut self.scale_gradient, regularize: false, rows: None },
+            Parameter { value: &mut self.shift, gradient: &mut self.shift_gradient, regularize: false, rows: None }
+        ];
+    }
+
+    fn buffers(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.running_mean, &self.running_variance];
+    }
+
+    fn buffers_mut(&mut self) -> Vec<&mut Array2<f32>> {
+        return vec![&mut self.running_mean, &mut self.running_variance];
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        return (input_size == self.scale.nrows()).then_some(input_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::NORMALIZATION;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
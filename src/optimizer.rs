@@ -0,0 +1,466 @@
+use ndarray::{Array2, Zip};
+use serde::{Serialize, Deserialize};
+use crate::optimizer::OptimizerType::{ADAM, ADAMW, MOMENTUM, NESTEROV, RMSPROP, SGD};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OptimizerType {
+    SGD = 1,
+    MOMENTUM = 2,
+    NESTEROV = 3,
+    RMSPROP = 4,
+    ADAM = 5,
+    ADAMW = 6,
+}
+
+impl OptimizerType {
+    /// Get optimizer type from u8
+    ///
+    /// * `val` - u8 value
+    /// * `returns` - Option some if val is matched else None
+    pub fn from_u8(val: u8) -> Option<Self> {
+        let optimizers = [ SGD, MOMENTUM, NESTEROV, RMSPROP, ADAM, ADAMW ];
+        return optimizers.into_iter().find(|o| (*o as u8) == val);
+    }
+
+    /// Get the optimizer type as a string
+    ///
+    /// * `optimizer` - optimizer type
+    pub fn convert_to_string(optimizer: OptimizerType) -> String {
+        return match optimizer {
+            SGD => "SGD".to_owned(),
+            MOMENTUM => "Momentum".to_owned(),
+            NESTEROV => "Nesterov".to_owned(),
+            RMSPROP => "RMSProp".to_owned(),
+            ADAM => "Adam".to_owned(),
+            ADAMW => "AdamW".to_owned()
+        };
+    }
+}
+
+/// An optimizer decides how a gradient is applied to a network parameter.
+///
+/// Every trainable parameter in a network is identified by a stable index. Indices count the
+/// parameters of every layer in order, as returned by [`crate::layer::Layer::parameters`]. For a
+/// network built from dense layers without normalization, the weights of layer `i` have index
+/// `2 * i` and the biases `2 * i + 1`.
+/// Optimizers that keep per-parameter state (velocity, moment estimates) key it by this index.
+pub trait Optimizer {
+    /// Apply a gradient to a parameter in place
+    ///
+    /// * `index` - Parameter index
+    /// * `parameter` - Parameter to update
+    /// * `gradient` - Gradient of the cost with respect to the parameter, averaged over the batch
+    /// * `regularize` - True if weight decay applies to the parameter. Biases and normalization
+    ///                  parameters are not decayed.
+    /// * `learning_rate` - Learning rate
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, regularize: bool, learning_rate: f32);
+
+    /// Apply a gradient to some rows of a parameter in place. Rows that are not listed keep their
+    /// value and optimizer state, so a sparse parameter such as an embedding table only moves the
+    /// vectors that were looked up. The default applies a dense [`Optimizer::update`].
+    ///
+    /// * `index` - Parameter index
+    /// * `parameter` - Parameter to update
+    /// * `gradient` - Gradient of the cost with respect to the parameter, averaged over the batch
+    /// * `rows` - Rows to update
+    /// * `regularize` - True if weight decay applies to the parameter
+    /// * `learning_rate` - Learning rate
+    fn update_rows(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, _rows: &[usize], regularize: bool,
+                   learning_rate: f32) {
+        self.update(index, parameter, gradient, regularize, learning_rate);
+    }
+
+    /// Optimizer type, used to restore the optimizer from a .gnm file
+    fn optimizer_type(&self) -> OptimizerType;
+
+    /// Serialize the optimizer hyper-parameters and per-parameter state
+    fn to_bytes(&self) -> Vec<u8>;
+}
+
+/// Restore an optimizer that was serialized with [`Optimizer::to_bytes`]
+///
+/// * `optimizer_type` - Optimizer type
+/// * `bytes` - Serialized optimizer
+pub fn optimizer_from_bytes(optimizer_type: OptimizerType, bytes: &[u8]) -> bincode::Result<Box<dyn Optimizer>> {
+    return Ok(match optimizer_type {
+        SGD => Box::new(bincode::deserialize::<StochasticGradientDescent>(bytes)?),
+        MOMENTUM => Box::new(bincode::deserialize::<Momentum>(bytes)?),
+        NESTEROV => Box::new(bincode::deserialize::<Nesterov>(bytes)?),
+        RMSPROP => Box::new(bincode::deserialize::<RMSProp>(bytes)?),
+        ADAM => Box::new(bincode::deserialize::<Adam>(bytes)?),
+        ADAMW => Box::new(bincode::deserialize::<AdamW>(bytes)?)
+    });
+}
+
+/// Get the state array for a parameter, creating a zeroed array if it does not exist yet or if
+/// the parameter has changed shape.
+///
+/// * `states` - Per-parameter state
+/// * `index` - Parameter index
+/// * `shape` - Parameter shape
+fn parameter_state(states: &mut Vec<Array2<f32>>, index: usize, shape: (usize, usize)) -> &mut Array2<f32> {
+    if states.len() <= index {
+        states.resize(index + 1, Array2::zeros((0, 0)));
+    }
+    if states[index].dim() != shape {
+        states[index] = Array2::zeros(shape);
+    }
+    return &mut states[index];
+}
+
+/// Run `step` on every element of a parameter and its gradient, or only on the elements of `rows`
+fn apply(parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: Option<&[usize]>, mut step: impl FnMut(&mut f32, f32)) {
+    match rows {
+        None => Zip::from(parameter).and(gradient).for_each(|p, &g| step(p, g)),
+        Some(rows) => for &row in rows {
+            Zip::from(parameter.row_mut(row)).and(gradient.row(row)).for_each(|p, &g| step(p, g));
+        }
+    }
+}
+
+/// [`apply`] with one state array per parameter
+fn apply_with_state(parameter: &mut Array2<f32>, state: &mut Array2<f32>, gradient: &Array2<f32>, rows: Option<&[usize]>,
+                    mut step: impl FnMut(&mut f32, &mut f32, f32)) {
+    match rows {
+        None => Zip::from(parameter).and(state).and(gradient).for_each(|p, s, &g| step(p, s, g)),
+        Some(rows) => for &row in rows {
+            Zip::from(parameter.row_mut(row)).and(state.row_mut(row)).and(gradient.row(row))
+                .for_each(|p, s, &g| step(p, s, g));
+        }
+    }
+}
+
+/// [`apply`] with two state arrays per parameter
+fn apply_with_states(parameter: &mut Array2<f32>, first: &mut Array2<f32>, second: &mut Array2<f32>, gradient: &Array2<f32>,
+                     rows: Option<&[usize]>, mut step: impl FnMut(&mut f32, &mut f32, &mut f32, f32)) {
+    match rows {
+        None => Zip::from(parameter).and(first).and(second).and(gradient).for_each(|p, m, v, &g| step(p, m, v, g)),
+        Some(rows) => for &row in rows {
+            Zip::from(parameter.row_mut(row)).and(first.row_mut(row)).and(second.row_mut(row)).and(gradient.row(row))
+                .for_each(|p, m, v, &g| step(p, m, v, g));
+        }
+    }
+}
+
+/// Plain stochastic gradient descent
+///
+/// `p = p - lr * g`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StochasticGradientDescent {}
+
+impl StochasticGradientDescent {
+    pub fn new() -> Self {
+        return Self {};
+    }
+}
+
+impl Optimizer for StochasticGradientDescent {
+    fn update(&mut self, _index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, _regularize: bool, learning_rate: f32) {
+        parameter.scaled_add(-learning_rate, gradient);
+    }
+
+    fn update_rows(&mut self, _index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: &[usize], _regularize: bool,
+                   learning_rate: f32) {
+        apply(parameter, gradient, Some(rows), |p, g| *p -= learning_rate * g);
+    }
+
+    fn optimizer_type(&self) -> OptimizerType {
+        SGD
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Stochastic gradient descent with momentum
+///
+/// `v = momentum * v + g`
+/// `p = p - lr * v`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Momentum {
+    momentum: f32,
+    velocities: Vec<Array2<f32>>
+}
+
+impl Momentum {
+    /// * `momentum` - Fraction of the previous velocity carried into each update
+    pub fn new(momentum: f32) -> Self {
+        return Self { momentum, velocities: Vec::new() };
+    }
+
+    /// Apply a gradient to every row of a parameter, or only to `rows`
+    fn step(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: Option<&[usize]>, learning_rate: f32) {
+        let momentum = self.momentum;
+        let velocity = parameter_state(&mut self.velocities, index, parameter.dim());
+        apply_with_state(parameter, velocity, gradient, rows, |p, v, g| {
+            *v = momentum * *v + g;
+            *p -= learning_rate * *v;
+        });
+    }
+}
+
+impl Default for Momentum {
+    fn default() -> Self {
+        return Momentum::new(0.9);
+    }
+}
+
+impl Optimizer for Momentum {
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, _regularize: bool, learning_rate: f32) {
+        self.step(index, parameter, gradient, None, learning_rate);
+    }
+
+    fn update_rows(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: &[usize], _regularize: bool,
+                   learning_rate: f32) {
+        self.step(index, parameter, gradient, Some(rows), learning_rate);
+    }
+
+    fn optimizer_type(&self) -> OptimizerType {
+        MOMENTUM
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Stochastic gradient descent with Nesterov momentum
+///
+/// `v = momentum * v + g`
+/// `p = p - lr * (g + momentum * v)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Nesterov {
+    momentum: f32,
+    velocities: Vec<Array2<f32>>
+}
+
+impl Nesterov {
+    /// * `momentum` - Fraction of the previous velocity carried into each update
+    pub fn new(momentum: f32) -> Self {
+        return Self { momentum, velocities: Vec::new() };
+    }
+
+    /// Apply a gradient to every row of a parameter, or only to `rows`
+    fn step(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: Option<&[usize]>, learning_rate: f32) {
+        let momentum = self.momentum;
+        let velocity = parameter_state(&mut self.velocities, index, parameter.dim());
+        apply_with_state(parameter, velocity, gradient, rows, |p, v, g| {
+            *v = momentum * *v + g;
+            *p -= learning_rate * (g + momentum * *v);
+        });
+    }
+}
+
+impl Default for Nesterov {
+    fn default() -> Self {
+        return Nesterov::new(0.9);
+    }
+}
+
+impl Optimizer for Nesterov {
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, _regularize: bool, learning_rate: f32) {
+        self.step(index, parameter, gradient, None, learning_rate);
+    }
+
+    fn update_rows(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: &[usize], _regularize: bool,
+                   learning_rate: f32) {
+        self.step(index, parameter, gradient, Some(rows), learning_rate);
+    }
+
+    fn optimizer_type(&self) -> OptimizerType {
+        NESTEROV
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// RMSProp
+///
+/// `s = decay * s + (1 - decay) * g^2`
+/// `p = p - lr * g / (sqrt(s) + epsilon)`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RMSProp {
+    decay: f32,
+    epsilon: f32,
+    mean_squares: Vec<Array2<f32>>
+}
+
+impl RMSProp {
+    /// * `decay` - Decay rate of the running mean of squared gradients
+    /// * `epsilon` - Small constant that prevents division by zero
+    pub fn new(decay: f32, epsilon: f32) -> Self {
+        return Self { decay, epsilon, mean_squares: Vec::new() };
+    }
+
+    /// Apply a gradient to every row of a parameter, or only to `rows`
+    fn step(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: Option<&[usize]>, learning_rate: f32) {
+        let decay = self.decay;
+        let epsilon = self.epsilon;
+        let mean_square = parameter_state(&mut self.mean_squares, index, parameter.dim());
+        apply_with_state(parameter, mean_square, gradient, rows, |p, s, g| {
+            *s = decay * *s + (1.0 - decay) * g * g;
+            *p -= learning_rate * g / (s.sqrt() + epsilon);
+        });
+    }
+}
+
+impl Default for RMSProp {
+    fn default() -> Self {
+        return RMSProp::new(0.9, 1e-7);
+    }
+}
+
+impl Optimizer for RMSProp {
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, _regularize: bool, learning_rate: f32) {
+        self.step(index, parameter, gradient, None, learning_rate);
+    }
+
+    fn update_rows(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: &[usize], _regularize: bool,
+                   learning_rate: f32) {
+        self.step(index, parameter, gradient, Some(rows), learning_rate);
+    }
+
+    fn optimizer_type(&self) -> OptimizerType {
+        RMSPROP
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Bias corrected first and second moment estimates shared by [`Adam`] and [`AdamW`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct AdamMoments {
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    first_moments: Vec<Array2<f32>>,
+    second_moments: Vec<Array2<f32>>,
+    timesteps: Vec<i32>
+}
+
+impl AdamMoments {
+    fn new(beta1: f32, beta2: f32, epsilon: f32) -> Self {
+        return Self {
+            beta1,
+            beta2,
+            epsilon,
+            first_moments: Vec::new(),
+            second_moments: Vec::new(),
+            timesteps: Vec::new()
+        };
+    }
+
+    /// Apply the Adam update to every row of a parameter, or only to `rows`, first applying
+    /// `p = p - lr * weight_decay * p`
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: Option<&[usize]>,
+              learning_rate: f32, weight_decay: f32) {
+        if self.timesteps.len() <= index {
+            self.timesteps.resize(index + 1, 0);
+        }
+        self.timesteps[index] += 1;
+        let t = self.timesteps[index];
+
+        let (beta1, beta2, epsilon) = (self.beta1, self.beta2, self.epsilon);
+        let first_correction = 1.0 - beta1.powi(t);
+        let second_correction = 1.0 - beta2.powi(t);
+        let m = parameter_state(&mut self.first_moments, index, parameter.dim());
+        let v = parameter_state(&mut self.second_moments, index, parameter.dim());
+        apply_with_states(parameter, m, v, gradient, rows, |p, m, v, g| {
+            *m = beta1 * *m + (1.0 - beta1) * g;
+            *v = beta2 * *v + (1.0 - beta2) * g * g;
+            let m_hat = *m / first_correction;
+            let v_hat = *v / second_correction;
+            *p -= learning_rate * (weight_decay * *p + m_hat / (v_hat.sqrt() + epsilon));
+        });
+    }
+}
+
+/// Adam (adaptive moment estimation)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Adam {
+    moments: AdamMoments
+}
+
+impl Adam {
+    /// * `beta1` - Decay rate of the first moment estimate
+    /// * `beta2` - Decay rate of the second moment estimate
+    /// * `epsilon` - Small constant that prevents division by zero
+    pub fn new(beta1: f32, beta2: f32, epsilon: f32) -> Self {
+        return Self { moments: AdamMoments::new(beta1, beta2, epsilon) };
+    }
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        return Adam::new(0.9, 0.999, 1e-8);
+    }
+}
+
+impl Optimizer for Adam {
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, _regularize: bool, learning_rate: f32) {
+        self.moments.update(index, parameter, gradient, None, learning_rate, 0.0);
+    }
+
+    fn update_rows(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: &[usize], _regularize: bool,
+                   learning_rate: f32) {
+        self.moments.update(index, parameter, gradient, Some(rows), learning_rate, 0.0);
+    }
+
+    fn optimizer_type(&self) -> OptimizerType {
+        ADAM
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Adam with decoupled weight decay. Only parameters the network regularizes are decayed, so
+/// biases and normalization parameters are left alone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdamW {
+    moments: AdamMoments,
+    weight_decay: f32
+}
+
+impl AdamW {
+    /// * `beta1` - Decay rate of the first moment estimate
+    /// * `beta2` - Decay rate of the second moment estimate
+    /// * `epsilon` - Small constant that prevents division by zero
+    /// * `weight_decay` - Weight decay coefficient, applied directly to the parameter
+    pub fn new(beta1: f32, beta2: f32, epsilon: f32, weight_decay: f32) -> Self {
+        return Self { moments: AdamMoments::new(beta1, beta2, epsilon), weight_decay };
+    }
+}
+
+impl Default for AdamW {
+    fn default() -> Self {
+        return AdamW::new(0.9, 0.999, 1e-8, 0.01);
+    }
+}
+
+impl Optimizer for AdamW {
+    fn update(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, regularize: bool, learning_rate: f32) {
+        let weight_decay = if regularize { self.weight_decay } else { 0.0 };
+        self.moments.update(index, parameter, gradient, None, learning_rate, weight_decay);
+    }
+
+    fn update_rows(&mut self, index: usize, parameter: &mut Array2<f32>, gradient: &Array2<f32>, rows: &[usize], regularize: bool,
+                   learning_rate: f32) {
+        let weight_decay = if regularize { self.weight_decay } else { 0.0 };
+        self.moments.update(index, parameter, gradient, Some(rows), learning_rate, weight_decay);
+    }
+
+    fn optimizer_type(&self) -> OptimizerType {
+        ADAMW
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
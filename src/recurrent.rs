@@ -0,0 +1,509 @@
+use ndarray::{concatenate, s, Array2, ArrayView2, Axis};
+use rand::RngCore;
+use serde::{Serialize, Deserialize};
+use crate::column_vector::ColumnVector;
+use crate::error::{GraymatError, Result};
+use crate::initializer::Initializer;
+use crate::layer::{check_declared_size, Layer, LayerType, Parameter};
+use crate::recurrent::RecurrentType::{GRU, LSTM, RNN};
+use crate::utilities::math_utils;
+
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecurrentType {
+    RNN = 1,
+    LSTM = 2,
+    GRU = 3,
+}
+
+impl RecurrentType {
+    /// Get recurrent type from u8
+    ///
+    /// * `val` - u8 value
+    /// * `returns` - Option some if val is matched else None
+    pub fn from_u8(val: u8) -> Option<Self> {
+        let recurrent_types = [ RNN, LSTM, GRU ];
+        return recurrent_types.into_iter().find(|r| (*r as u8) == val);
+    }
+
+    /// Get the recurrent type as a string
+    ///
+    /// * `recurrent_type` - recurrent type
+    pub fn convert_to_string(recurrent_type: RecurrentType) -> String {
+        return match recurrent_type {
+            RNN => "RNN".to_owned(),
+            LSTM => "LSTM".to_owned(),
+            GRU => "GRU".to_owned()
+        };
+    }
+
+    /// Number of gates, each with its own block of `hidden_size` rows in the stacked weights
+    fn gates(&self) -> usize {
+        return match self {
+            RNN => 1,
+            LSTM => 4,
+            GRU => 3
+        };
+    }
+}
+
+/// Concatenate a sequence of per-step vectors into the single column a recurrent layer takes.
+/// Step `t` of a sequence of `d` features occupies rows `t * d..(t + 1) * d`.
+///
+/// * `sequence` - Per-step vectors of equal size
+pub fn sequence_to_column(sequence: &[ColumnVector]) -> ColumnVector {
+    let steps: Vec<ArrayView2<f32>> = sequence.iter().map(|step| step.get_data().view()).collect();
+    return ColumnVector::from(&concatenate(Axis(0), &steps).expect("Sequence steps must have the same size"));
+}
+
+/// Split a column, for example the output of a recurrent layer that returns sequences, into
+/// per-step vectors
+///
+/// * `column` - Concatenated steps
+/// * `step_size` - Number of values per step
+pub fn column_to_sequence(column: &ColumnVector, step_size: usize) -> Vec<ColumnVector> {
+    assert!(step_size > 0 && column.size().is_multiple_of(step_size),
+            "A column of {} values cannot be split into steps of {}", column.size(), step_size);
+    return column.get_data().exact_chunks((step_size, 1)).into_iter()
+        .map(|step| ColumnVector::from(&step.to_owned()))
+        .collect();
+}
+
+/// Values of one time step, cached by the forward pass for back propagation
+#[derive(Debug, Clone)]
+struct RecurrentStep {
+    input: Array2<f32>,
+    previous_hidden: Array2<f32>,
+    previous_cell: Array2<f32>,
+    /// Activated gates, stacked like the weights
+    gates: Array2<f32>,
+    /// Recurrent contribution to the gates, `recurrent_weights * previous_hidden`
+    recurrent: Array2<f32>,
+    cell: Array2<f32>,
+    hidden: Array2<f32>
+}
+
+/// Recurrent layer over fixed length sequences
+///
+/// A sequence of `sequence_length` steps of `input_size` features is stored as one column (see
+/// [`sequence_to_column`]). The layer outputs the hidden state of the last step, or the hidden
+/// states of every step concatenated the same way.
+///
+/// The weights of every gate are stacked into one input weight matrix
+/// (`gates * hidden_size x input_size`), one recurrent weight matrix
+/// (`gates * hidden_size x hidden_size`) and one bias vector. Writing `z_k` for the block of
+/// gate `k` in `input_weights * x + biases` and `r_k` for its block in
+/// `recurrent_weights * h_prev`:
+///
+/// * RNN - `h = tanh(z_0 + r_0)`
+/// * LSTM - input, forget, cell and output gates `i = sig(z_0 + r_0)`, `f = sig(z_1 + r_1)`,
+///          `g = tanh(z_2 + r_2)`, `o = sig(z_3 + r_3)`, with `c = f * c_prev + i * g` and
+///          `h = o * tanh(c)`
+/// * GRU - reset and update gates `r = sig(z_0 + r_0)`, `u = sig(z_1 + r_1)`, candidate
+///         `n = tanh(z_2 + r * r_2)` and `h = (1 - u) * n + u * h_prev`
+///
+/// The hidden and cell states start at zero for every sequence.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recurrent {
+    recurrent_type: RecurrentType,
+    input_size: usize,
+    hidden_size: usize,
+    sequence_length: usize,
+    return_sequences: bool,
+    truncation: Option<usize>,
+    input_weights: Array2<f32>,
+    recurrent_weights: Array2<f32>,
+    biases: Array2<f32>,
+    #[serde(skip)]
+    steps: Vec<RecurrentStep>,
+    #[serde(skip)]
+    input_weight_gradient: Array2<f32>,
+    #[serde(skip)]
+    recurrent_weight_gradient: Array2<f32>,
+    #[serde(skip)]
+    bias_gradient: Array2<f32>
+}
+
+impl Recurrent {
+    /// Constructor
+    ///
+    /// * `recurrent_type` - RNN, LSTM or GRU
+    /// * `input_size` - Number of features per step
+    /// * `hidden_size` - Number of hidden units
+    /// * `sequence_length` - Number of steps per sequence
+    pub fn new(recurrent_type: RecurrentType, input_size: usize, hidden_size: usize, sequence_length: usize) -> Self {
+        assert!(input_size > 0 && hidden_size > 0 && sequence_length > 0,
+                "Recurrent layer sizes and sequence length must be positive");
+        let rows = recurrent_type.gates() * hidden_size;
+        return Recurrent {
+            recurrent_type,
+            input_size,
+            hidden_size,
+            sequence_length,
+            return_sequences: false,
+            truncation: None,
+            input_weights: Array2::zeros((rows, input_size)),
+            recurrent_weights: Array2::zeros((rows, hidden_size)),
+            biases: Array2::zeros((rows, 1)),
+            steps: Vec::new(),
+            input_weight_gradient: Array2::zeros((rows, input_size)),
+            recurrent_weight_gradient: Array2::zeros((rows, hidden_size)),
+            bias_gradient: Array2::zeros((rows, 1))
+        };
+    }
+
+    /// Simple recurrent layer with a tanh activation
+    ///
+    /// * `input_size` - Number of features per step
+    /// * `hidden_size` - Number of hidden units
+    /// * `sequence_length` - Number of steps per sequence
+    pub fn rnn(input_size: usize, hidden_size: usize, sequence_length: usize) -> Self {
+        return Self::new(RNN, input_size, hidden_size, sequence_length);
+    }
+
+    /// Long short-term memory layer
+    ///
+    /// * `input_size` - Number of features per step
+    /// * `hidden_size` - Number of hidden units
+    /// * `sequence_length` - Number of steps per sequence
+    pub fn lstm(input_size: usize, hidden_size: usize, sequence_length: usize) -> Self {
+        return Self::new(LSTM, input_size, hidden_size, sequence_length);
+    }
+
+    /// Gated recurrent unit layer
+    ///
+    /// * `input_size` - Number of features per step
+    /// * `hidden_size` - Number of hidden units
+    /// * `sequence_length` - Number of steps per sequence
+    pub fn gru(input_size: usize, hidden_size: usize, sequence_length: usize) -> Self {
+        return Self::new(GRU, input_size, hidden_size, sequence_length);
+    }
+
+    /// Output the hidden state of every step instead of only the last one
+    ///
+    /// * `return_sequences` - True to output `sequence_length * hidden_size` values per example
+    pub fn set_return_sequences(&mut self, return_sequences: bool) {
+        self.return_sequences = return_sequences;
+    }
+
+    ///
+    /// Get whether the hidden state of every step is output
+    ///
+    pub fn return_sequences(&self) -> bool {
+        self.return_sequences
+    }
+
+    /// Limit back propagation through time to windows of `steps` steps. The sequence is split
+    /// into windows counted back from its last step, and gradients do not flow from a window into
+    /// the one before it. Every output still receives its loss gradient, which reaches back to the
+    /// start of its window, so a layer that only outputs the last step is trained through its last
+    /// `steps` steps.
+    ///
+    /// * `steps` - Window length, None to back propagate through the whole sequence
+    pub fn set_truncation(&mut self, steps: Option<usize>) {
+        assert!(steps != Some(0), "Truncated back propagation needs at least one step");
+        self.truncation = steps;
+    }
+
+    ///
+    /// Get the number of steps back propagated through, None for the whole sequence
+    ///
+    pub fn truncation(&self) -> Option<usize> {
+        self.truncation
+    }
+
+    ///
+    /// Get the recurrent type
+    ///
+    pub fn recurrent_type(&self) -> RecurrentType {
+        self.recurrent_type
+    }
+
+    ///
+    /// Get the number of features per step
+    ///
+    pub fn input_size(&self) -> usize {
+        self.input_size
+    }
+
+    ///
+    /// Get the number of hidden units
+    ///
+    pub fn hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    ///
+    /// Get the number of steps per sequence
+    ///
+    pub fn sequence_length(&self) -> usize {
+        self.sequence_length
+    }
+
+    ///
+    /// Get the stacked input weights of every gate
+    ///
+    pub fn input_weights(&self) -> &Array2<f32> {
+        &self.input_weights
+    }
+
+    ///
+    /// Get the stacked input weights of every gate (mutable)
+    ///
+    pub fn input_weights_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.input_weights
+    }
+
+    ///
+    /// Get the stacked recurrent weights of every gate
+    ///
+    pub fn recurrent_weights(&self) -> &Array2<f32> {
+        &self.recurrent_weights
+    }
+
+    ///
+    /// Get the stacked recurrent weights of every gate (mutable)
+    ///
+    pub fn recurrent_weights_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.recurrent_weights
+    }
+
+    ///
+    /// Get the stacked biases of every gate
+    ///
+    pub fn biases(&self) -> &Array2<f32> {
+        &self.biases
+    }
+
+    ///
+    /// Get the stacked biases of every gate (mutable)
+    ///
+    pub fn biases_mut(&mut self) -> &mut Array2<f32> {
+        &mut self.biases
+    }
+
+    /// Advance one time step
+    ///
+    /// * `input` - Step inputs (input_size x batch)
+    /// * `previous_hidden` - Hidden state of the previous step
+    /// * `previous_cell` - Cell state of the previous step, only used by LSTM layers
+    fn step(&self, input: Array2<f32>, previous_hidden: Array2<f32>, previous_cell: Array2<f32>) -> RecurrentStep {
+        let h = self.hidden_size;
+        let mut gates = self.input_weights.dot(&input) + &self.biases;
+        let recurrent = self.recurrent_weights.dot(&previous_hidden);
+        let (cell, hidden) = match self.recurrent_type {
+            RNN => {
+                gates += &recurrent;
+                gates.mapv_inplace(f32::tanh);
+                (previous_cell.clone(), gates.clone())
+            }
+            LSTM => {
+                gates += &recurrent;
+                gates.slice_mut(s![..2 * h, ..]).mapv_inplace(math_utils::sigf);
+                gates.slice_mut(s![2 * h..3 * h, ..]).mapv_inplace(f32::tanh);
+                gates.slice_mut(s![3 * h.., ..]).mapv_inplace(math_utils::sigf);
+                let cell = &gate(&gates, 1, h) * &previous_cell + &gate(&gates, 0, h) * &gate(&gates, 2, h);
+                let hidden = &gate(&gates, 3, h) * &cell.mapv(f32::tanh);
+                (cell, hidden)
+            }
+            GRU => {
+                let mut reset_update = gates.slice_mut(s![..2 * h, ..]);
+                reset_update += &recurrent.slice(s![..2 * h, ..]);
+                reset_update.mapv_inplace(math_utils::sigf);
+                let reset_candidate = &gate(&gates, 0, h) * &gate(&recurrent, 2, h);
+                let mut candidate = gates.slice_mut(s![2 * h.., ..]);
+                candidate += &reset_candidate;
+                candidate.mapv_inplace(f32::tanh);
+                let update = gate(&gates, 1, h);
+                let hidden = &(1.0 - &update) * &gate(&gates, 2, h) + &update * &previous_hidden;
+                (previous_cell.clone(), hidden)
+            }
+        };
+        return RecurrentStep { input, previous_hidden, previous_cell, gates, recurrent, cell, hidden };
+    }
+
+    /// Run every step of a batch of sequences
+    ///
+    /// * `input` - Sequences (sequence_length * input_size x batch)
+    fn forward_steps(&self, input: &Array2<f32>) -> Vec<RecurrentStep> {
+        let mut steps: Vec<RecurrentStep> = Vec::with_capacity(self.sequence_length);
+        let mut hidden = Array2::zeros((self.hidden_size, input.ncols()));
+        let mut cell = Array2::zeros((self.hidden_size, input.ncols()));
+        for t in 0..self.sequence_length {
+            let step_input = input.slice(s![t * self.input_size..(t + 1) * self.input_size, ..]).to_owned();
+            let step = self.step(step_input, hidden, cell);
+            hidden = step.hidden.clone();
+            cell = step.cell.clone();
+            steps.push(step);
+        }
+        return steps;
+    }
+
+    /// Layer output of the steps of a batch
+    fn output(&self, steps: &[RecurrentStep]) -> Array2<f32> {
+        if !self.return_sequences {
+            return steps[steps.len() - 1].hidden.clone();
+        }
+        let hidden: Vec<ArrayView2<f32>> = steps.iter().map(|step| step.hidden.view()).collect();
+        return concatenate(Axis(0), &hidden).unwrap();
+    }
+
+    /// Back propagate through one step
+    ///
+    /// * `step` - Cached step
+    /// * `hidden_gradient` - Loss gradient with respect to the step's hidden state
+    /// * `cell_gradient` - Loss gradient with respect to the step's cell state
+    /// * `returns` - Gradients with respect to the input and recurrent pre-activations of the
+    ///               gates, and the parts of the previous hidden and cell state gradients that
+    ///               bypass the recurrent weights
+    fn step_backward(&self, step: &RecurrentStep, hidden_gradient: &Array2<f32>, cell_gradient: &Array2<f32>)
+                     -> (Array2<f32>, Array2<f32>, Array2<f32>, Array2<f32>) {
+        let h = self.hidden_size;
+        let zeros = Array2::zeros(hidden_gradient.dim());
+        return match self.recurrent_type {
+            RNN => {
+                let delta = hidden_gradient * &step.hidden.mapv(math_utils::tanh_prime_from_output);
+                (delta.clone(), delta, zeros.clone(), zeros)
+            }
+            LSTM => {
+                let (input_gate, forget_gate, cell_gate, output_gate) =
+                    (gate(&step.gates, 0, h), gate(&step.gates, 1, h), gate(&step.gates, 2, h), gate(&step.gates, 3, h));
+                let cell_tanh = step.cell.mapv(f32::tanh);
+                let cell_gradient = cell_gradient + &(hidden_gradient * &output_gate * &cell_tanh.mapv(math_utils::tanh_prime_from_output));
+                let input_delta = &cell_gradient * &cell_gate * &input_gate.mapv(math_utils::sigf_prime_from_output);
+                let forget_delta = &cell_gradient * &step.previous_cell * &forget_gate.mapv(math_utils::sigf_prime_from_output);
+                let cell_delta = &cell_gradient * &input_gate * &cell_gate.mapv(math_utils::tanh_prime_from_output);
+                let output_delta = hidden_gradient * &cell_tanh * &output_gate.mapv(math_utils::sigf_prime_from_output);
+                let delta = concatenate![Axis(0), input_delta, forget_delta, cell_delta, output_delta];
+                (delta.clone(), delta, zeros, &cell_gradient * &forget_gate)
+            }
+            GRU => {
+                let (reset, update, candidate) = (gate(&step.gates, 0, h), gate(&step.gates, 1, h), gate(&step.gates, 2, h));
+                let candidate_delta = hidden_gradient * &(1.0 - &update) * &candidate.mapv(math_utils::tanh_prime_from_output);
+                let update_delta = hidden_gradient * &(&step.previous_hidden - &candidate) * &update.mapv(math_utils::sigf_prime_from_output);
+                let reset_delta = &candidate_delta * &gate(&step.recurrent, 2, h) * &reset.mapv(math_utils::sigf_prime_from_output);
+                let recurrent_candidate_delta = &candidate_delta * &reset;
+                let input_delta = concatenate![Axis(0), reset_delta, update_delta, candidate_delta];
+                let recurrent_delta = concatenate![Axis(0), reset_delta, update_delta, recurrent_candidate_delta];
+                (input_delta, recurrent_delta, hidden_gradient * &update, zeros)
+            }
+        };
+    }
+
+    /// Restore a recurrent layer serialized with [`Layer::to_bytes`]
+    ///
+    /// * `bytes` - Serialized layer
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let recurrent: Recurrent = bincode::deserialize(bytes)?;
+        // Check the declared sizes against the weights before allocating anything from them
+        let (input_size, hidden_size) = (recurrent.input_size, recurrent.hidden_size);
+        let shapes = recurrent.recurrent_type.gates().checked_mul(hidden_size)
+            .map(|rows| [(rows, input_size), (rows, hidden_size), (rows, 1)]);
+        if input_size == 0 || hidden_size == 0 || recurrent.sequence_length == 0 || recurrent.truncation == Some(0)
+            || shapes != Some([recurrent.input_weights.dim(), recurrent.recurrent_weights.dim(), recurrent.biases.dim()]) {
+            return Err(GraymatError::ShapeMismatch(
+                format!("{} weights do not match {} inputs and {} hidden units",
+                        RecurrentType::convert_to_string(recurrent.recurrent_type), input_size, hidden_size)));
+        }
+        check_declared_size("recurrent input", recurrent.sequence_length.checked_mul(input_size))?;
+        check_declared_size("recurrent output", recurrent.sequence_length.checked_mul(hidden_size))?;
+        return Ok(Recurrent {
+            input_weight_gradient: Array2::zeros(recurrent.input_weights.dim()),
+            recurrent_weight_gradient: Array2::zeros(recurrent.recurrent_weights.dim()),
+            bias_gradient: Array2::zeros(recurrent.biases.dim()),
+            ..recurrent
+        });
+    }
+}
+
+impl Layer for Recurrent {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        return self.output(&self.forward_steps(input));
+    }
+
+    fn forward_train(&mut self, input: &Array2<f32>, _rng: &mut dyn RngCore) -> Array2<f32> {
+        self.steps = self.forward_steps(input);
+        return self.output(&self.steps);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        let (h, length) = (self.hidden_size, self.sequence_length);
+        let examples = output_gradient.ncols();
+        let mut input_weight_gradient = Array2::zeros(self.input_weights.dim());
+        let mut recurrent_weight_gradient = Array2::zeros(self.recurrent_weights.dim());
+        let mut bias_gradient = Array2::zeros(self.biases.dim());
+        let mut input_gradient = Array2::zeros((length * self.input_size, examples));
+        let mut hidden_gradient = Array2::zeros((h, examples));
+        let mut cell_gradient = Array2::zeros((h, examples));
+
+        for t in (0..length).rev() {
+            if self.return_sequences {
+                hidden_gradient += &output_gradient.slice(s![t * h..(t + 1) * h, ..]);
+            } else if t == length - 1 {
+                hidden_gradient += output_gradient;
+            }
+            let step = &self.steps[t];
+            let (input_delta, recurrent_delta, previous_hidden_gradient, previous_cell_gradient)
+                = self.step_backward(step, &hidden_gradient, &cell_gradient);
+
+            input_weight_gradient += &input_delta.dot(&step.input.t());
+            recurrent_weight_gradient += &recurrent_delta.dot(&step.previous_hidden.t());
+            bias_gradient += &input_delta.sum_axis(Axis(1)).insert_axis(Axis(1));
+            input_gradient.slice_mut(s![t * self.input_size..(t + 1) * self.input_size, ..])
+                .assign(&self.input_weights.t().dot(&input_delta));
+            hidden_gradient = self.recurrent_weights.t().dot(&recurrent_delta) + previous_hidden_gradient;
+            cell_gradient = previous_cell_gradient;
+            // Step t starts a truncation window, so nothing flows on into the previous window
+            if self.truncation.is_some_and(|steps| (length - t).is_multiple_of(steps)) {
+                hidden_gradient.fill(0.0);
+                cell_gradient.fill(0.0);
+            }
+        }
+
+        self.input_weight_gradient = input_weight_gradient;
+        self.recurrent_weight_gradient = recurrent_weight_gradient;
+        self.bias_gradient = bias_gradient;
+        return input_gradient;
+    }
+
+    fn parameters(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.input_weights, &self.recurrent_weights, &self.biases];
+    }
+
+    fn gradients(&self) -> Vec<&Array2<f32>> {
+        return vec![&self.input_weight_gradient, &self.recurrent_weight_gradient, &self.bias_gradient];
+    }
+
+    fn parameters_mut(&mut self) -> Vec<Parameter<'_>> {
+        return vec![
+            Parameter { value: &mut self.input_weights, gradient: &mut self.input_weight_gradient, regularize: true, rows: None },
+            Parameter { value: &mut self.recurrent_weights, gradient: &mut self.recurrent_weight_gradient, regularize: true, rows: None },
+            Parameter { value: &mut self.biases, gradient: &mut self.bias_gradient, regularize: false, rows: None }
+        ];
+    }
+
+    fn initialize(&mut self, weight_initializer: Initializer, bias_initializer: Initializer, rng: &mut dyn RngCore) {
+        weight_initializer.initialize(&mut self.input_weights, rng);
+        weight_initializer.initialize(&mut self.recurrent_weights, rng);
+        bias_initializer.initialize(&mut self.biases, rng);
+    }
+
+    fn output_size(&self, input_size: usize) -> Option<usize> {
+        let output_size = if self.return_sequences { self.sequence_length * self.hidden_size } else { self.hidden_size };
+        return (input_size == self.sequence_length * self.input_size).then_some(output_size);
+    }
+
+    fn layer_type(&self) -> LayerType {
+        return LayerType::RECURRENT;
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        return bincode::serialize(self).unwrap();
+    }
+}
+
+/// Block of gate `k` of stacked gate values
+fn gate(stacked: &Array2<f32>, k: usize, hidden_size: usize) -> Array2<f32> {
+    return stacked.slice(s![k * hidden_size..(k + 1) * hidden_size, ..]).to_owned();
+}
+
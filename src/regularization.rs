@@ -0,0 +1,124 @@
+use ndarray::{Array2, Zip};
+use crate::layer::Parameter;
+
+/// Weight penalties added to the training loss. Penalties apply to weights only, never to
+/// biases.
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Regularization {
+    /// No penalty
+    NONE,
+    /// Lasso, `lambda * sum(|w|)`. Drives weights to exactly 0.
+    L1(f32),
+    /// Ridge (weight decay), `lambda / 2 * sum(w^2)`
+    L2(f32),
+    /// Elastic net, the sum of an L1 penalty and an L2 penalty `(l1_lambda, l2_lambda)`
+    ELASTIC_NET(f32, f32),
+}
+
+impl Regularization {
+    /// L1 and L2 strengths
+    fn lambdas(&self) -> (f32, f32) {
+        return match *self {
+            Regularization::NONE => (0.0, 0.0),
+            Regularization::L1(l1) => (l1, 0.0),
+            Regularization::L2(l2) => (0.0, l2),
+            Regularization::ELASTIC_NET(l1, l2) => (l1, l2)
+        };
+    }
+
+    /// Penalty of a weight matrix
+    ///
+    /// * `weights` - Layer weights
+    pub fn penalty(&self, weights: &Array2<f32>) -> f32 {
+        let (l1, l2) = self.lambdas();
+        if l1 == 0.0 && l2 == 0.0 {
+            return 0.0;
+        }
+        return weights.fold(0.0, |total, w| total + l1 * w.abs() + 0.5 * l2 * w * w);
+    }
+
+    /// Add the penalty gradient to a weight gradient in place
+    ///
+    /// Note: The derivative of `|w|` at 0 is taken to be 0.
+    ///
+    /// * `weights` - Layer weights
+    /// * `gradient` - Loss gradient with respect to the weights
+    pub fn add_gradient(&self, weights: &Array2<f32>, gradient: &mut Array2<f32>) {
+        let (l1, l2) = self.lambdas();
+        if l1 == 0.0 && l2 == 0.0 {
+            return;
+        }
+        Zip::from(gradient).and(weights).for_each(|g, &w| {
+            let sign = if w == 0.0 { 0.0 } else { w.signum() };
+            *g += l1 * sign + l2 * w;
+        });
+    }
+}
+
+/// Gradient clipping, applied to the averaged batch gradients before the optimizer update
+#[allow(non_camel_case_types)]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum GradientClipping {
+    /// No clipping
+    NONE,
+    /// Clamp every gradient element to `[-limit, limit]`
+    VALUE(f32),
+    /// Scale all gradients down together so their global L2 norm is at most `max_norm`. This
+    /// keeps the direction of the update.
+    NORM(f32),
+}
+
+impl GradientClipping {
+    /// Clip gradients in place
+    ///
+    /// * `gradients` - Gradients of every network parameter
+    pub fn clip(&self, gradients: &mut [&mut Array2<f32>]) {
+        match *self {
+            GradientClipping::NONE => {}
+            GradientClipping::VALUE(limit) => {
+                for gradient in gradients.iter_mut() {
+                    gradient.mapv_inplace(|g| g.clamp(-limit, limit));
+                }
+            }
+            GradientClipping::NORM(max_norm) => {
+                let norm = global_norm(gradients);
+                if norm > max_norm {
+                    let scale = max_norm / norm;
+                    for gradient in gradients.iter_mut() {
+                        **gradient *= scale;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Clip the gradients of network parameters in place. Sparse parameters are clipped, and
+    /// count towards the global norm, by their rows that received a gradient only.
+    ///
+    /// * `parameters` - Every network parameter
+    pub fn clip_parameters(&self, parameters: &mut [Parameter]) {
+        match *self {
+            GradientClipping::NONE => {}
+            GradientClipping::VALUE(limit) => {
+                for parameter in parameters.iter_mut() {
+                    parameter.map_gradient_inplace(|g| g.clamp(-limit, limit));
+                }
+            }
+            GradientClipping::NORM(max_norm) => {
+                let norm = parameters.iter().map(|parameter| parameter.gradient_squared_sum()).sum::<f32>().sqrt();
+                if norm > max_norm {
+                    let scale = max_norm / norm;
+                    for parameter in parameters.iter_mut() {
+                        parameter.map_gradient_inplace(|g| g * scale);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// L2 norm of all gradients taken together
+fn global_norm(gradients: &[&mut Array2<f32>]) -> f32 {
+    return gradients.iter().map(|g| g.fold(0.0, |total, x| total + x * x)).sum::<f32>().sqrt();
+}
@@ -0,0 +1,133 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use crate::activation_function::ActivationFunction;
+use crate::initializer::Initializer;
+use crate::layer::{ActivationLayer, Dropout, Layer};
+use crate::neural_network::{NeuralNetwork, NeuralNetworkLayer};
+use crate::normalization::Normalization;
+
+/// Builder that stacks layers into a [`NeuralNetwork`]
+///
+/// ```
+/// # use graymat::activation_function::ActivationFunction;
+/// # use graymat::sequential::Sequential;
+/// let nn = Sequential::new(4)
+///     .dense(16, ActivationFunction::LINEAR)
+///     .batch_normalization()
+///     .activation(ActivationFunction::RELU)
+///     .dropout(0.2)
+///     .dense(3, ActivationFunction::SOFTMAX)
+///     .build();
+/// ```
+pub struct Sequential {
+    input_size: usize,
+    output_size: usize,
+    layers: Vec<Box<dyn Layer>>,
+    weight_initializer: Initializer,
+    bias_initializer: Initializer,
+    rng: Option<Box<dyn RngCore>>
+}
+
+impl Sequential {
+    /// Constructor
+    ///
+    /// * `input_size` - Number of network inputs
+    pub fn new(input_size: usize) -> Self {
+        return Sequential {
+            input_size,
+            output_size: input_size,
+            layers: Vec::new(),
+            weight_initializer: Initializer::XAVIER_UNIFORM,
+            bias_initializer: Initializer::ZEROS,
+            rng: None
+        };
+    }
+
+    /// Append a layer
+    ///
+    /// * `layer` - Layer taking the outputs of the previous layer
+    pub fn layer(mut self, layer: Box<dyn Layer>) -> Self {
+        self.output_size = layer.output_size(self.output_size)
+            .unwrap_or_else(|| panic!("Layer {} cannot take {} inputs", self.layers.len() + 1, self.output_size));
+        self.layers.push(layer);
+        return self;
+    }
+
+    /// Append a fully connected layer
+    ///
+    /// * `neurons` - Number of layer neurons
+    /// * `activation` - Activation function applied to the layer output
+    pub fn dense(self, neurons: usize, activation: ActivationFunction) -> Self {
+        let inputs = self.output_size;
+        return self.layer(Box::new(NeuralNetworkLayer::new(inputs, neurons, activation)));
+    }
+
+    /// Append an activation function
+    ///
+    /// * `function` - Activation function type
+    pub fn activation(self, function: ActivationFunction) -> Self {
+        return self.layer(Box::new(ActivationLayer::new(function)));
+    }
+
+    /// Append inverted dropout
+    ///
+    /// * `rate` - Probability of dropping an input, in [0, 1)
+    pub fn dropout(self, rate: f32) -> Self {
+        return self.layer(Box::new(Dropout::new(rate)));
+    }
+
+    ///
+    /// Append batch normalization with default momentum and epsilon
+    ///
+    pub fn batch_normalization(self) -> Self {
+        let size = self.output_size;
+        return self.layer(Box::new(Normalization::batch(size)));
+    }
+
+    ///
+    /// Append layer normalization with default epsilon
+    ///
+    pub fn layer_normalization(self) -> Self {
+        let size = self.output_size;
+        return self.layer(Box::new(Normalization::layer(size)));
+    }
+
+    /// Set the initialization schemes applied to every layer when the network is built
+    ///
+    /// * `weight_initializer` - Weight initialization scheme, [`Initializer::XAVIER_UNIFORM`] by
+    ///                          default
+    /// * `bias_initializer` - Bias initialization scheme, [`Initializer::ZEROS`] by default
+    pub fn initializer(mut self, weight_initializer: Initializer, bias_initializer: Initializer) -> Self {
+        self.weight_initializer = weight_initializer;
+        self.bias_initializer = bias_initializer;
+        return self;
+    }
+
+    /// Set the random number generator used to initialize the layers. The network keeps it for
+    /// dropout and shuffling. An entropy seeded generator is used by default.
+    ///
+    /// * `rng` - Random number generator
+    pub fn rng(mut self, rng: Box<dyn RngCore>) -> Self {
+        self.rng = Some(rng);
+        return self;
+    }
+
+    ///
+    /// Get the number of outputs of the layers added so far
+    ///
+    pub fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    ///
+    /// Initialize the layers and build the network
+    ///
+    pub fn build(self) -> NeuralNetwork {
+        let mut rng = self.rng.unwrap_or_else(|| Box::new(StdRng::from_entropy()));
+        let mut layers = self.layers;
+        for layer in layers.iter_mut() {
+            layer.initialize(self.weight_initializer, self.bias_initializer, &mut *rng);
+        }
+        return NeuralNetwork::from_layers(self.input_size, layers, rng);
+    }
+}
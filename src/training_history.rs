@@ -0,0 +1,186 @@
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+use crate::error::GraymatError;
+use crate::metrics::Metric;
+
+/// Results of a single training epoch
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochRecord {
+    /// Epoch number, starting at 1
+    pub epoch: usize,
+    /// Training loss averaged over the epoch's batches, including the weight penalty
+    pub train_loss: f32,
+    /// Validation loss at the end of the epoch, if validation data was given
+    pub validation_loss: Option<f32>,
+    /// Learning rate used during the epoch
+    pub learning_rate: f32,
+    /// Configured metrics on the training data, from the outputs of the epoch's batches
+    pub metrics: Vec<(Metric, f32)>,
+    /// Configured metrics on the validation data at the end of the epoch
+    pub validation_metrics: Vec<(Metric, f32)>
+}
+
+impl EpochRecord {
+    /// Get the value of a training metric
+    ///
+    /// * `metric` - Metric
+    /// * `returns` - Some value if the metric was recorded else None
+    pub fn metric(&self, metric: Metric) -> Option<f32> {
+        return self.metrics.iter().find(|(m, _)| *m == metric).map(|(_, value)| *value);
+    }
+
+    /// Get the value of a validation metric
+    ///
+    /// * `metric` - Metric
+    /// * `returns` - Some value if the metric was recorded else None
+    pub fn validation_metric(&self, metric: Metric) -> Option<f32> {
+        return self.validation_metrics.iter().find(|(m, _)| *m == metric).map(|(_, value)| *value);
+    }
+}
+
+/// Per-epoch losses and metrics recorded while training a network
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TrainingHistory {
+    metrics: Vec<Metric>,
+    epochs: Vec<EpochRecord>
+}
+
+impl TrainingHistory {
+    /// Constructor
+    ///
+    /// * `metrics` - Metrics recorded every epoch
+    pub fn new(metrics: Vec<Metric>) -> Self {
+        return TrainingHistory { metrics, epochs: Vec::new() };
+    }
+
+    /// Append an epoch record
+    ///
+    /// * `record` - Epoch results
+    pub fn push(&mut self, record: EpochRecord) {
+        self.epochs.push(record);
+    }
+
+    ///
+    /// Get the recorded epochs
+    ///
+    pub fn epochs(&self) -> &Vec<EpochRecord> {
+        &self.epochs
+    }
+
+    ///
+    /// Get the metrics recorded every epoch
+    ///
+    pub fn metrics(&self) -> &Vec<Metric> {
+        &self.metrics
+    }
+
+    ///
+    /// Get the training loss of every epoch
+    ///
+    pub fn train_loss(&self) -> Vec<f32> {
+        return self.epochs.iter().map(|e| e.train_loss).collect();
+    }
+
+    ///
+    /// Get the validation loss of every epoch
+    ///
+    pub fn validation_loss(&self) -> Vec<Option<f32>> {
+        return self.epochs.iter().map(|e| e.validation_loss).collect();
+    }
+
+    ///
+    /// Get the learning rate of every epoch
+    ///
+    pub fn learning_rate(&self) -> Vec<f32> {
+        return self.epochs.iter().map(|e| e.learning_rate).collect();
+    }
+
+    /// Format the history as CSV, one row per epoch. Missing values are left empty.
+    ///
+    /// Columns are `epoch`, `train_loss`, `validation_loss`, `learning_rate`, each training metric
+    /// and each validation metric prefixed with `validation_`.
+    pub fn to_csv(&self) -> String {
+        let mut csv = "epoch,train_loss,validation_loss,learning_rate".to_string();
+        for metric in self.metrics.iter() {
+            write!(csv, ",{}", Metric::convert_to_string(*metric)).unwrap();
+        }
+        for metric in self.metrics.iter() {
+            write!(csv, ",validation_{}", Metric::convert_to_string(*metric)).unwrap();
+        }
+        csv.push('\n');
+
+        for record in self.epochs.iter() {
+            write!(csv, "{},{},{},{}", record.epoch, record.train_loss, csv_value(record.validation_loss),
+                   record.learning_rate).unwrap();
+            for metric in self.metrics.iter() {
+                write!(csv, ",{}", csv_value(record.metric(*metric))).unwrap();
+            }
+            for metric in self.metrics.iter() {
+                write!(csv, ",{}", csv_value(record.validation_metric(*metric))).unwrap();
+            }
+            csv.push('\n');
+        }
+        return csv;
+    }
+
+    /// Format the history as JSON
+    ///
+    /// `{"epochs": [{"epoch": 1, "train_loss": 0.5, "validation_loss": null, "learning_rate": 0.1,
+    /// "metrics": {...}, "validation_metrics": {...}}, ...]}`. Missing and non-finite values are
+    /// written as `null`.
+    pub fn to_json(&self) -> String {
+        let mut json = "{\"epochs\":[".to_string();
+        for (i, record) in self.epochs.iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "{{\"epoch\":{},\"train_loss\":{},\"validation_loss\":{},\"learning_rate\":{},\"metrics\":{},\"validation_metrics\":{}}}",
+                   record.epoch,
+                   json_value(Some(record.train_loss)),
+                   json_value(record.validation_loss),
+                   json_value(Some(record.learning_rate)),
+                   json_metrics(&record.metrics),
+                   json_metrics(&record.validation_metrics)).unwrap();
+        }
+        json.push_str("]}");
+        return json;
+    }
+
+    /// Write the history to a CSV file
+    ///
+    /// * `path` - File path
+    pub fn write_csv(&self, path: impl AsRef<Path>) -> Result<(), GraymatError> {
+        fs::write(path, self.to_csv())?;
+        return Ok(());
+    }
+
+    /// Write the history to a JSON file
+    ///
+    /// * `path` - File path
+    pub fn write_json(&self, path: impl AsRef<Path>) -> Result<(), GraymatError> {
+        fs::write(path, self.to_json())?;
+        return Ok(());
+    }
+}
+
+/// Format an optional value for CSV
+fn csv_value(value: Option<f32>) -> String {
+    return value.map(|v| v.to_string()).unwrap_or_default();
+}
+
+/// Format an optional value for JSON
+fn json_value(value: Option<f32>) -> String {
+    return match value {
+        Some(v) if v.is_finite() => v.to_string(),
+        _ => "null".to_owned()
+    };
+}
+
+/// Format metric values as a JSON object
+fn json_metrics(metrics: &[(Metric, f32)]) -> String {
+    let fields: Vec<String> = metrics.iter()
+        .map(|(metric, value)| format!("\"{}\":{}", Metric::convert_to_string(*metric), json_value(Some(*value))))
+        .collect();
+    return format!("{{{}}}", fields.join(","));
+}
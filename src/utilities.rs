@@ -1,4 +1,5 @@
 
 pub mod array2_utils;
+pub mod checksum;
 pub mod math_utils;
 pub mod string_utils;
@@ -17,12 +17,17 @@ pub mod math {
     /// * `arr` - Array to process
     pub fn sig(arr: &Array2<f32>) -> Array2<f32> {
         let mut result = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = math_utils::sigf(*element);
-        }
+        sig_inplace(&mut result);
         return result;
     }
 
+    /// Sigmoid, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn sig_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::sigf);
+    }
+
     /// Sigmoid Prime
     ///
     /// Perform first derivative of sigmoid function on a 2 dimensional array
@@ -30,23 +35,41 @@ pub mod math {
     /// * `arr` - Array to process
     pub fn sig_prime(arr: &Array2<f32>) -> Array2<f32> {
         let mut result: Array2<f32> = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = math_utils::sigf_prime(*element);
-        }
+        sig_prime_inplace(&mut result);
         return result;
     }
 
+    /// Sigmoid Prime, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn sig_prime_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::sigf_prime);
+    }
+
+    /// Sigmoid Prime from the sigmoid output, in place. Backpropagation uses this with the
+    /// cached forward output instead of evaluating the sigmoid again.
+    ///
+    /// * `arr` - Sigmoid output, overwritten with the derivative
+    pub fn sig_prime_from_output_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::sigf_prime_from_output);
+    }
+
     /// Hyperbolic tangent
     ///
     /// * `arr` - Array to process
     pub fn tanh(arr: &Array2<f32>) -> Array2<f32> {
         let mut result: Array2<f32> = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = element.tanh();
-        }
+        tanh_inplace(&mut result);
         return result;
     }
 
+    /// Hyperbolic tangent, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn tanh_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(f32::tanh);
+    }
+
     /// Hyperbolic tangent
     ///
     /// First derivative of the hyperbolic tangent function
@@ -54,23 +77,41 @@ pub mod math {
     /// * `arr` - Array to process
     pub fn tanh_prime(arr: &Array2<f32>) -> Array2<f32> {
         let mut result: Array2<f32> = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = math_utils::tanh_prime(*element);
-        }
+        tanh_prime_inplace(&mut result);
         return result;
     }
 
+    /// Hyperbolic tangent prime, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn tanh_prime_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::tanh_prime);
+    }
+
+    /// Hyperbolic tangent prime from the hyperbolic tangent output, in place. Backpropagation
+    /// uses this with the cached forward output instead of evaluating tanh again.
+    ///
+    /// * `arr` - Hyperbolic tangent output, overwritten with the derivative
+    pub fn tanh_prime_from_output_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::tanh_prime_from_output);
+    }
+
     /// Rectified Linear Unit
     ///
     /// * `arr` - Array to process
     pub fn relu(arr: &Array2<f32>) -> Array2<f32> {
         let mut result: Array2<f32> = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = math_utils::relu(*element);
-        }
+        relu_inplace(&mut result);
         return result;
     }
 
+    /// Rectified Linear Unit, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn relu_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::relu);
+    }
+
     /// Rectified Linear Unit
     ///
     /// First derivative of the Rectified Linear Unit function
@@ -83,22 +124,143 @@ pub mod math {
     /// * `arr` - Array to process
     pub fn relu_prime(arr: &Array2<f32>) -> Array2<f32> {
         let mut result: Array2<f32> = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = math_utils::relu_prime(*element);
+        relu_prime_inplace(&mut result);
+        return result;
+    }
+
+    /// Rectified Linear Unit prime, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn relu_prime_inplace(arr: &mut Array2<f32>) {
+        arr.mapv_inplace(math_utils::relu_prime);
+    }
+
+    /// Linear (identity)
+    ///
+    /// * `arr` - Array to process
+    pub fn linear(arr: &Array2<f32>) -> Array2<f32> {
+        return arr.to_owned();
+    }
+
+    /// Linear (identity), in place. The array is left unchanged.
+    ///
+    /// * `_arr` - Array to process
+    pub fn linear_inplace(_arr: &mut Array2<f32>) {}
+
+    /// Linear (identity)
+    ///
+    /// First derivative of the identity function, which is 1 everywhere
+    ///
+    /// * `arr` - Array to process
+    pub fn linear_prime(arr: &Array2<f32>) -> Array2<f32> {
+        return Array2::ones(arr.dim());
+    }
+
+    /// Linear (identity) prime, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn linear_prime_inplace(arr: &mut Array2<f32>) {
+        arr.fill(1.0);
+    }
+
+    /// Softmax
+    ///
+    /// Each column is treated as one example and normalized to a probability distribution.
+    /// The column maximum is subtracted before exponentiation so large inputs do not overflow.
+    ///
+    /// * `arr` - Array to process
+    pub fn softmax(arr: &Array2<f32>) -> Array2<f32> {
+        let mut result: Array2<f32> = arr.to_owned();
+        softmax_inplace(&mut result);
+        return result;
+    }
+
+    /// Softmax, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn softmax_inplace(arr: &mut Array2<f32>) {
+        for mut column in arr.columns_mut() {
+            let max = column.fold(f32::NEG_INFINITY, |m, &x| m.max(x));
+            column.mapv_inplace(|x| (x - max).exp());
+            let sum = column.sum();
+            column.mapv_inplace(|x| x / sum);
         }
+    }
+
+    /// Softmax Prime
+    ///
+    /// Diagonal of the softmax Jacobian, `s * (1 - s)`. Softmax outputs depend on every input
+    /// in the column, so backpropagation uses the full Jacobian (see `softmax_backward`).
+    ///
+    /// * `arr` - Array to process
+    pub fn softmax_prime(arr: &Array2<f32>) -> Array2<f32> {
+        let mut result: Array2<f32> = arr.to_owned();
+        softmax_prime_inplace(&mut result);
+        return result;
+    }
+
+    /// Softmax Prime, in place
+    ///
+    /// * `arr` - Array to process
+    pub fn softmax_prime_inplace(arr: &mut Array2<f32>) {
+        softmax_inplace(arr);
+        arr.mapv_inplace(|s| s * (1.0 - s));
+    }
+
+    /// Softmax Backward
+    ///
+    /// Multiply an error by the transposed softmax Jacobian, column by column:
+    /// `s * (error - sum(error * s))`
+    ///
+    /// * `s` - Softmax output
+    /// * `error` - Error with respect to the softmax output
+    pub fn softmax_backward(s: &Array2<f32>, error: &Array2<f32>) -> Array2<f32> {
+        let mut result: Array2<f32> = error.to_owned();
+        softmax_backward_inplace(s, &mut result);
         return result;
     }
 
+    /// Softmax Backward, in place. `error` is overwritten with the result.
+    ///
+    /// * `s` - Softmax output
+    /// * `error` - Error with respect to the softmax output
+    pub fn softmax_backward_inplace(s: &Array2<f32>, error: &mut Array2<f32>) {
+        *error *= s;
+        for (mut column, s_column) in error.columns_mut().into_iter().zip(s.columns()) {
+            let dot = column.sum();
+            column.zip_mut_with(&s_column, |r, &s| *r -= s * dot);
+        }
+    }
+
     /// Multiple an array2 by a certain power
     ///
     /// * `arr` - Array
     /// * `power` - Raise to the power of
     pub fn pow(arr: &Array2<f32>, power: f32) -> Array2<f32> {
-        let mut result = arr.to_owned();
-        for element in result.iter_mut() {
-            *element = element.powf(power);
-        }
-        return result;
+        return arr.mapv(|element| element.powf(power));
+    }
+}
+
+/// Copy an array into a buffer, reallocating the buffer only if its shape differs
+///
+/// * `buffer` - Buffer to overwrite
+/// * `source` - Array to copy
+pub fn assign_to_buffer(buffer: &mut Array2<f32>, source: &Array2<f32>) {
+    if buffer.dim() == source.dim() {
+        buffer.assign(source);
+    } else {
+        *buffer = source.to_owned();
+    }
+}
+
+/// Give a buffer a shape, reallocating it only if its shape differs. The buffer is zeroed when it
+/// is reallocated and keeps its contents otherwise.
+///
+/// * `buffer` - Buffer to reshape
+/// * `shape` - Shape (rows, columns)
+pub fn fit_buffer(buffer: &mut Array2<f32>, shape: (usize, usize)) {
+    if buffer.dim() != shape {
+        *buffer = Array2::zeros(shape);
     }
 }
 
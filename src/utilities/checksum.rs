@@ -0,0 +1,36 @@
+
+const CRC32_POLYNOMIAL: u32 = 0xEDB88320;
+
+/// CRC32 lookup table (IEEE 802.3, reflected)
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table: [u32; 256] = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ CRC32_POLYNOMIAL } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    return table;
+}
+
+/// CRC32 checksum (IEEE 802.3)
+/// ```
+/// use graymat::utilities::checksum::crc32;
+///
+/// assert_eq!(crc32(b"123456789"), 0xCBF43926);
+/// ```
+/// * `bytes` - Data to checksum
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc: u32 = 0xFFFFFFFF;
+    for byte in bytes {
+        crc = CRC32_TABLE[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    return !crc;
+}
@@ -1,25 +1,42 @@
-use std::f32::consts::E;
-
 /// Sigmoid Function
 ///
+/// Negative values are evaluated as `e^x / (1 + e^x)`, so `e^-x` never overflows for large
+/// negative values.
+///
 /// * `val` - Float value
 pub fn sigf(val: f32) -> f32 {
-    return 1.0 / (1.0 + E.powf(val * -1.0));
+    if val >= 0.0 {
+        return 1.0 / (1.0 + (-val).exp());
+    }
+    let e = val.exp();
+    return e / (1.0 + e);
 }
 
 /// Sigmoid Prime Function
 ///
 /// * `val` - Float value
 pub fn sigf_prime(val: f32) -> f32 {
-    let z: f32 = sigf(val);
-    return z * (1.0 - z);
+    return sigf_prime_from_output(sigf(val));
+}
+
+/// Sigmoid Prime Function, computed from the sigmoid output `s` as `s * (1 - s)`
+///
+/// * `s` - Sigmoid of the value
+pub fn sigf_prime_from_output(s: f32) -> f32 {
+    return s * (1.0 - s);
 }
 
 /// Sigmoid Function
 ///
+/// Negative values are evaluated as `e^x / (1 + e^x)`, see [`sigf`].
+///
 /// * `val` - Double value
 pub fn sig(val: f64) -> f64 {
-    return 1.0 / (1.0 + std::f64::consts::E.powf(val))
+    if val >= 0.0 {
+        return 1.0 / (1.0 + (-val).exp());
+    }
+    let e = val.exp();
+    return e / (1.0 + e);
 }
 
 /// Hyperbolic tangent
@@ -28,7 +45,14 @@ pub fn sig(val: f64) -> f64 {
 ///
 /// * `val` - Float value
 pub fn tanh_prime(val: f32) -> f32 {
-    return 1.0 - val.tanh().powf(2.0);
+    return tanh_prime_from_output(val.tanh());
+}
+
+/// Hyperbolic tangent prime, computed from the hyperbolic tangent output `t` as `1 - t^2`
+///
+/// * `t` - Hyperbolic tangent of the value
+pub fn tanh_prime_from_output(t: f32) -> f32 {
+    return 1.0 - t * t;
 }
 
 /// Rectified Linear Unit
@@ -51,6 +75,200 @@ pub fn relu_prime(val: f32) -> f32 {
     return if val > 0.0 { 1.0 } else { 0.0 };
 }
 
+/// Leaky Rectified Linear Unit
+///
+/// * `val` - Float value
+/// * `slope` - Slope for negative values
+pub fn leaky_relu(val: f32, slope: f32) -> f32 {
+    return if val > 0.0 { val } else { slope * val };
+}
+
+/// Leaky Rectified Linear Unit Prime
+///
+/// * `val` - Float value
+/// * `slope` - Slope for negative values
+pub fn leaky_relu_prime(val: f32, slope: f32) -> f32 {
+    return if val > 0.0 { 1.0 } else { slope };
+}
+
+/// Leaky Rectified Linear Unit Prime, computed from the function output. Only valid for a
+/// non-negative slope, which keeps the sign of the input.
+///
+/// * `y` - Leaky ReLU of the value
+/// * `slope` - Slope for negative values
+pub fn leaky_relu_prime_from_output(y: f32, slope: f32) -> f32 {
+    return leaky_relu_prime(y, slope);
+}
+
+/// Exponential Linear Unit
+///
+/// * `val` - Float value
+/// * `alpha` - Value approached for large negative values is `-alpha`
+pub fn elu(val: f32, alpha: f32) -> f32 {
+    return if val > 0.0 { val } else { alpha * val.exp_m1() };
+}
+
+/// Exponential Linear Unit Prime
+///
+/// * `val` - Float value
+/// * `alpha` - Value approached for large negative values is `-alpha`
+pub fn elu_prime(val: f32, alpha: f32) -> f32 {
+    return if val > 0.0 { 1.0 } else { alpha * val.exp() };
+}
+
+/// Exponential Linear Unit Prime, computed from the function output as `y + alpha` for
+/// non-positive outputs. Only valid for a non-negative alpha.
+///
+/// * `y` - ELU of the value
+/// * `alpha` - Value approached for large negative values is `-alpha`
+pub fn elu_prime_from_output(y: f32, alpha: f32) -> f32 {
+    return if y > 0.0 { 1.0 } else { y + alpha };
+}
+
+/// Scale of the Scaled Exponential Linear Unit
+pub const SELU_SCALE: f32 = 1.050_701;
+
+/// Alpha of the Scaled Exponential Linear Unit
+pub const SELU_ALPHA: f32 = 1.673_263_2;
+
+/// Scaled Exponential Linear Unit
+///
+/// * `val` - Float value
+pub fn selu(val: f32) -> f32 {
+    return SELU_SCALE * elu(val, SELU_ALPHA);
+}
+
+/// Scaled Exponential Linear Unit Prime
+///
+/// * `val` - Float value
+pub fn selu_prime(val: f32) -> f32 {
+    return SELU_SCALE * elu_prime(val, SELU_ALPHA);
+}
+
+/// Scaled Exponential Linear Unit Prime, computed from the function output
+///
+/// * `y` - SELU of the value
+pub fn selu_prime_from_output(y: f32) -> f32 {
+    return SELU_SCALE * elu_prime_from_output(y / SELU_SCALE, SELU_ALPHA);
+}
+
+/// sqrt(2 / pi), used by the GELU approximation
+const GELU_SCALE: f32 = 0.797_884_6;
+
+/// Gaussian Error Linear Unit, tanh approximation
+///
+/// `0.5 * x * (1 + tanh(sqrt(2 / pi) * (x + 0.044715 * x^3)))`
+///
+/// * `val` - Float value
+pub fn gelu(val: f32) -> f32 {
+    let inner = GELU_SCALE * (val + 0.044715 * val.powi(3));
+    return 0.5 * val * (1.0 + inner.tanh());
+}
+
+/// Gaussian Error Linear Unit Prime, of the tanh approximation
+///
+/// * `val` - Float value
+pub fn gelu_prime(val: f32) -> f32 {
+    let inner = GELU_SCALE * (val + 0.044715 * val.powi(3));
+    let t = inner.tanh();
+    return 0.5 * (1.0 + t) + 0.5 * val * (1.0 - t * t) * GELU_SCALE * (1.0 + 3.0 * 0.044715 * val * val);
+}
+
+/// Swish, also known as SiLU
+///
+/// * `val` - Float value
+pub fn swish(val: f32) -> f32 {
+    return val * sigf(val);
+}
+
+/// Swish Prime
+///
+/// * `val` - Float value
+pub fn swish_prime(val: f32) -> f32 {
+    let s = sigf(val);
+    return s + val * s * (1.0 - s);
+}
+
+/// Softplus, `ln(1 + e^x)`, computed without overflow for large values
+///
+/// * `val` - Float value
+pub fn softplus(val: f32) -> f32 {
+    return val.max(0.0) + (-val.abs()).exp().ln_1p();
+}
+
+/// Softplus Prime, which is the sigmoid function
+///
+/// * `val` - Float value
+pub fn softplus_prime(val: f32) -> f32 {
+    return sigf(val);
+}
+
+/// Softplus Prime, computed from the function output as `1 - e^-y`
+///
+/// * `y` - Softplus of the value
+pub fn softplus_prime_from_output(y: f32) -> f32 {
+    return -(-y).exp_m1();
+}
+
+/// Mish, `x * tanh(softplus(x))`
+///
+/// * `val` - Float value
+pub fn mish(val: f32) -> f32 {
+    return val * softplus(val).tanh();
+}
+
+/// Mish Prime
+///
+/// * `val` - Float value
+pub fn mish_prime(val: f32) -> f32 {
+    let t = softplus(val).tanh();
+    return t + val * (1.0 - t * t) * sigf(val);
+}
+
+/// Softsign, `x / (1 + |x|)`
+///
+/// * `val` - Float value
+pub fn softsign(val: f32) -> f32 {
+    return val / (1.0 + val.abs());
+}
+
+/// Softsign Prime
+///
+/// * `val` - Float value
+pub fn softsign_prime(val: f32) -> f32 {
+    return 1.0 / (1.0 + val.abs()).powi(2);
+}
+
+/// Softsign Prime, computed from the function output as `(1 - |y|)^2`
+///
+/// * `y` - Softsign of the value
+pub fn softsign_prime_from_output(y: f32) -> f32 {
+    return (1.0 - y.abs()).powi(2);
+}
+
+/// Hard Sigmoid, a piecewise linear approximation of the sigmoid function
+///
+/// `clamp(x / 6 + 1 / 2, 0, 1)`
+///
+/// * `val` - Float value
+pub fn hard_sigmoid(val: f32) -> f32 {
+    return (val / 6.0 + 0.5).clamp(0.0, 1.0);
+}
+
+/// Hard Sigmoid Prime
+///
+/// * `val` - Float value
+pub fn hard_sigmoid_prime(val: f32) -> f32 {
+    return if val > -3.0 && val < 3.0 { 1.0 / 6.0 } else { 0.0 };
+}
+
+/// Hard Sigmoid Prime, computed from the function output
+///
+/// * `y` - Hard sigmoid of the value
+pub fn hard_sigmoid_prime_from_output(y: f32) -> f32 {
+    return if y > 0.0 && y < 1.0 { 1.0 / 6.0 } else { 0.0 };
+}
+
 /// Float compare
 /// ```
 /// use graymat::utilities::math_utils::float_compare;
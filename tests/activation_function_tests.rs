@@ -0,0 +1,281 @@
+use ndarray::{array, Array2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::sync::Arc;
+use graymat::activation_function::{register_activation, registered_activations, Activation, ActivationFunction};
+use graymat::column_vector::ColumnVector;
+use graymat::error::GraymatError;
+use graymat::layer::{ActivationLayer, Layer};
+use graymat::neural_network::NeuralNetwork;
+use graymat::sequential::Sequential;
+use graymat::utilities::checksum::crc32;
+
+#[test]
+fn registry_round_trip_test() {
+    let functions = ActivationFunction::all();
+    assert_eq!(functions.len(), 15);
+    let ids: Vec<u8> = functions.iter().map(|f| f.id()).collect();
+    assert_eq!(ids, (1..=15).collect::<Vec<u8>>());
+
+    for function in functions {
+        let tuned = function.with_parameter(0.3);
+        for f in [function, tuned] {
+            assert_eq!(ActivationFunction::from_id(f.id(), f.parameter()), Some(f.clone()));
+            assert_eq!(ActivationFunction::from_name(f.name(), f.parameter()), Some(f.clone()));
+            assert_eq!(f.to_string().parse::<ActivationFunction>().unwrap(), f);
+        }
+    }
+    assert_eq!(ActivationFunction::from_id(16, None), None);
+    assert_eq!(ActivationFunction::from_id(6, None), Some(ActivationFunction::LEAKY_RELU(0.01)));
+}
+
+#[test]
+fn names_and_aliases_test() {
+    assert_eq!(ActivationFunction::LEAKY_RELU(0.2).to_string(), "LeakyReLU(0.2)");
+    assert_eq!(ActivationFunction::GELU.to_string(), "GELU");
+    assert_eq!("elu(0.5)".parse::<ActivationFunction>().unwrap(), ActivationFunction::ELU(0.5));
+    assert_eq!("Identity".parse::<ActivationFunction>().unwrap(), ActivationFunction::IDENTITY);
+    assert_eq!("silu".parse::<ActivationFunction>().unwrap(), ActivationFunction::SWISH);
+    assert!(matches!("Bogus".parse::<ActivationFunction>(), Err(GraymatError::UnknownActivationName(name)) if name == "Bogus"));
+    assert!("LeakyReLU(x)".parse::<ActivationFunction>().is_err());
+}
+
+#[test]
+fn activation_values_test() {
+    let x = array![[-2.0, 0.0, 1.0, 100.0]];
+    let expected = [
+        (ActivationFunction::LEAKY_RELU(0.1), [-0.2, 0.0, 1.0, 100.0]),
+        (ActivationFunction::ELU(1.0), [(-2f32).exp() - 1.0, 0.0, 1.0, 100.0]),
+        (ActivationFunction::SELU, [1.050_701 * 1.673_263_2 * ((-2f32).exp() - 1.0), 0.0, 1.050_701, 105.0701]),
+        (ActivationFunction::GELU, [-0.045_402, 0.0, 0.841_192, 100.0]),
+        (ActivationFunction::SWISH, [-0.238_406, 0.0, 0.731_059, 100.0]),
+        (ActivationFunction::MISH, [-0.252_501, 0.0, 0.865_098, 100.0]),
+        (ActivationFunction::SOFTPLUS, [0.126_928, 2f32.ln(), 1.313_262, 100.0]),
+        (ActivationFunction::SOFTSIGN, [-2.0 / 3.0, 0.0, 0.5, 100.0 / 101.0]),
+        (ActivationFunction::HARD_SIGMOID, [1.0 / 6.0, 0.5, 2.0 / 3.0, 1.0]),
+        (ActivationFunction::IDENTITY, [-2.0, 0.0, 1.0, 100.0])
+    ];
+    for (function, values) in expected {
+        let mut y = x.clone();
+        function.apply_inplace(&mut y);
+        for (actual, expected) in y.iter().zip(values.iter()) {
+            assert!((actual - expected).abs() < 1e-3 * expected.abs().max(1.0), "{}: {} vs {}", function, actual, expected);
+        }
+    }
+}
+
+#[test]
+fn derivatives_match_central_differences_test() {
+    let x = Array2::from_shape_fn((1, 13), |(_, j)| j as f32 * 0.5 - 3.05);
+    let h = 1e-3;
+    for function in ActivationFunction::all() {
+        if function == ActivationFunction::SOFTMAX {
+            continue;
+        }
+        let mut derivative = x.clone();
+        function.derivative_inplace(&mut derivative);
+        let (mut plus, mut minus) = (&x + h, &x - h);
+        function.apply_inplace(&mut plus);
+        function.apply_inplace(&mut minus);
+        let numerical = (plus - minus) / (2.0 * h);
+        for (analytic, numerical) in derivative.iter().zip(numerical.iter()) {
+            assert!((analytic - numerical).abs() < 1e-2, "{}: {} vs {}", function, analytic, numerical);
+        }
+    }
+}
+
+#[test]
+fn derivatives_from_output_test() {
+    let x = Array2::from_shape_fn((1, 13), |(_, j)| j as f32 * 0.5 - 3.05);
+    let mut functions = ActivationFunction::all();
+    functions.extend([ActivationFunction::LEAKY_RELU(0.3), ActivationFunction::ELU(0.5)]);
+    for function in functions {
+        if !function.derivative_uses_output() || function == ActivationFunction::SOFTMAX {
+            continue;
+        }
+        let mut from_input = x.clone();
+        function.derivative_inplace(&mut from_input);
+        let mut from_output = x.clone();
+        function.apply_inplace(&mut from_output);
+        assert!(function.derivative_from_output_inplace(&mut from_output));
+        for (expected, actual) in from_input.iter().zip(from_output.iter()) {
+            assert!((expected - actual).abs() < 1e-5, "{}: {} vs {}", function, expected, actual);
+        }
+    }
+
+    for function in [ActivationFunction::PRELU(0.25), ActivationFunction::GELU, ActivationFunction::SWISH,
+                     ActivationFunction::MISH, ActivationFunction::LEAKY_RELU(-0.5), ActivationFunction::ELU(-1.0)] {
+        assert!(!function.derivative_uses_output(), "{}", function);
+
+        // The output is left for the caller to fall back to the derivative of the input
+        let mut output = x.clone();
+        assert!(!function.derivative_from_output_inplace(&mut output), "{}", function);
+        assert_eq!(output, x);
+    }
+
+    // Softmax has no element-wise derivative, only the diagonal of its Jacobian
+    let mut output = x.clone();
+    assert!(!ActivationFunction::SOFTMAX.derivative_from_output_inplace(&mut output));
+    assert_eq!(output, x);
+}
+
+#[test]
+fn activation_layer_backward_twice_test() {
+    let input = array![[-1.5, 0.2], [0.7, 2.0]];
+    let output_gradient = array![[1.0, -2.0], [0.5, 3.0]];
+    for function in [ActivationFunction::SIGMOID, ActivationFunction::GELU, ActivationFunction::SOFTMAX] {
+        let mut layer = ActivationLayer::new(function.clone());
+        layer.forward_train(&input, &mut StdRng::seed_from_u64(0));
+
+        // Back propagating leaves the cached forward pass intact
+        let first = layer.backward(&output_gradient);
+        assert_eq!(layer.backward(&output_gradient), first, "{}", function);
+    }
+}
+
+#[test]
+fn sigmoid_layer_large_input_test() {
+    let mut layer = ActivationLayer::new(ActivationFunction::SIGMOID);
+    let output = layer.forward_train(&array![[-1000.0, 0.0, 1000.0]], &mut StdRng::seed_from_u64(0));
+    assert_eq!(output, array![[0.0, 0.5, 1.0]]);
+    assert_eq!(layer.backward(&array![[1.0, 2.0, 1.0]]), array![[0.0, 0.5, 0.0]]);
+}
+
+#[test]
+fn prelu_learns_slope_test() {
+    let mut layer = ActivationLayer::new(ActivationFunction::PRELU(0.25));
+    assert_eq!(layer.parameters(), vec![&array![[0.25]]]);
+
+    let input = array![[-2.0, 1.0], [-1.0, 3.0]];
+    assert_eq!(layer.forward_train(&input, &mut StdRng::seed_from_u64(0)), array![[-0.5, 1.0], [-0.25, 3.0]]);
+    let input_gradient = layer.backward(&array![[1.0, 1.0], [2.0, 1.0]]);
+    assert_eq!(input_gradient, array![[0.25, 1.0], [0.5, 1.0]]);
+    // d/da of a * x summed over the negative inputs
+    assert_eq!(layer.gradients(), vec![&array![[-4.0]]]);
+
+    layer.parameters_mut()[0].value.fill(0.5);
+    assert_eq!(layer.activation_function(), ActivationFunction::PRELU(0.5));
+    assert_eq!(ActivationLayer::from_bytes(&layer.to_bytes()).unwrap().activation_function(), ActivationFunction::PRELU(0.5));
+}
+
+#[test]
+fn parametric_activation_network_io_test() {
+    let mut nn = Sequential::new(2)
+        .dense(4, ActivationFunction::PRELU(0.1))
+        .activation(ActivationFunction::ELU(0.5))
+        .dense(1, ActivationFunction::LEAKY_RELU(0.2))
+        .rng(Box::new(StdRng::seed_from_u64(4)))
+        .build();
+    let data = vec![(ColumnVector::from(&array![[1.0], [-1.0]]), ColumnVector::from(&array![[0.5]])),
+                    (ColumnVector::from(&array![[-1.0], [-2.0]]), ColumnVector::from(&array![[-0.5]]))];
+    nn.train(data.clone(), 20, 2, 0.1).unwrap();
+
+    // The PReLU slope is trained with the dense layer it belongs to
+    let slope = nn.dense_layers()[0].activation_function();
+    assert!(slope != ActivationFunction::PRELU(0.1));
+    assert_eq!(nn.parameters().len(), 5);
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+    assert_eq!(loaded.dense_layers()[0].activation_function(), slope);
+    assert_eq!(loaded.layers()[1].output_activation(), Some(ActivationFunction::ELU(0.5)));
+    assert_eq!(loaded.dense_layers()[1].activation_function(), ActivationFunction::LEAKY_RELU(0.2));
+    assert_eq!(loaded.parameters(), nn.parameters());
+    for (input, _) in data {
+        assert!(loaded.evaluate(input.clone()) == nn.evaluate(input));
+    }
+}
+
+#[test]
+fn unknown_activation_id_test() {
+    assert!(matches!(ActivationLayer::from_bytes(&bincode::serialize(&(40u8, 0f32)).unwrap()),
+                     Err(GraymatError::UnknownActivation(40))));
+}
+
+/// `scale * x^2 + shift` with an arbitrary shift, as a custom activation with two parameters
+struct ShiftedSquare {
+    scale: f32,
+    shift: f32
+}
+
+impl Activation for ShiftedSquare {
+    fn forward(&self, x: &mut Array2<f32>) {
+        x.mapv_inplace(|v| self.scale * v * v + self.shift);
+    }
+
+    fn derivative(&self, x: &mut Array2<f32>) {
+        x.mapv_inplace(|v| 2.0 * self.scale * v);
+    }
+
+    fn name(&self) -> &str {
+        "ShiftedSquare"
+    }
+
+    fn parameters(&self) -> Vec<f32> {
+        vec![self.scale, self.shift]
+    }
+}
+
+fn shifted_square(parameters: &[f32]) -> Arc<dyn Activation> {
+    Arc::new(ShiftedSquare { scale: parameters[0], shift: parameters[1] })
+}
+
+#[test]
+fn custom_activation_test() {
+    let function = ActivationFunction::custom(ShiftedSquare { scale: 0.5, shift: -1.0 });
+    assert_eq!(function.id(), 255);
+    assert_eq!(function.name(), "ShiftedSquare");
+    assert_eq!(function.to_string(), "ShiftedSquare(0.5, -1)");
+
+    let mut layer = ActivationLayer::new(function.clone());
+    let output = layer.forward_train(&array![[2.0, -1.0]], &mut StdRng::seed_from_u64(0));
+    assert_eq!(output, array![[1.0, -0.5]]);
+    assert_eq!(layer.backward(&array![[1.0, 2.0]]), array![[2.0, -2.0]]);
+
+    register_activation("ShiftedSquare", shifted_square).unwrap();
+    assert!(registered_activations().contains(&"ShiftedSquare".to_owned()));
+    assert_eq!(function.to_string().parse::<ActivationFunction>().unwrap(), function);
+    assert_eq!(ActivationFunction::registered("ShiftedSquare", &[0.5, -1.0]).unwrap(), function);
+    assert_eq!(ActivationLayer::from_bytes(&layer.to_bytes()).unwrap().activation_function(), function);
+}
+
+#[test]
+fn custom_activation_network_io_test() {
+    register_activation("ShiftedSquare", shifted_square).unwrap();
+    let function = ActivationFunction::registered("ShiftedSquare", &[0.25, 0.5]).unwrap();
+    let nn = Sequential::new(2)
+        .dense(3, function.clone())
+        .dense(1, ActivationFunction::SIGMOID)
+        .rng(Box::new(StdRng::seed_from_u64(6)))
+        .build();
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+    assert_eq!(loaded.dense_layers()[0].activation_function(), function);
+    let input = ColumnVector::from(&array![[0.3], [-0.7]]);
+    assert!(loaded.evaluate(input.clone()) == nn.evaluate(input));
+}
+
+#[test]
+fn missing_custom_activation_test() {
+    let nn = Sequential::new(2)
+        .dense(1, ActivationFunction::custom(ShiftedSquare { scale: 1.0, shift: 0.0 }))
+        .build();
+    let mut bytes = nn.to_bytes().unwrap();
+    // Rename the function so it cannot be registered by another test
+    let name = bytes.windows(13).position(|w| w == b"ShiftedSquare").unwrap();
+    bytes[name..name + 13].copy_from_slice(b"UnknownSquare");
+    let size_bytes = u64::from_le_bytes(bytes[40 + 1..40 + 9].try_into().unwrap()) as usize;
+    let checksum = crc32(&bytes[40 + 13..40 + 13 + size_bytes]);
+    bytes[40 + 9..40 + 13].copy_from_slice(&checksum.to_le_bytes());
+
+    let error = NeuralNetwork::from_bytes(&bytes).err().unwrap();
+    assert!(matches!(&error, GraymatError::UnknownActivationName(name) if name == "UnknownSquare"));
+    assert_eq!(error.to_string(), "Unknown activation function: UnknownSquare");
+}
+
+#[test]
+fn register_built_in_name_test() {
+    let error = register_activation("ELU", shifted_square).err().unwrap();
+    assert!(matches!(&error, GraymatError::BuiltInActivationName(name) if name == "ELU"));
+    assert_eq!(error.to_string(), "ELU is a built-in activation function");
+    assert!(!registered_activations().contains(&"ELU".to_owned()));
+}
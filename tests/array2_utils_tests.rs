@@ -0,0 +1,56 @@
+use ndarray::array;
+use graymat::utilities::array2_utils::math::{sig_inplace, sig_prime_from_output_inplace, sig_prime_inplace, softmax,
+                                             softmax_backward, tanh_inplace, tanh_prime_from_output_inplace, tanh_prime_inplace};
+
+#[test]
+fn softmax_test() {
+    let result = softmax(&array![[1.0, 0.0], [2.0, 0.0], [3.0, 0.0]]);
+    let expected = [0.09003057, 0.24472847, 0.66524096];
+    for i in 0..3 {
+        assert!((result[[i, 0]] - expected[i]).abs() < 1e-6);
+        assert!((result[[i, 1]] - 1.0 / 3.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn softmax_large_input_test() {
+    let result = softmax(&array![[1000.0], [1000.0], [-1000.0]]);
+    assert!(result.iter().all(|x| x.is_finite()));
+    assert!((result[[0, 0]] - 0.5).abs() < 1e-6);
+    assert_eq!(result[[2, 0]], 0.0);
+}
+
+#[test]
+fn softmax_backward_test() {
+    // Compare against the explicit Jacobian J[i][j] = s_i * (delta_ij - s_j)
+    let s = softmax(&array![[0.5], [-1.0], [2.0]]);
+    let error = array![[0.3], [-0.7], [1.1]];
+    let result = softmax_backward(&s, &error);
+    for i in 0..3 {
+        let mut expected = 0.0;
+        for j in 0..3 {
+            let kronecker = if i == j { 1.0 } else { 0.0 };
+            expected += s[[i, 0]] * (kronecker - s[[j, 0]]) * error[[j, 0]];
+        }
+        assert!((result[[i, 0]] - expected).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn prime_from_output_inplace_test() {
+    let x = array![[-3.0, -0.5, 0.0], [0.5, 2.0, -1000.0]];
+
+    let mut from_input = x.clone();
+    sig_prime_inplace(&mut from_input);
+    let mut from_output = x.clone();
+    sig_inplace(&mut from_output);
+    sig_prime_from_output_inplace(&mut from_output);
+    assert!((from_input - from_output).iter().all(|d| d.abs() < 1e-6));
+
+    let mut from_input = x.clone();
+    tanh_prime_inplace(&mut from_input);
+    let mut from_output = x.clone();
+    tanh_inplace(&mut from_output);
+    tanh_prime_from_output_inplace(&mut from_output);
+    assert!((from_input - from_output).iter().all(|d| d.abs() < 1e-6));
+}
@@ -0,0 +1,125 @@
+use ndarray::{s, Array2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::activation_function::ActivationFunction;
+use graymat::attention::{MultiHeadAttention, PositionalEncoding, TransformerEncoder};
+use graymat::column_vector::ColumnVector;
+use graymat::initializer::Initializer;
+use graymat::layer::{Layer, LayerType};
+use graymat::neural_network::NeuralNetwork;
+use graymat::sequential::Sequential;
+
+mod common;
+
+/// Two sequences of 3 positions of 4 features
+fn inputs() -> Array2<f32> {
+    common::inputs(12)
+}
+
+#[test]
+fn attention_gradient_test() {
+    let mut attention = MultiHeadAttention::new(4, 2, 3);
+    attention.initialize(Initializer::UNIFORM(-0.8, 0.8), Initializer::UNIFORM(-0.5, 0.5), &mut StdRng::seed_from_u64(2));
+    common::check_gradients(&mut attention, &inputs(), 3e-2);
+
+    let weights = attention.attention_weights().unwrap();
+    assert_eq!(weights.len(), 4);
+    for head in weights {
+        assert_eq!(head.dim(), (3, 3));
+        assert!(head.rows().into_iter().all(|row| (row.sum() - 1.0).abs() < 1e-5));
+    }
+}
+
+#[test]
+fn transformer_encoder_gradient_test() {
+    let mut encoder = TransformerEncoder::new(4, 2, 6, 3);
+    encoder.initialize(Initializer::UNIFORM(-0.8, 0.8), Initializer::UNIFORM(-0.5, 0.5), &mut StdRng::seed_from_u64(3));
+    assert_eq!(encoder.parameters().len(), 16);
+    common::check_gradients(&mut encoder, &inputs(), 3e-2);
+}
+
+#[test]
+fn attention_permutation_test() {
+    let mut attention = MultiHeadAttention::new(4, 2, 3);
+    attention.initialize(Initializer::UNIFORM(-0.8, 0.8), Initializer::UNIFORM(-0.5, 0.5), &mut StdRng::seed_from_u64(4));
+
+    // Without positional encodings, reordering the positions only reorders the outputs
+    let input = inputs();
+    let mut swapped = input.clone();
+    swapped.slice_mut(s![0..4, ..]).assign(&input.slice(s![8..12, ..]));
+    swapped.slice_mut(s![8..12, ..]).assign(&input.slice(s![0..4, ..]));
+
+    let output = attention.forward(&input);
+    let swapped_output = attention.forward(&swapped);
+    for (a, b) in [(0, 8), (4, 4), (8, 0)] {
+        let difference = &output.slice(s![a..a + 4, ..]) - &swapped_output.slice(s![b..b + 4, ..]);
+        assert!(difference.iter().all(|d| d.abs() < 1e-5));
+    }
+}
+
+#[test]
+fn positional_encoding_test() {
+    let mut encoding = PositionalEncoding::new(4, 3);
+    assert_eq!(encoding.output_size(12), Some(12));
+    assert_eq!(encoding.output_size(8), None);
+
+    let output = encoding.forward(&Array2::zeros((12, 2)));
+    assert_eq!(output.column(0), output.column(1));
+    assert_eq!(output.slice(s![0..4, 0]).to_vec(), vec![0.0, 1.0, 0.0, 1.0]);
+    let expected = [1f32.sin(), 1f32.cos(), 0.01f32.sin(), 0.01f32.cos()];
+    for (actual, expected) in output.slice(s![4..8, 0]).iter().zip(expected.iter()) {
+        assert!((actual - expected).abs() < 1e-6);
+    }
+
+    let gradient = inputs();
+    assert_eq!(encoding.backward(&gradient), gradient);
+    assert!(encoding.parameters().is_empty());
+}
+
+/// Every sequence of 4 values in {0, 1, 2}, one-hot encoded with 4 features, labelled with the
+/// one-hot encoding of the sorted sequence
+fn sorting_examples() -> Vec<(ColumnVector, ColumnVector)> {
+    (0..81).map(|i| {
+        let values: Vec<usize> = (0..4).map(|t| (i / 3usize.pow(t)) % 3).collect();
+        let mut sorted = values.clone();
+        sorted.sort();
+        let input = Array2::from_shape_fn((16, 1), |(row, _)| if values[row / 4] == row % 4 { 1.0 } else { 0.0 });
+        let label = Array2::from_shape_fn((12, 1), |(row, _)| if sorted[row / 3] == row % 3 { 1.0 } else { 0.0 });
+        (ColumnVector::from(&input), ColumnVector::from(&label))
+    }).collect()
+}
+
+/// Fraction of positions whose largest output is the sorted value
+fn sorting_accuracy(nn: &NeuralNetwork) -> f32 {
+    let argmax = |values: &[f32]| (0..values.len()).fold(0, |best, i| if values[i] > values[best] { i } else { best });
+    let mut correct = 0;
+    for (input, label) in sorting_examples() {
+        let output = nn.evaluate(input).get_data().column(0).to_vec();
+        let label = label.get_data().column(0).to_vec();
+        correct += (0..4).filter(|t| argmax(&output[t * 3..t * 3 + 3]) == argmax(&label[t * 3..t * 3 + 3])).count();
+    }
+    correct as f32 / (81.0 * 4.0)
+}
+
+#[test]
+fn transformer_sorting_and_io_test() {
+    let mut nn = Sequential::new(16)
+        .layer(Box::new(PositionalEncoding::new(4, 4)))
+        .layer(Box::new(TransformerEncoder::new(4, 2, 16, 4)))
+        .dense(12, ActivationFunction::SIGMOID)
+        .rng(Box::new(StdRng::seed_from_u64(7)))
+        .build();
+
+    let loss_before = nn.calculate_loss(&sorting_examples());
+    nn.train(sorting_examples(), 200, 9, 1.0).unwrap();
+    assert!(nn.calculate_loss(&sorting_examples()) < loss_before / 4.0);
+    assert!(sorting_accuracy(&nn) > 0.9);
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+    let layer_types: Vec<LayerType> = loaded.layers().iter().map(|layer| layer.layer_type()).collect();
+    assert_eq!(layer_types, vec![LayerType::POSITIONAL_ENCODING, LayerType::TRANSFORMER_ENCODER, LayerType::DENSE]);
+    assert_eq!(loaded.parameters(), nn.parameters());
+    for (input, _) in sorting_examples() {
+        assert!(loaded.evaluate(input.clone()) == nn.evaluate(input));
+    }
+}
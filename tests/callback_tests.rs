@@ -0,0 +1,198 @@
+use std::env;
+use std::cell::RefCell;
+use std::rc::Rc;
+use ndarray::array;
+use graymat::activation_function::ActivationFunction;
+use graymat::callback::{Callback, EarlyStopping, ModelCheckpoint, Monitor, ProgressLogger};
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
+use graymat::error::GraymatError;
+use graymat::metrics::Metric;
+use graymat::neural_network::NeuralNetwork;
+use graymat::normalization::Normalization;
+use graymat::training_history::{EpochRecord, TrainingHistory};
+
+fn xor_data() -> Vec<(ColumnVector, ColumnVector)> {
+    vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![1]), (cvec![0, 0], cvec![0]), (cvec![1, 1], cvec![0])]
+}
+
+#[derive(Default)]
+struct Counts {
+    train_start: usize,
+    train_end: usize,
+    epoch_start: usize,
+    epoch_end: usize,
+    batch_start: usize,
+    batch_end: usize
+}
+
+struct CountingCallback {
+    counts: Rc<RefCell<Counts>>
+}
+
+impl Callback for CountingCallback {
+    fn on_train_start(&mut self, _network: &mut NeuralNetwork, _epochs: usize) {
+        self.counts.borrow_mut().train_start += 1;
+    }
+
+    fn on_train_end(&mut self, _network: &mut NeuralNetwork, _history: &TrainingHistory) {
+        self.counts.borrow_mut().train_end += 1;
+    }
+
+    fn on_epoch_start(&mut self, _network: &mut NeuralNetwork, _epoch: usize) {
+        self.counts.borrow_mut().epoch_start += 1;
+    }
+
+    fn on_epoch_end(&mut self, _network: &mut NeuralNetwork, _record: &EpochRecord) -> Result<(), GraymatError> {
+        self.counts.borrow_mut().epoch_end += 1;
+        return Ok(());
+    }
+
+    fn on_batch_start(&mut self, _network: &mut NeuralNetwork, _batch: usize) {
+        self.counts.borrow_mut().batch_start += 1;
+    }
+
+    fn on_batch_end(&mut self, _network: &mut NeuralNetwork, _batch: usize, _loss: f32) {
+        self.counts.borrow_mut().batch_end += 1;
+    }
+}
+
+/// Overwrites every weight after each epoch, so the loss can only get worse
+struct Saboteur {}
+
+impl Callback for Saboteur {
+    fn on_epoch_end(&mut self, network: &mut NeuralNetwork, _record: &EpochRecord) -> Result<(), GraymatError> {
+        let parameters: Vec<_> = network.parameters().iter().map(|p| p.mapv(|_| 10.0)).collect();
+        network.set_parameters(&parameters);
+        return Ok(());
+    }
+}
+
+/// Pushes the output bias far negative after each epoch, so the loss gets worse while the hidden
+/// layer keeps training
+struct OutputSaboteur {}
+
+impl Callback for OutputSaboteur {
+    fn on_epoch_end(&mut self, network: &mut NeuralNetwork, _record: &EpochRecord) -> Result<(), GraymatError> {
+        let mut parameters = network.parameters();
+        parameters.last_mut().unwrap().fill(-10.0);
+        network.set_parameters(&parameters);
+        return Ok(());
+    }
+}
+
+#[test]
+fn callback_invocation_test() {
+    let counts = Rc::new(RefCell::new(Counts::default()));
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], ActivationFunction::SIGMOID);
+    nn.add_callback(Box::new(CountingCallback { counts: counts.clone() }));
+
+    nn.train(xor_data(), 5, 2, 0.1).unwrap();
+    nn.train(xor_data(), 5, 2, 0.1).unwrap();
+
+    let counts = counts.borrow();
+    assert_eq!(counts.train_start, 2);
+    assert_eq!(counts.train_end, 2);
+    assert_eq!(counts.epoch_start, 10);
+    assert_eq!(counts.epoch_end, 10);
+    assert_eq!(counts.batch_start, 20);
+    assert_eq!(counts.batch_end, 20);
+}
+
+#[test]
+fn early_stopping_restores_best_weights_test() {
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
+    let initial = nn.parameters();
+    nn.add_callback(Box::new(EarlyStopping::new(Monitor::TRAIN_LOSS, 2, 0.0, true)));
+    nn.add_callback(Box::new(Saboteur {}));
+
+    // With a learning rate of 0 only the saboteur changes the network, so the first epoch is best
+    let history = nn.train(xor_data(), 100, 4, 0.0).unwrap();
+
+    assert_eq!(history.epochs().len(), 3);
+    assert_eq!(nn.parameters(), initial);
+}
+
+#[test]
+fn early_stopping_restores_best_normalization_statistics_test() {
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
+    nn.set_layer_normalization(0, Some(Normalization::batch(3)));
+    let mut best = NeuralNetwork::new(2, 1, vec![3], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
+    best.set_layer_normalization(0, Some(Normalization::batch(3)));
+    best.set_parameters(&nn.parameters());
+    best.train(xor_data(), 1, 4, 0.0).unwrap();
+
+    nn.add_callback(Box::new(EarlyStopping::new(Monitor::TRAIN_LOSS, 2, 0.0, true)));
+    nn.add_callback(Box::new(OutputSaboteur {}));
+    nn.train(xor_data(), 100, 4, 0.0).unwrap();
+
+    // The running statistics keep moving towards the batch statistics after the first epoch,
+    // but are restored to those of the first epoch
+    assert_eq!(nn.buffers().len(), 2);
+    for (restored, expected) in nn.buffers().iter().zip(best.buffers().iter()) {
+        assert!((restored - expected).iter().all(|d| d.abs() < 1e-6));
+    }
+    assert_eq!(nn.parameters(), best.parameters());
+}
+
+#[test]
+fn early_stopping_min_delta_test() {
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
+    nn.set_metrics(vec![Metric::ACCURACY]);
+    nn.add_callback(Box::new(EarlyStopping::new(Monitor::VALIDATION_LOSS, 3, 1.0, false)));
+
+    // No epoch can improve the validation loss by more than 1
+    let validation_data = xor_data();
+    let history = nn.train_with_validation(xor_data(), Some(&validation_data), 100, 2, 0.5).unwrap();
+
+    assert_eq!(history.epochs().len(), 4);
+}
+
+#[test]
+fn model_checkpoint_test() {
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
+    nn.add_callback(Box::new(ModelCheckpoint::new(env::temp_dir(), "callback_checkpoint_test", Monitor::TRAIN_LOSS)));
+    nn.add_callback(Box::new(Saboteur {}));
+
+    let expected = nn.parameters();
+    nn.train(xor_data(), 5, 4, 0.0).unwrap();
+
+    // Only the first epoch improved, before the saboteur ran
+    let loaded = NeuralNetwork::from_file(env::temp_dir(), "callback_checkpoint_test").unwrap();
+    assert_eq!(loaded.parameters(), expected);
+}
+
+#[test]
+fn model_checkpoint_save_error_test() {
+    let counts = Rc::new(RefCell::new(Counts::default()));
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], ActivationFunction::SIGMOID);
+    nn.add_callback(Box::new(ModelCheckpoint::new("./missing_checkpoint_directory", "checkpoint", Monitor::TRAIN_LOSS)));
+    nn.add_callback(Box::new(CountingCallback { counts: counts.clone() }));
+
+    let result = nn.train(xor_data(), 5, 4, 0.1);
+
+    assert!(matches!(result, Err(GraymatError::FileNotFound(_))));
+    assert_eq!(counts.borrow().epoch_start, 1);
+    assert_eq!(counts.borrow().train_end, 0);
+
+    // The callbacks are still registered after the failed run
+    assert!(nn.train(xor_data(), 5, 4, 0.1).is_err());
+    assert_eq!(counts.borrow().train_start, 2);
+}
+
+#[test]
+fn progress_logger_format_test() {
+    let record = EpochRecord {
+        epoch: 10,
+        train_loss: 0.15321,
+        validation_loss: Some(0.171),
+        learning_rate: 0.1,
+        metrics: vec![(Metric::ACCURACY, 0.95)],
+        validation_metrics: vec![]
+    };
+    let mut logger = ProgressLogger::new(10);
+    let mut nn = NeuralNetwork::new(1, 1, vec![], ActivationFunction::LINEAR);
+    logger.on_train_start(&mut nn, 100);
+
+    assert_eq!(logger.format(&record), "Epoch 10/100 - loss: 0.1532 - validation_loss: 0.1710 - accuracy: 0.9500");
+}
@@ -0,0 +1,52 @@
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::layer::Layer;
+
+/// Two examples of `rows` inputs each, spread over [-0.4, 0.46]
+pub fn inputs(rows: usize) -> Array2<f32> {
+    Array2::from_shape_fn((rows, 2), |(i, j)| ((i * 5 + j * 3) % 7) as f32 / 7.0 - 0.4)
+}
+
+/// Weighted sum of the outputs, so every output gets a different gradient
+fn loss(layer: &dyn Layer, input: &Array2<f32>) -> f32 {
+    let output = layer.forward(input);
+    output.indexed_iter().map(|((i, j), y)| y * (1.0 + i as f32 * 0.3 - j as f32 * 0.2)).sum()
+}
+
+/// Gradient of the weighted sum with respect to the outputs
+pub fn loss_gradient(output: &Array2<f32>) -> Array2<f32> {
+    Array2::from_shape_fn(output.dim(), |(i, j)| 1.0 + i as f32 * 0.3 - j as f32 * 0.2)
+}
+
+/// Compare every analytic parameter and input gradient of a layer with central differences
+///
+/// * `layer` - Layer to check, its parameters are restored afterwards
+/// * `inputs` - Inputs (features x batch)
+/// * `tolerance` - Largest accepted difference between the analytic and numerical gradient
+pub fn check_gradients(layer: &mut dyn Layer, inputs: &Array2<f32>, tolerance: f32) {
+    let output = layer.forward_train(inputs, &mut StdRng::seed_from_u64(0));
+    let input_gradient = layer.backward(&loss_gradient(&output));
+    let gradients: Vec<Array2<f32>> = layer.gradients().into_iter().cloned().collect();
+
+    let h = 1e-2;
+    for (p, gradient) in gradients.iter().enumerate() {
+        for ((i, j), analytic) in gradient.indexed_iter() {
+            layer.parameters_mut()[p].value[[i, j]] += h;
+            let plus = loss(layer, inputs);
+            layer.parameters_mut()[p].value[[i, j]] -= 2.0 * h;
+            let minus = loss(layer, inputs);
+            layer.parameters_mut()[p].value[[i, j]] += h;
+            let numerical = (plus - minus) / (2.0 * h);
+            assert!((analytic - numerical).abs() < tolerance, "parameter {} ({}, {}): {} vs {}", p, i, j, analytic, numerical);
+        }
+    }
+    for ((i, j), analytic) in input_gradient.indexed_iter() {
+        let mut plus = inputs.clone();
+        plus[[i, j]] += h;
+        let mut minus = inputs.clone();
+        minus[[i, j]] -= h;
+        let numerical = (loss(layer, &plus) - loss(layer, &minus)) / (2.0 * h);
+        assert!((analytic - numerical).abs() < tolerance, "input ({}, {}): {} vs {}", i, j, analytic, numerical);
+    }
+}
@@ -0,0 +1,172 @@
+use ndarray::{array, Array2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::convolution::{AvgPool2D, Conv2D, Flatten, MaxPool2D};
+use graymat::error::GraymatError;
+use graymat::initializer::Initializer;
+use graymat::layer::{Layer, LayerType};
+use graymat::loss::CategoricalCrossEntropy;
+use graymat::neural_network::NeuralNetwork;
+use graymat::sequential::Sequential;
+use graymat::utilities::checksum::crc32;
+
+/// A single channel 3x3 image as a column
+fn image() -> Array2<f32> {
+    array![[1.0], [2.0], [3.0], [4.0], [5.0], [6.0], [7.0], [8.0], [9.0]]
+}
+
+/// Sum of the outputs of a layer, whose gradient with respect to the outputs is 1
+fn output_sum(layer: &dyn Layer, input: &Array2<f32>) -> f32 {
+    layer.forward(input).sum()
+}
+
+#[test]
+fn conv2d_forward_test() {
+    let mut conv = Conv2D::new((1, 3, 3), 2, 2, 1, 0);
+    conv.weights_mut().assign(&array![[1.0, 0.0, 0.0, 1.0], [0.5, 0.5, -1.0, 0.0]]);
+    conv.biases_mut().assign(&array![[0.0], [1.0]]);
+    assert_eq!(conv.output_shape(), (2, 2, 2));
+
+    let output = conv.forward(&image());
+    let expected = array![[6.0], [8.0], [12.0], [14.0], [-1.5], [-1.5], [-1.5], [-1.5]];
+    assert_eq!(output, expected);
+
+    // One pixel of zero padding keeps a 3x3 kernel from shrinking the image
+    let mut padded = Conv2D::new((1, 3, 3), 1, 3, 1, 1);
+    padded.weights_mut().fill(1.0);
+    let output = padded.forward(&image());
+    assert_eq!(output.column(0).to_vec(), vec![12.0, 21.0, 16.0, 27.0, 45.0, 33.0, 24.0, 39.0, 28.0]);
+}
+
+#[test]
+fn conv2d_gradient_test() {
+    let mut conv = Conv2D::new((2, 4, 4), 3, 3, 2, 1);
+    conv.initialize(Initializer::UNIFORM(-1.0, 1.0), Initializer::UNIFORM(-1.0, 1.0), &mut StdRng::seed_from_u64(1));
+    let inputs = Array2::from_shape_fn((32, 2), |(i, j)| ((i * 7 + j * 3) % 11) as f32 / 11.0 - 0.5);
+
+    let outputs = conv.forward_train(&inputs, &mut StdRng::seed_from_u64(0));
+    let input_gradient = conv.backward(&Array2::ones(outputs.dim()));
+    let weight_gradient = conv.gradients()[0].clone();
+
+    let h = 1e-2;
+    for (i, j) in [(0, 0), (1, 5), (2, 17)] {
+        let mut plus = conv.clone();
+        plus.weights_mut()[[i, j]] += h;
+        let mut minus = conv.clone();
+        minus.weights_mut()[[i, j]] -= h;
+        let numerical = (output_sum(&plus, &inputs) - output_sum(&minus, &inputs)) / (2.0 * h);
+        assert!((weight_gradient[[i, j]] - numerical).abs() < 1e-2);
+    }
+    for (i, j) in [(0, 0), (5, 1), (21, 0), (31, 1)] {
+        let mut plus = inputs.clone();
+        plus[[i, j]] += h;
+        let mut minus = inputs.clone();
+        minus[[i, j]] -= h;
+        let numerical = (output_sum(&conv, &plus) - output_sum(&conv, &minus)) / (2.0 * h);
+        assert!((input_gradient[[i, j]] - numerical).abs() < 1e-2);
+    }
+    assert_eq!(conv.gradients()[1], Array2::from_elem((3, 1), 8.0));
+}
+
+#[test]
+fn max_pool2d_test() {
+    let mut pool = MaxPool2D::new((1, 3, 3), 2, 1);
+    assert_eq!(pool.output_shape(), (1, 2, 2));
+
+    let output = pool.forward_train(&image(), &mut StdRng::seed_from_u64(0));
+    assert_eq!(output, array![[5.0], [6.0], [8.0], [9.0]]);
+
+    // Each gradient flows back to the maximum of its window
+    let input_gradient = pool.backward(&array![[1.0], [2.0], [3.0], [4.0]]);
+    assert_eq!(input_gradient.column(0).to_vec(), vec![0.0, 0.0, 0.0, 0.0, 1.0, 2.0, 0.0, 3.0, 4.0]);
+}
+
+#[test]
+fn avg_pool2d_test() {
+    let mut pool = AvgPool2D::new((2, 2, 2), 2, 2);
+    assert_eq!(pool.output_shape(), (2, 1, 1));
+
+    let inputs = array![[1.0, 0.0], [2.0, 0.0], [3.0, 0.0], [6.0, 4.0], [0.0, 8.0], [0.0, 0.0], [0.0, 0.0], [4.0, 0.0]];
+    let output = pool.forward_train(&inputs, &mut StdRng::seed_from_u64(0));
+    assert_eq!(output, array![[3.0, 1.0], [1.0, 2.0]]);
+
+    let input_gradient = pool.backward(&array![[4.0, 0.0], [0.0, 8.0]]);
+    assert_eq!(input_gradient.column(0).to_vec(), vec![1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 0.0]);
+    assert_eq!(input_gradient.column(1).to_vec(), vec![0.0, 0.0, 0.0, 0.0, 2.0, 2.0, 2.0, 2.0]);
+}
+
+/// 4x4 images of a vertical or a horizontal bar, labelled [1, 0] and [0, 1]
+fn bars() -> Vec<(ColumnVector, ColumnVector)> {
+    let mut examples = Vec::new();
+    for line in 0..4 {
+        let vertical = Array2::from_shape_fn((16, 1), |(i, _)| if i % 4 == line { 1.0 } else { 0.0 });
+        let horizontal = Array2::from_shape_fn((16, 1), |(i, _)| if i / 4 == line { 1.0 } else { 0.0 });
+        examples.push((ColumnVector::from(&vertical), ColumnVector::from(&array![[1.0], [0.0]])));
+        examples.push((ColumnVector::from(&horizontal), ColumnVector::from(&array![[0.0], [1.0]])));
+    }
+    examples
+}
+
+fn convolutional_network() -> NeuralNetwork {
+    let conv = Conv2D::new((1, 4, 4), 4, 3, 1, 1);
+    let pool = MaxPool2D::new(conv.output_shape(), 2, 2);
+    let flatten = Flatten::new(pool.output_shape());
+    let mut nn = Sequential::new(16)
+        .layer(Box::new(conv))
+        .activation(ActivationFunction::RELU)
+        .layer(Box::new(pool))
+        .layer(Box::new(flatten))
+        .dense(2, ActivationFunction::SOFTMAX)
+        .initializer(Initializer::HE_UNIFORM, Initializer::ZEROS)
+        .rng(Box::new(StdRng::seed_from_u64(5)))
+        .build();
+    nn.set_loss(Box::new(CategoricalCrossEntropy::new()));
+    nn
+}
+
+#[test]
+fn convolutional_network_training_test() {
+    let mut nn = convolutional_network();
+    let loss_before = nn.calculate_loss(&bars());
+    nn.train(bars(), 100, 4, 0.1).unwrap();
+    assert!(nn.calculate_loss(&bars()) < loss_before / 4.0);
+}
+
+#[test]
+fn convolutional_network_io_test() {
+    let mut nn = convolutional_network();
+    nn.train(bars(), 5, 4, 0.1).unwrap();
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+
+    let layer_types: Vec<LayerType> = loaded.layers().iter().map(|layer| layer.layer_type()).collect();
+    assert_eq!(layer_types, vec![LayerType::CONV2D, LayerType::ACTIVATION, LayerType::MAX_POOL2D,
+                                 LayerType::FLATTEN, LayerType::DENSE]);
+    assert_eq!(loaded.parameters(), nn.parameters());
+    for (input, _) in bars() {
+        assert!(loaded.evaluate(input.clone()) == nn.evaluate(input));
+    }
+}
+
+#[test]
+fn corrupt_convolution_file_test() {
+    let bytes = convolutional_network().to_bytes().unwrap();
+
+    // The convolution is the first layer. Its window starts with the input shape (3 x u64)
+    // followed by the window size, stride and padding. Overflowing or oversized declarations are
+    // rejected before any buffer is allocated from them.
+    let height = 8;
+    let padding = 40;
+    for (offset, value) in [(padding, u64::MAX), (padding, 1 << 40), (height, 1 << 40)] {
+        let mut corrupt = bytes.clone();
+        corrupt[53 + offset..53 + offset + 8].copy_from_slice(&value.to_le_bytes());
+        let size_bytes = u64::from_le_bytes(corrupt[41..49].try_into().unwrap()) as usize;
+        let checksum = crc32(&corrupt[53..53 + size_bytes]);
+        corrupt[49..53].copy_from_slice(&checksum.to_le_bytes());
+
+        let result = NeuralNetwork::from_bytes(&corrupt);
+        assert!(matches!(result, Err(GraymatError::InvalidHeader(_)) | Err(GraymatError::ShapeMismatch(_))));
+    }
+}
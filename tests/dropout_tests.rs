@@ -0,0 +1,90 @@
+use ndarray::array;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::activation_function::ActivationFunction;
+use graymat::cvec;
+use graymat::error::GraymatError;
+use graymat::column_vector::ColumnVector;
+use graymat::initializer::Initializer;
+use graymat::neural_network::NeuralNetwork;
+
+fn seeded_network(seed: u64) -> NeuralNetwork {
+    NeuralNetwork::with_rng(2, 1, vec![8], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID],
+                            Initializer::XAVIER_NORMAL, Initializer::UNIFORM(-0.5, 0.5),
+                            Box::new(StdRng::seed_from_u64(seed)))
+}
+
+fn training_data() -> Vec<(ColumnVector, ColumnVector)> {
+    vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![1]), (cvec![0, 0], cvec![0]), (cvec![1, 1], cvec![0])]
+}
+
+#[test]
+fn evaluate_ignores_dropout_test() {
+    let mut nn = seeded_network(3);
+    let expected = nn.evaluate(cvec![0.3, -0.7]);
+    nn.set_layer_dropout(0, 0.5).unwrap();
+
+    for _ in 0..10 {
+        assert!(nn.evaluate(cvec![0.3, -0.7]) == expected);
+    }
+}
+
+#[test]
+fn zero_dropout_matches_no_dropout_test() {
+    let mut with_dropout = seeded_network(5);
+    let mut without_dropout = seeded_network(5);
+    with_dropout.set_layer_dropout(0, 0.0).unwrap();
+
+    with_dropout.train(training_data(), 20, 2, 0.5).unwrap();
+    without_dropout.train(training_data(), 20, 2, 0.5).unwrap();
+
+    assert_eq!(with_dropout.to_bytes().unwrap(), without_dropout.to_bytes().unwrap());
+}
+
+#[test]
+fn dropped_units_are_not_updated_test() {
+    let mut nn = seeded_network(11);
+    nn.set_layer_dropout(0, 0.5).unwrap();
+    let before = nn.parameters();
+
+    nn.train(vec![(cvec![0.4, -0.9], cvec![1])], 1, 1, 0.5).unwrap();
+    let after = nn.parameters();
+
+    // A dropped hidden unit receives no gradient, and neither do the output weights reading it
+    let mut dropped = 0;
+    for unit in 0..8 {
+        let incoming_changed = before[0].row(unit) != after[0].row(unit);
+        let bias_changed = before[1][[unit, 0]] != after[1][[unit, 0]];
+        let outgoing_changed = before[2][[0, unit]] != after[2][[0, unit]];
+        assert_eq!(incoming_changed, bias_changed);
+        assert_eq!(incoming_changed, outgoing_changed);
+        if !incoming_changed {
+            dropped += 1;
+        }
+    }
+    assert!(dropped > 0 && dropped < 8);
+}
+
+#[test]
+fn dropout_is_reproducible_with_seed_test() {
+    let mut first = seeded_network(13);
+    let mut second = seeded_network(13);
+    first.set_layer_dropout(0, 0.3).unwrap();
+    second.set_layer_dropout(0, 0.3).unwrap();
+
+    first.train(training_data(), 20, 2, 0.5).unwrap();
+    second.train(training_data(), 20, 2, 0.5).unwrap();
+
+    assert_eq!(first.to_bytes().unwrap(), second.to_bytes().unwrap());
+}
+
+#[test]
+fn invalid_layer_dropout_test() {
+    let mut nn = seeded_network(1);
+
+    // The output layer, a rate of 1 and a layer that does not exist
+    for (layer_index, rate) in [(1, 0.5), (0, 1.0), (0, -0.1), (5, 0.5)] {
+        assert!(matches!(nn.set_layer_dropout(layer_index, rate), Err(GraymatError::InvalidArgument(_))));
+    }
+    assert_eq!(nn.dense_layers()[0].dropout(), 0.0);
+}
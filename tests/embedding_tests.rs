@@ -0,0 +1,141 @@
+use ndarray::{array, Array2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::embedding::Embedding;
+use graymat::error::GraymatError;
+use graymat::layer::{Layer, LayerType};
+use graymat::neural_network::NeuralNetwork;
+use graymat::optimizer::Adam;
+use graymat::sequential::Sequential;
+
+/// Two indices into a vocabulary of 3 followed by one numeric feature
+fn embedding() -> Embedding {
+    let mut embedding = Embedding::new(3, 2, 2);
+    embedding.set_features(1);
+    embedding.embeddings_mut().assign(&array![[0.1, 0.2], [0.3, 0.4], [0.5, 0.6]]);
+    embedding
+}
+
+#[test]
+fn embedding_forward_test() {
+    let embedding = embedding();
+    assert_eq!(embedding.output_size(3), Some(5));
+    assert_eq!(embedding.output_size(2), None);
+
+    let output = embedding.forward(&array![[2.0, 0.0], [0.0, 0.0], [7.5, -1.0]]);
+    assert_eq!(output, array![[0.5, 0.1], [0.6, 0.2], [0.1, 0.1], [0.2, 0.2], [7.5, -1.0]]);
+    assert_eq!(embedding.embedding(1).to_vec(), vec![0.3, 0.4]);
+}
+
+#[test]
+fn embedding_sparse_gradient_test() {
+    let mut embedding = embedding();
+    embedding.forward_train(&array![[2.0, 0.0], [0.0, 0.0], [7.5, -1.0]], &mut StdRng::seed_from_u64(0));
+    let output_gradient = array![[1.0, 2.0], [1.0, 2.0], [3.0, 4.0], [3.0, 4.0], [5.0, 6.0]];
+    let input_gradient = embedding.backward(&output_gradient);
+
+    // Index 0 is looked up three times, index 1 never
+    assert_eq!(embedding.gradients()[0], &array![[9.0, 9.0], [0.0, 0.0], [1.0, 1.0]]);
+    assert_eq!(embedding.updated_rows(), &vec![0, 2]);
+    assert_eq!(input_gradient, array![[0.0, 0.0], [0.0, 0.0], [5.0, 6.0]]);
+
+    let parameters = embedding.parameters_mut();
+    assert_eq!(parameters[0].rows, Some(&[0, 2][..]));
+    assert!(!parameters[0].regularize);
+}
+
+#[test]
+fn embedding_gradient_rows_reset_test() {
+    let mut embedding = embedding();
+    let mut rng = StdRng::seed_from_u64(0);
+    embedding.forward_train(&array![[2.0], [0.0], [1.0]], &mut rng);
+    embedding.backward(&array![[1.0], [1.0], [2.0], [2.0], [0.0]]);
+    embedding.forward_train(&array![[1.0], [1.0], [1.0]], &mut rng);
+    embedding.backward(&array![[1.0], [1.0], [2.0], [2.0], [0.0]]);
+
+    // The rows of the previous step are cleared before the new rows accumulate
+    assert_eq!(embedding.gradients()[0], &array![[0.0, 0.0], [3.0, 3.0], [0.0, 0.0]]);
+    assert_eq!(embedding.updated_rows(), &vec![1]);
+}
+
+#[test]
+#[should_panic(expected = "embedding index 3 is outside the vocabulary of 3")]
+fn embedding_index_outside_vocabulary_test() {
+    embedding().forward(&array![[3.0], [0.0], [1.0]]);
+}
+
+#[test]
+fn embedding_check_indices_test() {
+    let embedding = embedding();
+    assert!(embedding.check_indices(&array![[2.0, 0.0], [1.0, 0.0], [7.5, -1.0]]).is_ok());
+
+    for input in [array![[3.0], [0.0], [1.0]], array![[-1.0], [0.0], [1.0]], array![[0.5], [0.0], [1.0]], array![[0.0], [0.0]]] {
+        assert!(matches!(embedding.check_indices(&input), Err(GraymatError::InvalidArgument(_))));
+    }
+}
+
+/// A category in 0..5 and a numeric feature in [0, 1]. Categories 0, 2 and 4 add 0.5 to the
+/// feature, and the label is 1 when the sum is above 0.75.
+fn category_examples() -> Vec<(ColumnVector, ColumnVector)> {
+    let mut examples = Vec::new();
+    for category in 0..5 {
+        for step in 0..6 {
+            let feature = step as f32 / 5.0;
+            let sum = feature + if category % 2 == 0 { 0.5 } else { 0.0 };
+            let label = if sum > 0.75 { 1.0 } else { 0.0 };
+            examples.push((ColumnVector::from(&array![[category as f32], [feature]]),
+                           ColumnVector::from(&array![[label]])));
+        }
+    }
+    examples
+}
+
+fn embedding_network() -> NeuralNetwork {
+    // Index 5 of the vocabulary is never seen
+    let mut embedding = Embedding::new(6, 3, 1);
+    embedding.set_features(1);
+    let mut nn = Sequential::new(2)
+        .layer(Box::new(embedding))
+        .dense(8, ActivationFunction::TANH)
+        .dense(1, ActivationFunction::SIGMOID)
+        .rng(Box::new(StdRng::seed_from_u64(3)))
+        .build();
+    nn.set_optimizer(Box::new(Adam::default()));
+    nn
+}
+
+#[test]
+fn embedding_network_training_and_io_test() {
+    let mut nn = embedding_network();
+    let unseen: Vec<f32> = nn.layers()[0].downcast_ref::<Embedding>().unwrap().embedding(5).to_vec();
+
+    let loss_before = nn.calculate_loss(&category_examples());
+    nn.train(category_examples(), 200, 6, 0.02).unwrap();
+    assert!(nn.calculate_loss(&category_examples()) < loss_before / 4.0);
+
+    // Sparse updates leave the unseen embedding alone, even with Adam's momentum
+    let embedding = nn.layers()[0].downcast_ref::<Embedding>().unwrap();
+    assert_eq!(embedding.embedding(5).to_vec(), unseen);
+    assert!(embedding.embedding(0).to_vec() != embedding.embedding(1).to_vec());
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+    assert_eq!(loaded.layers()[0].layer_type(), LayerType::EMBEDDING);
+    assert_eq!(loaded.layers()[0].downcast_ref::<Embedding>().unwrap().features(), 1);
+    assert_eq!(loaded.parameters(), nn.parameters());
+    let inputs: Array2<f32> = array![[0.0, 3.0, 4.0], [0.2, 0.6, 0.9]];
+    assert_eq!(loaded.evaluate_batch(&inputs), nn.evaluate_batch(&inputs));
+}
+
+#[test]
+fn corrupt_embedding_sizes_test() {
+    let bytes = embedding().to_bytes();
+
+    // The number of indices and of numeric features follow the vocabulary and embedding sizes
+    for (offset, value) in [(16, 1u64 << 40), (24, u64::MAX)] {
+        let mut corrupt = bytes.clone();
+        corrupt[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+        assert!(matches!(Embedding::from_bytes(&corrupt), Err(GraymatError::InvalidHeader(_))));
+    }
+}
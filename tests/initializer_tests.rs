@@ -0,0 +1,70 @@
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::{thread_rng, SeedableRng};
+use graymat::activation_function::ActivationFunction;
+use graymat::initializer::Initializer;
+use graymat::neural_network::NeuralNetwork;
+
+#[test]
+fn uniform_bounds_test() {
+    let mut rng = thread_rng();
+    let mut weights: Array2<f32> = Array2::zeros((30, 20));
+
+    Initializer::XAVIER_UNIFORM.initialize(&mut weights, &mut rng);
+    let limit = (6.0f32 / 50.0).sqrt();
+    assert!(weights.iter().all(|w| w.abs() <= limit));
+    assert!(weights.iter().any(|w| *w < 0.0));
+
+    Initializer::HE_UNIFORM.initialize(&mut weights, &mut rng);
+    let limit = (6.0f32 / 20.0).sqrt();
+    assert!(weights.iter().all(|w| w.abs() <= limit));
+
+    Initializer::LECUN_UNIFORM.initialize(&mut weights, &mut rng);
+    let limit = (3.0f32 / 20.0).sqrt();
+    assert!(weights.iter().all(|w| w.abs() <= limit));
+}
+
+#[test]
+fn normal_statistics_test() {
+    let mut rng = thread_rng();
+    let mut weights: Array2<f32> = Array2::zeros((200, 100));
+
+    Initializer::HE_NORMAL.initialize(&mut weights, &mut rng);
+
+    let n = weights.len() as f32;
+    let mean = weights.sum() / n;
+    let std_dev = (weights.mapv(|w| (w - mean) * (w - mean)).sum() / n).sqrt();
+    let expected = (2.0f32 / 100.0).sqrt();
+    assert!(mean.abs() < 0.01);
+    assert!((std_dev - expected).abs() < 0.01);
+}
+
+#[test]
+fn orthogonal_test() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for shape in [(8, 5), (5, 8), (6, 6)] {
+        let mut weights: Array2<f32> = Array2::zeros(shape);
+        Initializer::ORTHOGONAL.initialize(&mut weights, &mut rng);
+
+        // The vectors along the shorter dimension are orthonormal
+        let gram = if shape.0 < shape.1 { weights.dot(&weights.t()) } else { weights.t().dot(&weights) };
+        let identity: Array2<f32> = Array2::eye(gram.nrows());
+        assert!((gram - identity).iter().all(|d| d.abs() < 1e-4));
+    }
+}
+
+#[test]
+fn network_with_initializer_test() {
+    let nn = NeuralNetwork::with_initializer(4, 2, vec![3], ActivationFunction::RELU,
+                                             Initializer::CONSTANT(0.5), Initializer::CONSTANT(0.1));
+
+    for layer in nn.dense_layers() {
+        assert!(layer.weights().iter().all(|w| *w == 0.5));
+        assert!(layer.biases().iter().all(|b| *b == 0.1));
+    }
+
+    let nn = NeuralNetwork::new(4, 2, vec![3], ActivationFunction::RELU);
+    for layer in nn.dense_layers() {
+        assert!(layer.biases().iter().all(|b| *b == 0.0));
+    }
+}
@@ -0,0 +1,252 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use ndarray::{array, Array2};
+use ndarray::linalg::general_mat_mul;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
+use graymat::error::GraymatError;
+use graymat::initializer::Initializer;
+use graymat::layer::{Layer, LayerType};
+use graymat::neural_network::{NeuralNetwork, NeuralNetworkLayer};
+use graymat::normalization::Normalization;
+use graymat::sequential::Sequential;
+use graymat::utilities::array2_utils::assign_to_buffer;
+
+/// Counts the allocations of the current thread, so tests can check that training reuses its
+/// buffers
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+/// Multiplies its inputs by a constant
+struct Scale {
+    factor: f32
+}
+
+impl Layer for Scale {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        input * self.factor
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        output_gradient * self.factor
+    }
+}
+
+/// Passes its inputs through and records the address of every buffer the network hands it
+#[derive(Default)]
+struct Probe {
+    inputs: Vec<*const f32>,
+    output_gradients: Vec<*const f32>
+}
+
+impl Layer for Probe {
+    fn forward(&self, input: &Array2<f32>) -> Array2<f32> {
+        input.clone()
+    }
+
+    fn forward_train_into(&mut self, input: &Array2<f32>, output: &mut Array2<f32>, _rng: &mut dyn RngCore) {
+        self.inputs.push(input.as_ptr());
+        assign_to_buffer(output, input);
+    }
+
+    fn backward(&mut self, output_gradient: &Array2<f32>) -> Array2<f32> {
+        output_gradient.clone()
+    }
+
+    fn backward_into(&mut self, output_gradient: &Array2<f32>, input_gradient: &mut Array2<f32>) {
+        self.output_gradients.push(output_gradient.as_ptr());
+        assign_to_buffer(input_gradient, output_gradient);
+    }
+}
+
+fn training_data() -> Vec<(ColumnVector, ColumnVector)> {
+    vec![(cvec![0.2, 0.7], cvec![1]), (cvec![-0.5, 0.1], cvec![0]), (cvec![0.9, -0.3], cvec![1])]
+}
+
+#[test]
+fn sequential_matches_constructor_test() {
+    let mut nn = NeuralNetwork::with_rng(2, 1, vec![4, 3], ActivationFunction::TANH,
+                                         Initializer::XAVIER_UNIFORM, Initializer::ZEROS,
+                                         Box::new(StdRng::seed_from_u64(7)));
+    let mut sequential = Sequential::new(2)
+        .dense(4, ActivationFunction::TANH)
+        .dense(3, ActivationFunction::TANH)
+        .dense(1, ActivationFunction::TANH)
+        .rng(Box::new(StdRng::seed_from_u64(7)))
+        .build();
+
+    nn.train(training_data(), 3, 2, 0.1).unwrap();
+    sequential.train(training_data(), 3, 2, 0.1).unwrap();
+
+    assert_eq!(nn.parameters(), sequential.parameters());
+}
+
+#[test]
+fn activation_layer_matches_dense_activation_test() {
+    let mut fused = Sequential::new(2)
+        .dense(3, ActivationFunction::SIGMOID)
+        .dense(1, ActivationFunction::LINEAR)
+        .rng(Box::new(StdRng::seed_from_u64(3)))
+        .build();
+    let mut stacked = Sequential::new(2)
+        .dense(3, ActivationFunction::LINEAR)
+        .activation(ActivationFunction::SIGMOID)
+        .dense(1, ActivationFunction::LINEAR)
+        .rng(Box::new(StdRng::seed_from_u64(3)))
+        .build();
+
+    fused.train(training_data(), 2, 3, 0.5).unwrap();
+    stacked.train(training_data(), 2, 3, 0.5).unwrap();
+
+    let difference = &fused.evaluate_batch(&array![[0.3], [0.4]]) - &stacked.evaluate_batch(&array![[0.3], [0.4]]);
+    assert!(difference.iter().all(|d| d.abs() < 1e-6));
+    assert_eq!(stacked.layers().len(), 3);
+    assert_eq!(stacked.dense_layers().len(), 2);
+}
+
+#[test]
+fn custom_layer_test() {
+    let mut nn = Sequential::new(2)
+        .dense(3, ActivationFunction::TANH)
+        .layer(Box::new(Scale { factor: 2.0 }))
+        .dense(1, ActivationFunction::LINEAR)
+        .build();
+
+    let hidden = nn.layers()[0].forward(&array![[0.3], [0.4]]);
+    let scaled = nn.layers()[1].forward(&hidden);
+    assert!(scaled == &hidden * 2.0);
+    assert_eq!(nn.layers()[1].downcast_ref::<Scale>().unwrap().factor, 2.0);
+    assert!(nn.layers()[1].downcast_ref::<NeuralNetworkLayer>().is_none());
+
+    let loss_before = nn.calculate_loss(&training_data());
+    nn.train(training_data(), 20, 3, 0.05).unwrap();
+    assert!(nn.calculate_loss(&training_data()) < loss_before);
+}
+
+#[test]
+fn sequential_io_round_trip_test() {
+    let mut nn = Sequential::new(2)
+        .dense(4, ActivationFunction::LINEAR)
+        .layer(Box::new(Normalization::batch(4)))
+        .activation(ActivationFunction::RELU)
+        .dropout(0.25)
+        .dense(1, ActivationFunction::SIGMOID)
+        .build();
+    nn.train(training_data(), 5, 3, 0.1).unwrap();
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+
+    let layer_types: Vec<LayerType> = loaded.layers().iter().map(|layer| layer.layer_type()).collect();
+    assert_eq!(layer_types, vec![LayerType::DENSE, LayerType::NORMALIZATION, LayerType::ACTIVATION,
+                                 LayerType::DROPOUT, LayerType::DENSE]);
+    assert_eq!(loaded.parameters(), nn.parameters());
+    assert_eq!(loaded.layers()[1].downcast_ref::<Normalization>(), nn.layers()[1].downcast_ref::<Normalization>());
+    assert!(loaded.evaluate(cvec![0.3, 0.4]) == nn.evaluate(cvec![0.3, 0.4]));
+}
+
+#[test]
+fn custom_layer_cannot_be_loaded_test() {
+    let nn = Sequential::new(2)
+        .dense(2, ActivationFunction::TANH)
+        .layer(Box::new(Scale { factor: 2.0 }))
+        .build();
+
+    let result = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap());
+    assert!(matches!(result, Err(GraymatError::UnknownLayer(255))));
+}
+
+#[test]
+#[should_panic(expected = "Layer 2 cannot take 3 inputs")]
+fn sequential_shape_mismatch_test() {
+    Sequential::new(2)
+        .dense(3, ActivationFunction::TANH)
+        .layer(Box::new(NeuralNetworkLayer::new(4, 1, ActivationFunction::LINEAR)));
+}
+
+#[test]
+fn dense_layer_training_does_not_allocate_test() {
+    let mut layer = NeuralNetworkLayer::new(3, 4, ActivationFunction::TANH);
+    layer.set_dropout(0.5);
+    assert_training_does_not_allocate(&mut layer);
+
+    for normalization in [Normalization::batch(4), Normalization::layer(4)] {
+        let mut layer = NeuralNetworkLayer::new(3, 4, ActivationFunction::TANH);
+        layer.set_normalization(Some(normalization));
+        assert_training_does_not_allocate(&mut layer);
+    }
+}
+
+/// Check that training steps of a 3 input, 4 neuron dense layer reuse their buffers
+fn assert_training_does_not_allocate(layer: &mut NeuralNetworkLayer) {
+    let mut rng = StdRng::seed_from_u64(0);
+    let input = Array2::from_shape_fn((3, 5), |(i, j)| i as f32 * 0.2 - j as f32 * 0.1);
+    let output_gradient = Array2::from_elem((4, 5), 0.3);
+    let mut output = Array2::zeros((0, 0));
+    let mut input_gradient = Array2::zeros((0, 0));
+
+    // Matrix products may allocate scratch space of their own, so count the allocations of the
+    // three products of a training step on their own first
+    let weights = layer.weights().clone();
+    let (mut z, mut weight_gradient, mut error) = (Array2::zeros((4, 5)), Array2::zeros((4, 3)), Array2::zeros((3, 5)));
+    let before = allocations();
+    general_mat_mul(1.0, &weights, &input, 0.0, &mut z);
+    general_mat_mul(1.0, &output_gradient, &input.t(), 0.0, &mut weight_gradient);
+    general_mat_mul(1.0, &weights.t(), &output_gradient, 0.0, &mut error);
+    let products = allocations() - before;
+
+    // The first step sizes the buffers for the batch
+    layer.forward_train_into(&input, &mut output, &mut rng);
+    layer.backward_into(&output_gradient, &mut input_gradient);
+
+    let before = allocations();
+    for _ in 0..3 {
+        layer.forward_train_into(&input, &mut output, &mut rng);
+        layer.backward_into(&output_gradient, &mut input_gradient);
+    }
+    assert_eq!(allocations() - before, 3 * products, "training steps of the same batch size reallocated buffers");
+    assert_eq!(input_gradient.dim(), (3, 5));
+}
+
+#[test]
+fn network_reuses_workspace_test() {
+    let mut nn = Sequential::new(2)
+        .dense(4, ActivationFunction::TANH)
+        .layer(Box::new(Probe::default()))
+        .activation(ActivationFunction::RELU)
+        .dense(1, ActivationFunction::SIGMOID)
+        .rng(Box::new(StdRng::seed_from_u64(5)))
+        .build();
+    let mut data = training_data();
+    data.push((cvec![0.1, 0.1], cvec![0]));
+    nn.train(data, 3, 2, 0.1).unwrap();
+
+    let probe = nn.layers()[1].downcast_ref::<Probe>().unwrap();
+    assert_eq!(probe.inputs.len(), 6);
+    assert!(probe.inputs.iter().all(|p| *p == probe.inputs[0]), "layer outputs were reallocated");
+    assert_eq!(probe.output_gradients.len(), 6);
+    assert!(probe.output_gradients.iter().all(|p| *p == probe.output_gradients[0]), "layer errors were reallocated");
+}
@@ -0,0 +1,78 @@
+use ndarray::array;
+use graymat::activation_function::ActivationFunction;
+use graymat::callback::Monitor;
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
+use graymat::learning_rate_schedule::{CosineAnnealingWarmRestarts, ExponentialDecay, LearningRateSchedule,
+                                      LinearWarmup, OneCycle, ReduceOnPlateau, StepDecay, ConstantLearningRate};
+use graymat::neural_network::NeuralNetwork;
+use graymat::training_history::{EpochRecord, TrainingHistory};
+
+fn rates(schedule: &mut dyn LearningRateSchedule, epochs: usize) -> Vec<f32> {
+    let history = TrainingHistory::default();
+    (1..=epochs).map(|epoch| schedule.learning_rate(epoch, 1.0, &history)).collect()
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn decay_schedules_test() {
+    assert_close(&rates(&mut StepDecay::new(2, 0.5), 5), &[1.0, 1.0, 0.5, 0.5, 0.25]);
+    assert_close(&rates(&mut ExponentialDecay::new(0.5), 3), &[1.0, 0.5, 0.25]);
+    assert_close(&rates(&mut LinearWarmup::new(4, Box::new(StepDecay::new(1, 0.5))), 6), &[0.25, 0.5, 0.75, 1.0, 1.0, 0.5]);
+}
+
+#[test]
+fn cosine_annealing_warm_restarts_test() {
+    let actual = rates(&mut CosineAnnealingWarmRestarts::new(2, 2, 0.0), 7);
+    // Cycles of 2 and 4 epochs, restarting at epochs 3 and 7
+    assert_close(&actual, &[1.0, 0.5, 1.0, 0.853553, 0.5, 0.146447, 1.0]);
+}
+
+#[test]
+fn one_cycle_test() {
+    let actual = rates(&mut OneCycle::new(11, 0.2, 10.0, 100.0), 11);
+
+    assert!((actual[0] - 0.1).abs() < 1e-6);
+    assert!((actual[2] - 1.0).abs() < 1e-6);
+    assert!((actual[10] - 0.001).abs() < 1e-6);
+    assert!(actual[2..].windows(2).all(|w| w[1] < w[0]));
+}
+
+#[test]
+fn reduce_on_plateau_test() {
+    let mut schedule = ReduceOnPlateau::new(Monitor::TRAIN_LOSS, 0.5, 2, 0.0, 0.2);
+    let mut history = TrainingHistory::default();
+    let mut actual = Vec::new();
+    for (epoch, loss) in [1.0, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0, 1.0, 1.0].iter().enumerate() {
+        actual.push(schedule.learning_rate(epoch + 1, 1.0, &history));
+        history.push(EpochRecord {
+            epoch: epoch + 1,
+            train_loss: *loss,
+            validation_loss: None,
+            learning_rate: actual[epoch],
+            metrics: vec![],
+            validation_metrics: vec![]
+        });
+    }
+    assert_close(&actual, &[1.0, 1.0, 1.0, 1.0, 0.5, 0.5, 0.25, 0.25, 0.2]);
+}
+
+#[test]
+fn schedule_recorded_in_history_test() {
+    let training_data = vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![0])];
+    let mut nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+    nn.set_learning_rate_schedule(Box::new(StepDecay::new(2, 0.1)));
+
+    let history = nn.train(training_data.clone(), 4, 2, 1.0).unwrap();
+    assert_close(&history.learning_rate(), &[1.0, 1.0, 0.1, 0.1]);
+
+    nn.set_learning_rate_schedule(Box::new(ConstantLearningRate::new()));
+    let history = nn.train(training_data, 2, 2, 0.3).unwrap();
+    assert_close(&history.learning_rate(), &[0.3, 0.3]);
+}
@@ -0,0 +1,141 @@
+use std::env;
+use ndarray::{array, Array2};
+use graymat::activation_function::ActivationFunction;
+use graymat::error::GraymatError;
+use graymat::neural_network::NeuralNetwork;
+use graymat::loss::{register_loss, BinaryCrossEntropy, CategoricalCrossEntropy, Hinge, Huber, Loss, LossType, MeanAbsoluteError,
+                    MeanSquaredError, Quadratic};
+
+#[test]
+fn loss_values_test() {
+    let result = array![[0.5, 1.0], [2.0, -1.0]];
+    let target = array![[0.0, 1.0], [1.0, 1.0]];
+    // Errors: [0.5, 0], [1, -2]
+    assert!((Quadratic::new().value(&result, &target) - 0.5 * (0.25 + 1.0 + 4.0) / 2.0).abs() < 1e-6);
+    assert!((MeanSquaredError::new().value(&result, &target) - (0.25 + 1.0 + 4.0) / 4.0).abs() < 1e-6);
+    assert!((MeanAbsoluteError::new().value(&result, &target) - (0.5 + 1.0 + 2.0) / 4.0).abs() < 1e-6);
+    assert!((Huber::new(1.0).value(&result, &target) - (0.125 + 0.5 + 1.5) / 4.0).abs() < 1e-6);
+    assert!((Hinge::new().value(&array![[0.5, -2.0]], &array![[1.0, 1.0]]) - (0.5 + 3.0) / 2.0).abs() < 1e-6);
+
+    let probabilities = array![[0.25], [0.75]];
+    let one_hot = array![[0.0], [1.0]];
+    assert!((CategoricalCrossEntropy::new().value(&probabilities, &one_hot) + 0.75f32.ln()).abs() < 1e-6);
+    let expected_bce = -(0.75f32.ln() + 0.75f32.ln()) / 2.0;
+    assert!((BinaryCrossEntropy::new().value(&probabilities, &one_hot) - expected_bce).abs() < 1e-6);
+}
+
+#[test]
+fn loss_gradients_match_finite_differences_test() {
+    let losses: Vec<Box<dyn Loss>> = vec![
+        Box::new(Quadratic::new()),
+        Box::new(MeanSquaredError::new()),
+        Box::new(MeanAbsoluteError::new()),
+        Box::new(Huber::new(0.5)),
+        Box::new(BinaryCrossEntropy::new()),
+        Box::new(CategoricalCrossEntropy::new()),
+        Box::new(Hinge::new())
+    ];
+    let result = array![[0.2], [0.7], [0.4]];
+    let target = array![[0.0], [1.0], [1.0]];
+    let h = 1e-3;
+    for loss in losses {
+        let gradient = loss.gradient(&result, &target);
+        for i in 0..3 {
+            let mut plus: Array2<f32> = result.clone();
+            plus[[i, 0]] += h;
+            let mut minus: Array2<f32> = result.clone();
+            minus[[i, 0]] -= h;
+            let numerical = (loss.value(&plus, &target) - loss.value(&minus, &target)) / (2.0 * h);
+            assert!((gradient[[i, 0]] - numerical).abs() < 1e-2,
+                    "{} gradient mismatch", LossType::convert_to_string(loss.loss_type()));
+        }
+    }
+}
+
+#[test]
+fn loss_gradient_into_test() {
+    let losses: Vec<Box<dyn Loss>> = vec![
+        Box::new(Quadratic::new()),
+        Box::new(MeanSquaredError::new()),
+        Box::new(MeanAbsoluteError::new()),
+        Box::new(Huber::new(0.5)),
+        Box::new(BinaryCrossEntropy::new()),
+        Box::new(CategoricalCrossEntropy::new()),
+        Box::new(Hinge::new())
+    ];
+    let result = array![[0.2, -0.5], [0.7, 1.0], [0.4, 0.4]];
+    let target = array![[0.0, -1.0], [1.0, 1.0], [1.0, 0.0]];
+    for loss in losses {
+        // The buffer is resized once, then reused
+        let mut gradient: Array2<f32> = Array2::zeros((0, 0));
+        loss.gradient_into(&result, &target, &mut gradient);
+        let buffer = gradient.as_ptr();
+        loss.gradient_into(&result, &target, &mut gradient);
+
+        assert_eq!(gradient, loss.gradient(&result, &target));
+        assert_eq!(gradient.as_ptr(), buffer, "{} reallocated its gradient", LossType::convert_to_string(loss.loss_type()));
+    }
+}
+
+#[test]
+fn loss_saved_with_network_test() {
+    let path = env::temp_dir();
+    let filename = "loss_io_test";
+
+    let mut nn_original = NeuralNetwork::new(3, 2, vec![4], ActivationFunction::SIGMOID);
+    nn_original.set_loss(Box::new(Huber::new(0.25)));
+    nn_original.to_file(&path, filename).unwrap();
+
+    let nn_loaded = NeuralNetwork::from_file(&path, filename).unwrap();
+
+    assert_eq!(nn_loaded.loss().loss_type(), LossType::HUBER);
+    assert_eq!(nn_loaded.loss().to_bytes(), nn_original.loss().to_bytes());
+}
+
+/// Quadratic cost scaled by a constant, as a custom loss with one parameter
+struct ScaledQuadratic {
+    scale: f32
+}
+
+impl Loss for ScaledQuadratic {
+    fn value(&self, result: &Array2<f32>, target: &Array2<f32>) -> f32 {
+        self.scale * Quadratic::new().value(result, target)
+    }
+
+    fn gradient(&self, result: &Array2<f32>, target: &Array2<f32>) -> Array2<f32> {
+        (result - target) * self.scale
+    }
+
+    fn loss_type(&self) -> LossType {
+        LossType::CUSTOM
+    }
+
+    fn name(&self) -> String {
+        "ScaledQuadratic".to_owned()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.scale.to_le_bytes().to_vec()
+    }
+}
+
+#[test]
+fn custom_loss_saved_with_network_test() {
+    let mut nn = NeuralNetwork::new(3, 2, vec![4], ActivationFunction::SIGMOID);
+    nn.set_loss(Box::new(ScaledQuadratic { scale: 3.0 }));
+    let bytes = nn.to_bytes().unwrap();
+
+    // A custom loss is never replaced by a built-in one
+    let result = NeuralNetwork::from_bytes(&bytes);
+    assert!(matches!(result, Err(GraymatError::UnknownLossName(name)) if name == "ScaledQuadratic"));
+
+    register_loss("ScaledQuadratic", |parameters| {
+        let scale = f32::from_le_bytes(parameters.try_into().map_err(|_| GraymatError::TruncatedData)?);
+        Ok(Box::new(ScaledQuadratic { scale }))
+    });
+    let loaded = NeuralNetwork::from_bytes(&bytes).unwrap();
+
+    assert_eq!(loaded.loss().loss_type(), LossType::CUSTOM);
+    assert_eq!(loaded.loss().name(), "ScaledQuadratic");
+    assert_eq!(loaded.loss().to_bytes(), 3.0f32.to_le_bytes().to_vec());
+}
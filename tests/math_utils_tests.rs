@@ -25,6 +25,7 @@ fn relu_prime_test() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn tanh_test() {
     let test_data = vec![(-2.0, 0.0706508),
                          (-0.5, 0.786448),
@@ -36,3 +37,35 @@ fn tanh_test() {
         assert_eq!(float_compare(graymat::utilities::math_utils::tanh_prime(t.0), t.1, precision), true);
     }
 }
+
+#[test]
+fn sig_test() {
+    let test_data = vec![(-2.0, 0.1192029),
+                         (0.0, 0.5),
+                         (2.0, 0.8807971)];
+    for t in test_data {
+        assert!(float_compare(graymat::utilities::math_utils::sigf(t.0), t.1, 5));
+        assert!((graymat::utilities::math_utils::sig(t.0 as f64) - t.1 as f64).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn sig_large_input_test() {
+    use graymat::utilities::math_utils::{sig, sigf, sigf_prime};
+    assert_eq!(sigf(-1000.0), 0.0);
+    assert_eq!(sigf(1000.0), 1.0);
+    assert!(sigf(-100.0) > 0.0 && sigf(-100.0) < 1e-40);
+    assert_eq!(sigf_prime(-1000.0), 0.0);
+    assert_eq!(sig(-1000.0), 0.0);
+    assert_eq!(sig(1000.0), 1.0);
+    assert!(sig(-700.0) > 0.0);
+}
+
+#[test]
+fn prime_from_output_test() {
+    use graymat::utilities::math_utils::{sigf, sigf_prime, sigf_prime_from_output, tanh_prime, tanh_prime_from_output};
+    for x in [-4.0, -0.5, 0.0, 0.25, 3.0] {
+        assert!(float_compare(sigf_prime_from_output(sigf(x)), sigf_prime(x), 6));
+        assert!(float_compare(tanh_prime_from_output(f32::tanh(x)), tanh_prime(x), 6));
+    }
+}
@@ -0,0 +1,39 @@
+use ndarray::array;
+use graymat::metrics::Metric;
+
+#[test]
+fn binary_metrics_test() {
+    // Predictions: 1, 1, 0, 0, 1. Targets: 1, 0, 0, 1, 1. TP = 2, FP = 1, FN = 1
+    let result = array![[0.9, 0.6, 0.2, 0.4, 0.7]];
+    let target = array![[1.0, 0.0, 0.0, 1.0, 1.0]];
+
+    assert!((Metric::ACCURACY.calculate(&result, &target) - 0.6).abs() < 1e-6);
+    assert!((Metric::PRECISION.calculate(&result, &target) - 2.0 / 3.0).abs() < 1e-6);
+    assert!((Metric::RECALL.calculate(&result, &target) - 2.0 / 3.0).abs() < 1e-6);
+    assert!((Metric::F1.calculate(&result, &target) - 2.0 / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn multi_class_metrics_test() {
+    // Predicted classes 0, 1, 1, 2. Target classes 0, 1, 2, 2
+    let result = array![[0.8, 0.1, 0.2, 0.1], [0.1, 0.7, 0.5, 0.2], [0.1, 0.2, 0.3, 0.7]];
+    let target = array![[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 1.0]];
+
+    assert!((Metric::ACCURACY.calculate(&result, &target) - 0.75).abs() < 1e-6);
+    // Per-class precision 1, 0.5, 1 and recall 1, 1, 0.5
+    assert!((Metric::PRECISION.calculate(&result, &target) - 2.5 / 3.0).abs() < 1e-6);
+    assert!((Metric::RECALL.calculate(&result, &target) - 2.5 / 3.0).abs() < 1e-6);
+    // Per-class F1 1, 2/3, 2/3
+    assert!((Metric::F1.calculate(&result, &target) - (1.0 + 4.0 / 3.0) / 3.0).abs() < 1e-6);
+}
+
+#[test]
+fn regression_metrics_test() {
+    let result = array![[1.0, 2.0, 4.0]];
+    let target = array![[1.0, 3.0, 5.0]];
+
+    assert!((Metric::MAE.calculate(&result, &target) - 2.0 / 3.0).abs() < 1e-6);
+    // SS_res = 2, SS_tot = (1 - 3)^2 + 0 + (5 - 3)^2 = 8
+    assert!((Metric::R2.calculate(&result, &target) - 0.75).abs() < 1e-6);
+    assert_eq!(Metric::R2.calculate(&target, &target), 1.0);
+}
@@ -1,28 +1,54 @@
+use std::env;
+use std::fs;
 use graymat::activation_function::ActivationFunction;
+use graymat::error::GraymatError;
+use ndarray::array;
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
 use graymat::neural_network::{NeuralNetwork};
+use graymat::normalization::{Normalization, NormalizationType};
 use graymat::neural_network_io::{check_gnm_filepath, GRAYMAT_NETWORK_FILE_EXTENSION};
+use graymat::utilities::checksum::crc32;
 
 #[test]
 fn test_network_io() {
-    let path = "./";
+    let path = env::temp_dir();
     let filename = "network_io_test";
 
     let nn_original = NeuralNetwork::new(100, 10, vec![25, 20, 25], ActivationFunction::RELU);
 
-    nn_original.to_file(path, filename);
+    nn_original.to_file(&path, filename).unwrap();
 
-    let nn_loaded = NeuralNetwork::from_file(path, filename);
+    let nn_loaded = NeuralNetwork::from_file(&path, filename).unwrap();
 
-    assert_eq!(nn_original.activation_function(), nn_loaded.activation_function());
     assert_eq!(nn_original.layers().len(), nn_loaded.layers().len());
 
     for i in 0..nn_original.layers().len() {
-        assert_eq!(nn_original.layers()[i].weights(), nn_loaded.layers()[i].weights());
-        assert_eq!(nn_original.layers()[i].biases(), nn_loaded.layers()[i].biases());
+        assert_eq!(nn_original.dense_layers()[i].activation_function(), nn_loaded.dense_layers()[i].activation_function());
+        assert_eq!(nn_original.dense_layers()[i].weights(), nn_loaded.dense_layers()[i].weights());
+        assert_eq!(nn_original.dense_layers()[i].biases(), nn_loaded.dense_layers()[i].biases());
     }
 }
 
 #[test]
+fn test_network_io_per_layer_activations() {
+    let path = env::temp_dir();
+    let filename = "network_io_activations_test";
+
+    let activations = vec![ActivationFunction::RELU, ActivationFunction::TANH, ActivationFunction::LINEAR];
+    let nn_original = NeuralNetwork::new(4, 2, vec![8, 6], activations.clone());
+
+    nn_original.to_file(&path, filename).unwrap();
+
+    let nn_loaded = NeuralNetwork::from_file(&path, filename).unwrap();
+
+    for (i, layer) in nn_loaded.dense_layers().iter().enumerate() {
+        assert_eq!(layer.activation_function(), activations[i]);
+    }
+}
+
+#[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_filepath_handling1() {
     let path = "./";
     let filename = "network_io_test";
@@ -32,10 +58,11 @@ fn test_filepath_handling1() {
         Err(err) => panic!("{:?}", err)
     };
 
-    assert_eq!(filepath.ends_with(GRAYMAT_NETWORK_FILE_EXTENSION), true);
+    assert_eq!(filepath.to_str().unwrap().ends_with(GRAYMAT_NETWORK_FILE_EXTENSION), true);
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_filepath_handling2() {
     let path = ".";
     let filename = "network_io_test.gnm";
@@ -45,7 +72,7 @@ fn test_filepath_handling2() {
         Err(err) => panic!("{:?}", err)
     };
 
-    assert_eq!(filepath.ends_with("/network_io_test.gnm"), true);
+    assert_eq!(filepath.ends_with("network_io_test.gnm"), true);
 }
 
 #[test]
@@ -60,3 +87,219 @@ fn test_filepath_handling_error() {
     };
 }
 
+
+#[test]
+fn test_network_bytes_round_trip() {
+    let nn_original = NeuralNetwork::new(5, 3, vec![4], vec![ActivationFunction::RELU, ActivationFunction::SOFTMAX]);
+
+    let bytes = nn_original.to_bytes().unwrap();
+    let nn_loaded = NeuralNetwork::from_bytes(&bytes).unwrap();
+
+    for i in 0..nn_original.layers().len() {
+        assert_eq!(nn_original.dense_layers()[i].weights(), nn_loaded.dense_layers()[i].weights());
+        assert_eq!(nn_original.dense_layers()[i].biases(), nn_loaded.dense_layers()[i].biases());
+        assert_eq!(nn_original.dense_layers()[i].activation_function(), nn_loaded.dense_layers()[i].activation_function());
+    }
+}
+
+#[test]
+fn test_network_stream_round_trip() {
+    let nn_original = NeuralNetwork::new(3, 2, vec![2], ActivationFunction::TANH);
+
+    let mut stream: Vec<u8> = Vec::new();
+    nn_original.write_to(&mut stream).unwrap();
+    nn_original.write_to(&mut stream).unwrap();
+
+    // Two networks written back to back are read back in order
+    let mut reader = stream.as_slice();
+    let first = NeuralNetwork::read_from(&mut reader).unwrap();
+    let second = NeuralNetwork::read_from(&mut reader).unwrap();
+
+    assert!(reader.is_empty());
+    assert_eq!(first.to_bytes().unwrap(), nn_original.to_bytes().unwrap());
+    assert_eq!(second.to_bytes().unwrap(), nn_original.to_bytes().unwrap());
+}
+
+#[test]
+fn test_load_truncated_bytes() {
+    let bytes = NeuralNetwork::new(3, 2, vec![2], ActivationFunction::TANH).to_bytes().unwrap();
+    let result = NeuralNetwork::from_bytes(&bytes[..bytes.len() - 1]);
+    assert!(matches!(result, Err(GraymatError::TruncatedData)));
+}
+
+#[test]
+fn test_load_missing_file() {
+    let result = NeuralNetwork::from_file("./", "does_not_exist");
+    assert!(matches!(result, Err(GraymatError::FileNotFound(_))));
+}
+
+#[test]
+fn test_load_truncated_file() {
+    let nn = NeuralNetwork::new(10, 4, vec![6], ActivationFunction::SIGMOID);
+    nn.to_file(env::temp_dir(), "network_io_truncated_test").unwrap();
+
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_truncated_test").unwrap();
+    let bytes = fs::read(&filepath).unwrap();
+    fs::write(&filepath, &bytes[..bytes.len() / 2]).unwrap();
+
+    let result = NeuralNetwork::from_file(env::temp_dir(), "network_io_truncated_test");
+    assert!(matches!(result, Err(GraymatError::TruncatedData)));
+}
+
+#[test]
+fn test_load_bad_magic() {
+    let nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+    nn.to_file(env::temp_dir(), "network_io_bad_magic_test").unwrap();
+
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_bad_magic_test").unwrap();
+    let mut bytes = fs::read(&filepath).unwrap();
+    bytes[4] = b'X';
+    fs::write(&filepath, &bytes).unwrap();
+
+    let result = NeuralNetwork::from_file(env::temp_dir(), "network_io_bad_magic_test");
+    assert!(matches!(result, Err(GraymatError::BadMagic)));
+}
+
+#[test]
+fn test_load_unknown_activation() {
+    let nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+    nn.to_file(env::temp_dir(), "network_io_unknown_activation_test").unwrap();
+
+    // The activation id is the first byte of the dense layer data, after the file header, the
+    // input size and the layer header. The layer checksum is updated to match.
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_unknown_activation_test").unwrap();
+    let mut bytes = fs::read(&filepath).unwrap();
+    bytes[40 + 13] = 200;
+    update_first_layer_checksum(&mut bytes);
+    fs::write(&filepath, &bytes).unwrap();
+
+    let result = NeuralNetwork::from_file(env::temp_dir(), "network_io_unknown_activation_test");
+    assert!(matches!(result, Err(GraymatError::UnknownActivation(200))));
+}
+
+#[test]
+fn test_load_corrupted_weights() {
+    let nn = NeuralNetwork::new(3, 2, vec![], ActivationFunction::SIGMOID);
+    nn.to_file(env::temp_dir(), "network_io_checksum_test").unwrap();
+
+    // Flip a bit in the first weight, after the activation id, dropout rate and array header
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_checksum_test").unwrap();
+    let mut bytes = fs::read(&filepath).unwrap();
+    bytes[40 + 13 + 30] ^= 0x01;
+    fs::write(&filepath, &bytes).unwrap();
+
+    let result = NeuralNetwork::from_file(env::temp_dir(), "network_io_checksum_test");
+    assert!(matches!(result, Err(GraymatError::ChecksumMismatch(0))));
+}
+
+#[test]
+fn test_load_unsupported_version() {
+    let nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+    nn.to_file(env::temp_dir(), "network_io_version_test").unwrap();
+
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_version_test").unwrap();
+    let mut bytes = fs::read(&filepath).unwrap();
+    bytes[0..4].copy_from_slice(&0x02_00_00u32.to_le_bytes());
+    fs::write(&filepath, &bytes).unwrap();
+
+    let result = NeuralNetwork::from_file(env::temp_dir(), "network_io_version_test");
+    assert!(matches!(result, Err(GraymatError::UnsupportedVersion(0x02_00_00))));
+}
+
+#[test]
+fn test_load_oversized_layer() {
+    let nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+    nn.to_file(env::temp_dir(), "network_io_oversized_test").unwrap();
+
+    // Declare 16 GiB of layer data
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_oversized_test").unwrap();
+    let mut bytes = fs::read(&filepath).unwrap();
+    bytes[40 + 1..40 + 9].copy_from_slice(&(4 * 65536 * 65536u64).to_le_bytes());
+    fs::write(&filepath, &bytes).unwrap();
+
+    let result = NeuralNetwork::from_file(env::temp_dir(), "network_io_oversized_test");
+    assert!(matches!(result, Err(GraymatError::InvalidHeader(_))));
+}
+
+#[test]
+fn test_load_version_1_1_file() {
+    // v1.1 files store one activation function for the whole network in the file header and
+    // have no optimizer or loss sections
+    let weights: Vec<Vec<f32>> = vec![vec![0.5, -0.5, 1.0, 2.0], vec![1.5, -1.0]];
+    let biases: Vec<Vec<f32>> = vec![vec![0.1, 0.2], vec![0.3]];
+    let shapes = [(2u32, 2u32), (1u32, 2u32)];
+
+    let mut meta = [0u8; 12];
+    meta.copy_from_slice(b"GrayMay(0_0)");
+    let mut bytes = bincode::serialize(&(0x01_01_00u32, meta, 37u64, 28u64, 2u32, ActivationFunction::TANH.id())).unwrap();
+    for i in 0..2 {
+        let serialized_weights = bincode::serialize(&weights[i]).unwrap();
+        let serialized_biases = bincode::serialize(&biases[i]).unwrap();
+        bytes.extend(bincode::serialize(&(shapes[i].0, shapes[i].1, shapes[i].0,
+                                          serialized_weights.len() as u64,
+                                          serialized_biases.len() as u64)).unwrap());
+        bytes.extend(serialized_weights);
+        bytes.extend(serialized_biases);
+    }
+    let filepath = check_gnm_filepath(env::temp_dir(), "network_io_v1_1_test").unwrap();
+    fs::write(&filepath, &bytes).unwrap();
+
+    let nn = NeuralNetwork::from_file(env::temp_dir(), "network_io_v1_1_test").unwrap();
+
+    assert_eq!(nn.layers().len(), 2);
+    assert_eq!(nn.dense_layers()[0].weights().shape(), &[2, 2]);
+    assert_eq!(nn.dense_layers()[0].weights()[[1, 1]], 2.0);
+    assert_eq!(nn.dense_layers()[1].biases()[[0, 0]], 0.3);
+    for layer in nn.dense_layers() {
+        assert_eq!(layer.activation_function(), ActivationFunction::TANH);
+    }
+}
+
+#[test]
+fn test_network_io_dropout() {
+    let mut nn = NeuralNetwork::new(4, 2, vec![8, 6], ActivationFunction::RELU);
+    nn.set_layer_dropout(0, 0.5).unwrap();
+    nn.set_layer_dropout(1, 0.2).unwrap();
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+
+    assert_eq!(loaded.dense_layers()[0].dropout(), 0.5);
+    assert_eq!(loaded.dense_layers()[1].dropout(), 0.2);
+    assert_eq!(loaded.dense_layers()[2].dropout(), 0.0);
+}
+
+#[test]
+fn test_load_invalid_dropout() {
+    let nn = NeuralNetwork::new(3, 2, vec![4], ActivationFunction::SIGMOID);
+    let mut bytes = nn.to_bytes().unwrap();
+    bytes[40 + 14..40 + 18].copy_from_slice(&1.5f32.to_le_bytes());
+    update_first_layer_checksum(&mut bytes);
+
+    let result = NeuralNetwork::from_bytes(&bytes);
+    assert!(matches!(result, Err(GraymatError::InvalidHeader(_))));
+}
+
+#[test]
+fn test_network_io_normalization() {
+    let training_data = vec![(cvec![0.2, 0.7], cvec![1]), (cvec![-0.5, 0.1], cvec![0]), (cvec![0.9, -0.3], cvec![1])];
+    let mut nn = NeuralNetwork::new(2, 1, vec![4, 3], ActivationFunction::TANH);
+    nn.set_layer_normalization(0, Some(Normalization::batch(4)));
+    nn.set_layer_normalization(1, Some(Normalization::layer(3)));
+    nn.train(training_data, 5, 3, 0.1).unwrap();
+
+    let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+
+    for i in 0..3 {
+        assert_eq!(loaded.dense_layers()[i].normalization(), nn.dense_layers()[i].normalization());
+    }
+    assert_eq!(loaded.dense_layers()[0].normalization().unwrap().normalization_type(), NormalizationType::BATCH);
+    assert!(loaded.dense_layers()[2].normalization().is_none());
+    assert!(loaded.evaluate(cvec![0.3, 0.4]) == nn.evaluate(cvec![0.3, 0.4]));
+}
+
+/// Recompute the checksum of the first layer of a saved network after its data was modified
+fn update_first_layer_checksum(bytes: &mut [u8]) {
+    let size_bytes = u64::from_le_bytes(bytes[40 + 1..40 + 9].try_into().unwrap()) as usize;
+    let checksum = crc32(&bytes[40 + 13..40 + 13 + size_bytes]);
+    bytes[40 + 9..40 + 13].copy_from_slice(&checksum.to_le_bytes());
+}
@@ -0,0 +1,180 @@
+use ndarray::{array, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::initializer::Initializer;
+use graymat::loss::CategoricalCrossEntropy;
+use graymat::cvec;
+use graymat::neural_network::NeuralNetwork;
+
+#[test]
+fn per_layer_activation_test() {
+    let weights = vec![array![[1.0, -1.0], [-1.0, 1.0]], array![[2.0, -3.0]]];
+    let biases = vec![array![[0.0], [0.0]], array![[-1.0]]];
+    let nn = NeuralNetwork::from(weights, biases, vec![ActivationFunction::RELU, ActivationFunction::LINEAR]);
+
+    // hidden = relu([-2, 2]) = [0, 2], output = 2 * 0 - 3 * 2 - 1
+    assert_eq!(nn.evaluate(cvec![1, 3])[0], -7.0);
+}
+
+#[test]
+#[should_panic]
+fn per_layer_activation_count_mismatch_test() {
+    NeuralNetwork::new(2, 1, vec![3, 3], vec![ActivationFunction::RELU, ActivationFunction::SIGMOID]);
+}
+
+#[test]
+fn softmax_cross_entropy_gradient_test() {
+    let weights = vec![array![[0.2, -0.4], [0.7, 0.1], [-0.3, 0.5]]];
+    let biases = vec![array![[0.1], [-0.2], [0.05]]];
+    let mut nn = NeuralNetwork::from(weights.clone(), biases.clone(), ActivationFunction::SOFTMAX);
+    nn.set_loss(Box::new(CategoricalCrossEntropy::new()));
+
+    let input = array![[0.6], [-0.9]];
+    let target = array![[0.0], [1.0], [0.0]];
+    let (weight_gradients, _) = nn.back_propagate(&input, &target);
+
+    let cost = |w: &Array2<f32>| -> f32 {
+        let nn = NeuralNetwork::from(vec![w.clone()], biases.clone(), ActivationFunction::SOFTMAX);
+        let output = nn.evaluate(ColumnVector::from(&input));
+        -(0..3).map(|i| target[[i, 0]] * output[i].ln()).sum::<f32>()
+    };
+
+    let h = 1e-3;
+    for i in 0..3 {
+        for j in 0..2 {
+            let mut w_plus = weights[0].clone();
+            w_plus[[i, j]] += h;
+            let mut w_minus = weights[0].clone();
+            w_minus[[i, j]] -= h;
+            let numerical = (cost(&w_plus) - cost(&w_minus)) / (2.0 * h);
+            assert!((weight_gradients[0][[i, j]] - numerical).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn predict_class_test() {
+    let mut training_data = Vec::with_capacity(4);
+    for i in 0..4 {
+        let mut target = ColumnVector::zeros(4);
+        target[i] = 1.0;
+        training_data.push((cvec![i & 0b1, (i >> 1) & 0b1], target));
+    }
+
+    let mut nn = NeuralNetwork::new(2, 4, vec![8], vec![ActivationFunction::SIGMOID, ActivationFunction::SOFTMAX]);
+    nn.set_loss(Box::new(CategoricalCrossEntropy::new()));
+    nn.train(training_data.clone(), 2_000, 4, 0.5).unwrap();
+
+    for (i, (input, _)) in training_data.into_iter().enumerate() {
+        assert_eq!(nn.predict_class(input), i);
+    }
+}
+
+#[test]
+fn evaluate_batch_matches_evaluate_test() {
+    let nn = NeuralNetwork::new(3, 2, vec![5, 4], vec![ActivationFunction::TANH, ActivationFunction::RELU, ActivationFunction::SOFTMAX]);
+    let inputs = array![[0.1, -0.5, 0.9, 0.0], [0.3, 0.2, -0.7, 1.0], [-0.6, 0.8, 0.4, -1.0]];
+
+    let outputs = nn.evaluate_batch(&inputs);
+
+    assert_eq!(outputs.dim(), (2, 4));
+    for j in 0..4 {
+        let expected = nn.evaluate(ColumnVector::from(&inputs.column(j).to_owned().insert_axis(Axis(1))));
+        for i in 0..2 {
+            assert!((outputs[[i, j]] - expected[i]).abs() < 1e-6);
+        }
+    }
+}
+
+#[test]
+fn batched_back_propagate_matches_per_example_test() {
+    let mut nn = NeuralNetwork::new(3, 2, vec![4], vec![ActivationFunction::SIGMOID, ActivationFunction::TANH]);
+    let inputs = array![[0.1, -0.5, 0.9], [0.3, 0.2, -0.7], [-0.6, 0.8, 0.4]];
+    let targets = array![[1.0, 0.0, 0.5], [0.0, 1.0, -0.5]];
+
+    let (batch_weights, batch_biases) = nn.back_propagate(&inputs, &targets);
+
+    for layer in 0..2 {
+        let mut weight_sum = Array2::<f32>::zeros(batch_weights[layer].dim());
+        let mut bias_sum = Array2::<f32>::zeros(batch_biases[layer].dim());
+        for j in 0..3 {
+            let input = inputs.column(j).to_owned().insert_axis(Axis(1));
+            let target = targets.column(j).to_owned().insert_axis(Axis(1));
+            let (weights, biases) = nn.back_propagate(&input, &target);
+            weight_sum += &weights[layer];
+            bias_sum += &biases[layer];
+        }
+        assert_eq!(batch_biases[layer].dim(), nn.dense_layers()[layer].biases().dim());
+        assert!((&batch_weights[layer] - &weight_sum).iter().all(|d| d.abs() < 1e-5));
+        assert!((&batch_biases[layer] - &bias_sum).iter().all(|d| d.abs() < 1e-5));
+    }
+}
+
+#[test]
+fn train_step_matches_back_propagate_test() {
+    let weights = vec![array![[0.2, -0.4], [0.7, 0.1], [-0.3, 0.5]], array![[0.6, -0.2, 0.3]]];
+    let biases = vec![array![[0.1], [-0.2], [0.05]], array![[0.4]]];
+    let activations = vec![ActivationFunction::TANH, ActivationFunction::SIGMOID];
+    let training_data = vec![(cvec![0.5, -1.0], cvec![1.0]), (cvec![-0.3, 0.8], cvec![0.0])];
+
+    // Batches of 1 and then 2 examples also exercise reallocating the training workspace
+    let mut nn = NeuralNetwork::from(weights.clone(), biases.clone(), activations.clone());
+    nn.train(training_data[..1].to_vec(), 1, 1, 0.5).unwrap();
+    nn.train(training_data.clone(), 1, 2, 0.5).unwrap();
+
+    let mut expected_weights = weights;
+    let mut expected_biases = biases;
+    let steps: Vec<Vec<(ColumnVector, ColumnVector)>> = vec![training_data[..1].to_vec(), training_data];
+    for step in steps {
+        let mut reference = NeuralNetwork::from(expected_weights.clone(), expected_biases.clone(), activations.clone());
+        for (input, target) in step.iter() {
+            let (weight_gradients, bias_gradients) = reference.back_propagate(input.get_data(), target.get_data());
+            for i in 0..2 {
+                expected_weights[i] -= &(&weight_gradients[i] * (0.5 / step.len() as f32));
+                expected_biases[i] -= &(&bias_gradients[i] * (0.5 / step.len() as f32));
+            }
+        }
+    }
+
+    for i in 0..2 {
+        assert!((nn.dense_layers()[i].weights() - &expected_weights[i]).iter().all(|d| d.abs() < 1e-5));
+        assert!((nn.dense_layers()[i].biases() - &expected_biases[i]).iter().all(|d| d.abs() < 1e-5));
+    }
+}
+
+fn seeded_network(seed: u64) -> NeuralNetwork {
+    NeuralNetwork::with_rng(2, 1, vec![4], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID],
+                            Initializer::XAVIER_NORMAL, Initializer::ZEROS,
+                            Box::new(StdRng::seed_from_u64(seed)))
+}
+
+#[test]
+fn seeded_training_is_reproducible_test() {
+    let training_data = vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![1]), (cvec![0, 0], cvec![0]), (cvec![1, 1], cvec![0])];
+
+    let mut first = seeded_network(7);
+    let mut second = seeded_network(7);
+    first.train(training_data.clone(), 50, 2, 0.5).unwrap();
+    second.train(training_data, 50, 2, 0.5).unwrap();
+
+    assert_eq!(first.to_bytes().unwrap(), second.to_bytes().unwrap());
+    assert_ne!(seeded_network(7).to_bytes().unwrap(), seeded_network(8).to_bytes().unwrap());
+}
+
+#[test]
+fn set_seed_test() {
+    let mut first = NeuralNetwork::new(3, 2, vec![5], ActivationFunction::RELU);
+    let mut second = NeuralNetwork::new(3, 2, vec![5], ActivationFunction::RELU);
+
+    first.set_seed(123);
+    first.initialize(Initializer::HE_UNIFORM, Initializer::CONSTANT(0.1));
+    second.set_seed(123);
+    second.initialize(Initializer::HE_UNIFORM, Initializer::CONSTANT(0.1));
+
+    for i in 0..2 {
+        assert_eq!(first.dense_layers()[i].weights(), second.dense_layers()[i].weights());
+        assert_eq!(first.dense_layers()[i].biases(), second.dense_layers()[i].biases());
+    }
+}
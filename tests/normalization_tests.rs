@@ -0,0 +1,155 @@
+use ndarray::{array, Array2, Axis};
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::error::GraymatError;
+use graymat::layer::Layer;
+use graymat::neural_network::{NeuralNetwork, NeuralNetworkLayer};
+use graymat::normalization::{Normalization, NormalizationType};
+
+fn weights() -> Vec<Array2<f32>> {
+    vec![array![[0.5, -0.3], [0.8, 0.2], [-0.6, 0.9]], array![[0.4, -0.7, 0.3]]]
+}
+
+fn biases() -> Vec<Array2<f32>> {
+    vec![array![[0.1], [-0.2], [0.3]], array![[0.05]]]
+}
+
+fn inputs() -> Array2<f32> {
+    array![[0.2, -0.5, 0.9, 0.4], [0.7, 0.1, -0.3, -0.8]]
+}
+
+fn targets() -> Array2<f32> {
+    array![[1.0, 0.0, 0.5, 0.2]]
+}
+
+fn examples() -> Vec<(ColumnVector, ColumnVector)> {
+    let (inputs, targets) = (inputs(), targets());
+    (0..inputs.ncols())
+        .map(|j| (ColumnVector::from(&inputs.column(j).to_owned().insert_axis(Axis(1))),
+                  ColumnVector::from(&targets.column(j).to_owned().insert_axis(Axis(1)))))
+        .collect()
+}
+
+fn normalized_network(weights: Vec<Array2<f32>>, normalization: Normalization) -> NeuralNetwork {
+    let mut nn = NeuralNetwork::from(weights, biases(), vec![ActivationFunction::TANH, ActivationFunction::LINEAR]);
+    nn.set_layer_normalization(0, Some(normalization));
+    nn
+}
+
+/// Loss with batch statistics. With a momentum of 0 one training pass at a learning rate of 0
+/// copies the statistics of the full batch into the running statistics.
+fn batch_norm_loss(weights: Vec<Array2<f32>>) -> f32 {
+    let mut nn = normalized_network(weights, Normalization::new(NormalizationType::BATCH, 3, 0.0, 1e-5));
+    nn.train(examples(), 1, 4, 0.0).unwrap();
+    nn.calculate_loss(&examples())
+}
+
+#[test]
+fn batch_norm_weight_gradient_test() {
+    let mut nn = normalized_network(weights(), Normalization::batch(3));
+    let (weight_gradients, _) = nn.back_propagate(&inputs(), &targets());
+
+    let h = 1e-2;
+    for i in 0..3 {
+        for j in 0..2 {
+            let mut w_plus = weights();
+            w_plus[0][[i, j]] += h;
+            let mut w_minus = weights();
+            w_minus[0][[i, j]] -= h;
+            let numerical = (batch_norm_loss(w_plus) - batch_norm_loss(w_minus)) / (2.0 * h);
+            assert!((weight_gradients[0][[i, j]] / 4.0 - numerical).abs() < 1e-3);
+        }
+    }
+}
+
+#[test]
+fn layer_norm_scale_gradient_test() {
+    let learning_rate = 1e-3;
+    let mut nn = normalized_network(weights(), Normalization::layer(3));
+    nn.train(examples(), 1, 4, learning_rate).unwrap();
+    let trained_scale = nn.dense_layers()[0].normalization().unwrap().scale().clone();
+
+    let loss_with_scale = |neuron: usize, delta: f32| -> f32 {
+        let mut normalization = Normalization::layer(3);
+        normalization.scale_mut()[[neuron, 0]] += delta;
+        normalized_network(weights(), normalization).calculate_loss(&examples())
+    };
+
+    let h = 1e-2;
+    for neuron in 0..3 {
+        let numerical = (loss_with_scale(neuron, h) - loss_with_scale(neuron, -h)) / (2.0 * h);
+        let analytic = (1.0 - trained_scale[[neuron, 0]]) / learning_rate;
+        assert!((analytic - numerical).abs() < 1e-2);
+    }
+}
+
+#[test]
+fn batch_norm_running_statistics_test() {
+    let mut nn = normalized_network(weights(), Normalization::new(NormalizationType::BATCH, 3, 0.0, 1e-5));
+    nn.train(examples(), 1, 4, 0.0).unwrap();
+
+    let z = weights()[0].dot(&inputs()) + &biases()[0];
+    let mean = z.mean_axis(Axis(1)).unwrap().insert_axis(Axis(1));
+    let variance = (&z - &mean).mapv(|x| x * x).mean_axis(Axis(1)).unwrap().insert_axis(Axis(1));
+
+    let normalization = nn.dense_layers()[0].normalization().unwrap();
+    assert!((normalization.running_mean() - &mean).iter().all(|d| d.abs() < 1e-5));
+    assert!((normalization.running_variance() - &variance).iter().all(|d| d.abs() < 1e-5));
+
+    // Evaluation uses the running statistics, so a single example gives the same output as it
+    // did within the batch
+    let batch_outputs = nn.evaluate_batch(&inputs());
+    let single_output = nn.evaluate_batch(&inputs().column(2).to_owned().insert_axis(Axis(1)));
+    assert!((single_output[[0, 0]] - batch_outputs[[0, 2]]).abs() < 1e-6);
+}
+
+#[test]
+fn layer_norm_output_test() {
+    let mut nn = NeuralNetwork::from(vec![weights()[0].clone()], vec![biases()[0].clone()], ActivationFunction::LINEAR);
+    nn.set_layer_normalization(0, Some(Normalization::layer(3)));
+
+    let outputs = nn.evaluate_batch(&inputs());
+    for column in outputs.columns() {
+        let mean = column.mean().unwrap();
+        let variance = column.mapv(|x| (x - mean) * (x - mean)).mean().unwrap();
+        assert!(mean.abs() < 1e-5);
+        assert!((variance - 1.0).abs() < 1e-3);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Normalization must have one value per neuron")]
+fn normalization_shape_mismatch_test() {
+    normalized_network(weights(), Normalization::batch(4));
+}
+
+#[test]
+fn corrupt_normalization_test() {
+    let bytes = Normalization::batch(3).to_bytes();
+    assert!(Normalization::from_bytes(&bytes).unwrap() == Normalization::batch(3));
+
+    // The momentum and epsilon follow the normalization type (u32)
+    for (offset, value) in [(4, 1.5f32), (4, f32::NAN), (8, 0.0), (8, -1e-5), (8, f32::INFINITY)] {
+        let mut corrupt = bytes.clone();
+        corrupt[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        assert!(matches!(Normalization::from_bytes(&corrupt), Err(GraymatError::InvalidHeader(_))));
+    }
+}
+
+#[test]
+fn corrupt_dense_layer_normalization_test() {
+    let mut nn = NeuralNetwork::from(weights(), biases(), ActivationFunction::SIGMOID);
+    nn.set_layer_normalization(0, Some(Normalization::batch(3)));
+    let layer = nn.dense_layers()[0];
+    let bytes = layer.to_bytes();
+    assert!(NeuralNetworkLayer::from_bytes(&bytes).is_ok());
+
+    // The dense layer embeds the serialized normalization, epsilon is at its offset 8
+    let normalization = layer.normalization().unwrap().to_bytes();
+    let start = bytes.windows(normalization.len()).position(|window| window == normalization.as_slice()).unwrap();
+    for (offset, value) in [(4, 1.5f32), (8, 0.0), (8, f32::NAN)] {
+        let mut corrupt = bytes.clone();
+        corrupt[start + offset..start + offset + 4].copy_from_slice(&value.to_le_bytes());
+        assert!(matches!(NeuralNetworkLayer::from_bytes(&corrupt), Err(GraymatError::InvalidHeader(_))));
+    }
+}
@@ -0,0 +1,122 @@
+use std::env;
+use ndarray::array;
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::neural_network::NeuralNetwork;
+use graymat::optimizer::{Adam, AdamW, Momentum, Nesterov, Optimizer, OptimizerType, RMSProp, StochasticGradientDescent};
+
+#[test]
+fn sgd_update_test() {
+    let mut optimizer = StochasticGradientDescent::new();
+    let mut parameter = array![[1.0, 2.0], [3.0, 4.0]];
+    optimizer.update(0, &mut parameter, &array![[1.0, -1.0], [0.5, 0.0]], true, 0.1);
+    assert_eq!(parameter, array![[0.9, 2.1], [2.95, 4.0]]);
+}
+
+#[test]
+fn momentum_accumulates_velocity_test() {
+    let mut optimizer = Momentum::new(0.5);
+    let mut parameter = array![[0.0]];
+    optimizer.update(0, &mut parameter, &array![[1.0]], true, 1.0);
+    assert_eq!(parameter, array![[-1.0]]);
+    optimizer.update(0, &mut parameter, &array![[1.0]], true, 1.0);
+    assert_eq!(parameter, array![[-2.5]]);
+}
+
+#[test]
+fn adam_first_step_test() {
+    // Bias correction makes the first Adam step approximately lr * sign(g)
+    let mut optimizer = Adam::default();
+    let mut parameter = array![[0.0, 0.0]];
+    optimizer.update(0, &mut parameter, &array![[4.0, -0.01]], true, 0.1);
+    assert!((parameter[[0, 0]] + 0.1).abs() < 1e-5);
+    assert!((parameter[[0, 1]] - 0.1).abs() < 1e-4);
+}
+
+#[test]
+fn adamw_decays_only_regularized_parameters_test() {
+    // With a zero gradient only the weight decay moves a parameter
+    let mut optimizer = AdamW::new(0.9, 0.999, 1e-8, 0.5);
+    let mut weights = array![[2.0, -4.0]];
+    let mut biases = array![[2.0, -4.0]];
+    optimizer.update(0, &mut weights, &array![[0.0, 0.0]], true, 0.1);
+    optimizer.update(1, &mut biases, &array![[0.0, 0.0]], false, 0.1);
+    assert_eq!(weights, array![[1.9, -3.8]]);
+    assert_eq!(biases, array![[2.0, -4.0]]);
+}
+
+#[test]
+fn adamw_network_does_not_decay_biases_test() {
+    // A step on an example with zero error has zero gradients
+    let mut nn = NeuralNetwork::from(vec![array![[1.0, 1.0]]], vec![array![[0.5]]], ActivationFunction::LINEAR);
+    nn.set_optimizer(Box::new(AdamW::new(0.9, 0.999, 1e-8, 0.1)));
+    nn.train(vec![(ColumnVector::from_vec(vec![1.0, 2.0]), ColumnVector::from_vec(vec![3.5]))], 1, 1, 0.1).unwrap();
+
+    assert!(nn.dense_layers()[0].weights().iter().all(|&w| w < 1.0));
+    assert_eq!(nn.dense_layers()[0].biases(), &array![[0.5]]);
+}
+
+#[test]
+fn optimizers_minimize_quadratic_test() {
+    let optimizers: Vec<(Box<dyn Optimizer>, f32)> = vec![
+        (Box::new(StochasticGradientDescent::new()), 0.1),
+        (Box::new(Momentum::default()), 0.05),
+        (Box::new(Nesterov::default()), 0.05),
+        (Box::new(RMSProp::default()), 0.01),
+        (Box::new(Adam::default()), 0.05),
+        (Box::new(AdamW::default()), 0.05)
+    ];
+    for (mut optimizer, learning_rate) in optimizers {
+        // f(p) = sum((p - 3)^2), gradient 2 * (p - 3)
+        let mut parameter = array![[0.0, 6.0]];
+        for _i in 0..1000 {
+            let gradient = (&parameter - 3.0) * 2.0;
+            optimizer.update(0, &mut parameter, &gradient, true, learning_rate);
+        }
+        for p in parameter.iter() {
+            assert!((p - 3.0).abs() < 0.1, "{:?} did not converge: {}",
+                    OptimizerType::convert_to_string(optimizer.optimizer_type()), p);
+        }
+    }
+}
+
+#[test]
+fn update_rows_leaves_other_rows_test() {
+    let optimizers: Vec<Box<dyn Optimizer>> = vec![
+        Box::new(StochasticGradientDescent::new()),
+        Box::new(Momentum::default()),
+        Box::new(Nesterov::default()),
+        Box::new(RMSProp::default()),
+        Box::new(Adam::default()),
+        Box::new(AdamW::default())
+    ];
+    for mut optimizer in optimizers {
+        let mut sparse = array![[1.0, 2.0], [3.0, 4.0], [5.0, 6.0]];
+        let mut dense = sparse.clone();
+        let gradient = array![[1.0, -1.0], [0.5, 0.5], [2.0, 0.0]];
+        optimizer.update_rows(0, &mut sparse, &gradient, &[0, 2], true, 0.1);
+        optimizer.update(1, &mut dense, &gradient, true, 0.1);
+
+        // Listed rows get the dense update, the others are untouched
+        assert_eq!(sparse.row(0), dense.row(0));
+        assert_eq!(sparse.row(1), array![3.0, 4.0]);
+        assert_eq!(sparse.row(2), dense.row(2));
+    }
+}
+
+#[test]
+fn optimizer_state_saved_with_network_test() {
+    let path = env::temp_dir();
+    let filename = "optimizer_io_test";
+
+    let mut nn_original = NeuralNetwork::new(2, 1, vec![3], ActivationFunction::SIGMOID);
+    nn_original.set_optimizer(Box::new(Adam::default()));
+    nn_original.train(vec![(ColumnVector::from_vec(vec![1.0, 0.0]),
+                            ColumnVector::from_vec(vec![1.0]))], 3, 1, 0.01).unwrap();
+    nn_original.to_file(&path, filename).unwrap();
+
+    let nn_loaded = NeuralNetwork::from_file(&path, filename).unwrap();
+
+    assert_eq!(nn_loaded.optimizer().optimizer_type(), OptimizerType::ADAM);
+    assert_eq!(nn_loaded.optimizer().to_bytes(), nn_original.optimizer().to_bytes());
+}
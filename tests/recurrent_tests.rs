@@ -0,0 +1,179 @@
+use ndarray::{array, s, Array2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
+use graymat::error::GraymatError;
+use graymat::initializer::Initializer;
+use graymat::layer::Layer;
+use graymat::neural_network::NeuralNetwork;
+use graymat::recurrent::{column_to_sequence, sequence_to_column, Recurrent, RecurrentType};
+use graymat::sequential::Sequential;
+use graymat::utilities::checksum::crc32;
+
+mod common;
+
+/// Two sequences of 4 steps of 2 features
+fn inputs() -> Array2<f32> {
+    common::inputs(8)
+}
+
+fn initialized(mut layer: Recurrent) -> Recurrent {
+    layer.initialize(Initializer::UNIFORM(-0.8, 0.8), Initializer::UNIFORM(-0.5, 0.5), &mut StdRng::seed_from_u64(2));
+    layer
+}
+
+#[test]
+fn recurrent_gradient_test() {
+    for recurrent_type in [RecurrentType::RNN, RecurrentType::LSTM, RecurrentType::GRU] {
+        common::check_gradients(&mut initialized(Recurrent::new(recurrent_type, 2, 3, 4)), &inputs(), 2e-2);
+
+        let mut sequences = initialized(Recurrent::new(recurrent_type, 2, 3, 4));
+        sequences.set_return_sequences(true);
+        common::check_gradients(&mut sequences, &inputs(), 2e-2);
+    }
+}
+
+#[test]
+fn return_sequences_test() {
+    let last = initialized(Recurrent::lstm(2, 3, 4));
+    let mut sequences = last.clone();
+    sequences.set_return_sequences(true);
+
+    assert_eq!(last.output_size(8), Some(3));
+    assert_eq!(sequences.output_size(8), Some(12));
+    assert_eq!(last.output_size(6), None);
+
+    let all_steps = sequences.forward(&inputs());
+    assert_eq!(all_steps.slice(s![9..12, ..]), last.forward(&inputs()));
+
+    // The first step only sees the first input
+    let mut one_step = Recurrent::lstm(2, 3, 1);
+    one_step.input_weights_mut().assign(last.input_weights());
+    one_step.biases_mut().assign(last.biases());
+    assert_eq!(one_step.forward(&inputs().slice(s![0..2, ..]).to_owned()), all_steps.slice(s![0..3, ..]));
+}
+
+#[test]
+fn truncated_backpropagation_test() {
+    let mut full = initialized(Recurrent::gru(2, 3, 4));
+    let mut truncated = full.clone();
+    truncated.set_truncation(Some(1));
+
+    let output = full.forward_train(&inputs(), &mut StdRng::seed_from_u64(0));
+    truncated.forward_train(&inputs(), &mut StdRng::seed_from_u64(0));
+    let full_input_gradient = full.backward(&common::loss_gradient(&output));
+    let truncated_input_gradient = truncated.backward(&common::loss_gradient(&output));
+
+    // Only the last step is back propagated through
+    assert!(truncated_input_gradient.slice(s![..6, ..]).iter().all(|g| *g == 0.0));
+    assert_eq!(truncated_input_gradient.slice(s![6.., ..]), full_input_gradient.slice(s![6.., ..]));
+    assert!(full_input_gradient.slice(s![..6, ..]).iter().any(|g| *g != 0.0));
+    assert!(truncated.gradients()[0] != full.gradients()[0]);
+}
+
+#[test]
+fn truncation_trains_every_output_test() {
+    let mut full = initialized(Recurrent::lstm(2, 3, 4));
+    full.set_return_sequences(true);
+    let mut truncated = full.clone();
+    truncated.set_truncation(Some(2));
+
+    // Only the output of the first step carries loss
+    let mut output_gradient = Array2::zeros((12, 2));
+    output_gradient.slice_mut(s![..3, ..]).fill(1.0);
+    full.forward_train(&inputs(), &mut StdRng::seed_from_u64(0));
+    truncated.forward_train(&inputs(), &mut StdRng::seed_from_u64(0));
+    let full_input_gradient = full.backward(&output_gradient);
+    let truncated_input_gradient = truncated.backward(&output_gradient);
+
+    // The first step starts from a zero hidden state, so only the recurrent weights get no gradient
+    assert!(truncated.gradients()[0].iter().any(|g| *g != 0.0));
+    assert!(truncated.gradients()[2].iter().any(|g| *g != 0.0));
+    assert!(truncated_input_gradient.slice(s![..2, ..]).iter().any(|g| *g != 0.0));
+    // The first step lies in the first window, so it is back propagated exactly
+    assert_eq!(truncated_input_gradient, full_input_gradient);
+    assert_eq!(truncated.gradients(), full.gradients());
+}
+
+#[test]
+fn corrupt_recurrent_sizes_test() {
+    let bytes = initialized(Recurrent::lstm(2, 3, 4)).to_bytes();
+    assert_eq!(Recurrent::from_bytes(&bytes).unwrap().parameters(), initialized(Recurrent::lstm(2, 3, 4)).parameters());
+
+    // The hidden size follows the recurrent type (u32) and the input size (u64). Neither a huge
+    // nor an overflowing size may be allocated before it is checked against the weights.
+    for hidden_size in [1u64 << 40, u64::MAX] {
+        let mut corrupt = bytes.clone();
+        corrupt[12..20].copy_from_slice(&hidden_size.to_le_bytes());
+        assert!(matches!(Recurrent::from_bytes(&corrupt), Err(GraymatError::ShapeMismatch(_))));
+    }
+}
+
+#[test]
+fn sequence_column_test() {
+    let sequence = vec![cvec![1, 2], cvec![3, 4], cvec![5, 6]];
+    let column = sequence_to_column(&sequence);
+    assert!(column == cvec![1, 2, 3, 4, 5, 6]);
+
+    let steps = column_to_sequence(&column, 2);
+    assert_eq!(steps.len(), 3);
+    for (step, expected) in steps.iter().zip(sequence.iter()) {
+        assert!(step == expected);
+    }
+}
+
+/// Sequences of 5 values in {-1, 1}, labelled with their first value. The label has to be
+/// remembered across the remaining steps.
+fn first_value_sequences() -> Vec<(ColumnVector, ColumnVector)> {
+    (0..16).map(|i| {
+        let steps: Vec<ColumnVector> = (0..5).map(|t| cvec![if (i >> (t % 4)) & 1 == 1 { 1.0 } else { -1.0 }]).collect();
+        let label = if i & 1 == 1 { 1.0 } else { 0.0 };
+        (sequence_to_column(&steps), ColumnVector::from(&array![[label]]))
+    }).collect()
+}
+
+#[test]
+fn recurrent_network_training_and_io_test() {
+    for recurrent_type in [RecurrentType::RNN, RecurrentType::LSTM, RecurrentType::GRU] {
+        let mut nn = Sequential::new(5)
+            .layer(Box::new(Recurrent::new(recurrent_type, 1, 6, 5)))
+            .dense(1, ActivationFunction::SIGMOID)
+            .rng(Box::new(StdRng::seed_from_u64(11)))
+            .build();
+
+        let loss_before = nn.calculate_loss(&first_value_sequences());
+        nn.train(first_value_sequences(), 150, 4, 0.5).unwrap();
+        assert!(nn.calculate_loss(&first_value_sequences()) < loss_before / 2.0);
+
+        let loaded = NeuralNetwork::from_bytes(&nn.to_bytes().unwrap()).unwrap();
+        let recurrent = loaded.layers()[0].downcast_ref::<Recurrent>().unwrap();
+        assert_eq!(recurrent.recurrent_type(), recurrent_type);
+        assert_eq!(loaded.parameters(), nn.parameters());
+        for (input, _) in first_value_sequences() {
+            assert!(loaded.evaluate(input.clone()) == nn.evaluate(input));
+        }
+    }
+}
+
+#[test]
+fn corrupt_recurrent_file_test() {
+    let nn = Sequential::new(10)
+        .layer(Box::new(Recurrent::lstm(2, 3, 5)))
+        .dense(1, ActivationFunction::SIGMOID)
+        .build();
+    let bytes = nn.to_bytes().unwrap();
+
+    // The recurrent layer is the first layer and its sequence length follows the recurrent type
+    // (u32), the input size and the hidden size (u64)
+    for sequence_length in [1u64 << 40, u64::MAX] {
+        let mut corrupt = bytes.clone();
+        corrupt[53 + 20..53 + 28].copy_from_slice(&sequence_length.to_le_bytes());
+        let size_bytes = u64::from_le_bytes(corrupt[41..49].try_into().unwrap()) as usize;
+        let checksum = crc32(&corrupt[53..53 + size_bytes]);
+        corrupt[49..53].copy_from_slice(&checksum.to_le_bytes());
+
+        assert!(matches!(NeuralNetwork::from_bytes(&corrupt), Err(GraymatError::InvalidHeader(_))));
+    }
+}
@@ -0,0 +1,87 @@
+use ndarray::{array, Array2};
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
+use graymat::layer::Parameter;
+use graymat::neural_network::NeuralNetwork;
+use graymat::regularization::{GradientClipping, Regularization};
+
+#[test]
+fn penalty_test() {
+    let weights = array![[1.0, -2.0], [0.0, 3.0]];
+
+    assert_eq!(Regularization::NONE.penalty(&weights), 0.0);
+    assert!((Regularization::L1(0.1).penalty(&weights) - 0.6).abs() < 1e-6);
+    assert!((Regularization::L2(0.1).penalty(&weights) - 0.7).abs() < 1e-6);
+    assert!((Regularization::ELASTIC_NET(0.1, 0.1).penalty(&weights) - 1.3).abs() < 1e-6);
+
+    let mut gradient: Array2<f32> = Array2::zeros((2, 2));
+    Regularization::ELASTIC_NET(0.1, 0.5).add_gradient(&weights, &mut gradient);
+    let expected = array![[0.6, -1.1], [0.0, 1.6]];
+    assert!((gradient - expected).iter().all(|d| d.abs() < 1e-6));
+}
+
+#[test]
+fn clip_by_value_test() {
+    let mut first = array![[0.5, -3.0]];
+    let mut second = array![[2.0]];
+
+    GradientClipping::VALUE(1.0).clip(&mut [&mut first, &mut second]);
+
+    assert_eq!(first, array![[0.5, -1.0]]);
+    assert_eq!(second, array![[1.0]]);
+}
+
+#[test]
+fn clip_by_global_norm_test() {
+    let mut first = array![[3.0, 0.0]];
+    let mut second = array![[-4.0]];
+
+    // The global norm is 5, so both arrays are halved
+    GradientClipping::NORM(2.5).clip(&mut [&mut first, &mut second]);
+    assert_eq!(first, array![[1.5, 0.0]]);
+    assert_eq!(second, array![[-2.0]]);
+
+    // Gradients within the limit are unchanged
+    GradientClipping::NORM(10.0).clip(&mut [&mut first, &mut second]);
+    assert_eq!(first, array![[1.5, 0.0]]);
+}
+
+#[test]
+fn clip_sparse_parameter_test() {
+    let mut value: Array2<f32> = Array2::zeros((3, 1));
+    let mut sparse_gradient = array![[3.0], [100.0], [-4.0]];
+    let mut dense_value: Array2<f32> = Array2::zeros((1, 1));
+    let mut dense_gradient = array![[0.0]];
+    let rows = [0, 2];
+    let mut parameters = vec![
+        Parameter { value: &mut value, gradient: &mut sparse_gradient, regularize: false, rows: Some(&rows) },
+        Parameter { value: &mut dense_value, gradient: &mut dense_gradient, regularize: true, rows: None }
+    ];
+
+    // Row 1 did not receive a gradient, so it neither counts towards the norm nor is scaled
+    GradientClipping::NORM(2.5).clip_parameters(&mut parameters);
+    assert_eq!(sparse_gradient, array![[1.5], [100.0], [-2.0]]);
+}
+
+#[test]
+fn regularized_training_step_test() {
+    let weights = vec![array![[0.5, -1.0]]];
+    let biases = vec![array![[0.2]]];
+    let training_data = vec![(cvec![1, 2], cvec![1])];
+
+    let mut nn = NeuralNetwork::from(weights.clone(), biases.clone(), ActivationFunction::SIGMOID);
+    nn.set_regularization(Regularization::L2(0.1));
+    let data_loss = nn.calculate_loss(&training_data);
+    let (weight_gradients, bias_gradients) = nn.back_propagate(training_data[0].0.get_data(), training_data[0].1.get_data());
+
+    let history = nn.train(training_data, 1, 1, 0.5).unwrap();
+
+    // Reported loss includes the penalty, 0.05 * (0.25 + 1)
+    assert!((history.train_loss()[0] - (data_loss + 0.0625)).abs() < 1e-6);
+    // Weights decay, biases do not
+    let expected_weights = &weights[0] - &((&weight_gradients[0] + &(&weights[0] * 0.1)) * 0.5);
+    let expected_biases = &biases[0] - &(&bias_gradients[0] * 0.5);
+    assert!((nn.dense_layers()[0].weights() - &expected_weights).iter().all(|d| d.abs() < 1e-6));
+    assert!((nn.dense_layers()[0].biases() - &expected_biases).iter().all(|d| d.abs() < 1e-6));
+}
@@ -0,0 +1,107 @@
+use ndarray::array;
+use graymat::activation_function::ActivationFunction;
+use graymat::column_vector::ColumnVector;
+use graymat::cvec;
+use graymat::error::GraymatError;
+use graymat::metrics::Metric;
+use graymat::neural_network::NeuralNetwork;
+use graymat::training_history::{EpochRecord, TrainingHistory};
+
+fn history() -> TrainingHistory {
+    let mut history = TrainingHistory::new(vec![Metric::ACCURACY]);
+    history.push(EpochRecord {
+        epoch: 1,
+        train_loss: 0.5,
+        validation_loss: None,
+        learning_rate: 0.1,
+        metrics: vec![(Metric::ACCURACY, 0.25)],
+        validation_metrics: vec![]
+    });
+    history.push(EpochRecord {
+        epoch: 2,
+        train_loss: 0.25,
+        validation_loss: Some(f32::NAN),
+        learning_rate: 0.05,
+        metrics: vec![(Metric::ACCURACY, 0.75)],
+        validation_metrics: vec![(Metric::ACCURACY, 0.5)]
+    });
+    history
+}
+
+#[test]
+fn train_records_history_test() {
+    let training_data = vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![1]), (cvec![0, 0], cvec![0]), (cvec![1, 1], cvec![0])];
+    let validation_data = training_data.clone();
+
+    let mut nn = NeuralNetwork::new(2, 1, vec![4], vec![ActivationFunction::TANH, ActivationFunction::SIGMOID]);
+    nn.set_seed(3);
+    nn.set_metrics(vec![Metric::ACCURACY, Metric::MAE]);
+    let history = nn.train_with_validation(training_data, Some(&validation_data), 500, 2, 1.0).unwrap();
+
+    assert_eq!(history.epochs().len(), 500);
+    assert_eq!(history.epochs()[0].epoch, 1);
+    let train_loss = history.train_loss();
+    assert!(train_loss[499] < train_loss[0]);
+
+    let last = &history.epochs()[499];
+    assert!((last.validation_loss.unwrap() - nn.calculate_loss(&validation_data)).abs() < 1e-6);
+    assert!(last.metric(Metric::MAE).is_some());
+    assert!(last.validation_metric(Metric::ACCURACY).is_some());
+    assert_eq!(last.metric(Metric::F1), None);
+}
+
+#[test]
+fn train_without_validation_test() {
+    let training_data = vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![0])];
+    let mut nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+
+    let history = nn.train(training_data, 3, 1, 0.1).unwrap();
+
+    assert_eq!(history.validation_loss(), vec![None, None, None]);
+    assert!(history.epochs().iter().all(|e| e.metrics.is_empty() && e.validation_metrics.is_empty()));
+}
+
+#[test]
+fn training_metrics_from_batch_outputs_test() {
+    let training_data = vec![(cvec![1, 0], cvec![1]), (cvec![0, 1], cvec![1]), (cvec![0, 0], cvec![0]), (cvec![1, 1], cvec![0])];
+    let validation_data = training_data.clone();
+
+    let mut nn = NeuralNetwork::new(2, 1, vec![3], ActivationFunction::SIGMOID);
+    nn.set_seed(5);
+    nn.set_metrics(vec![Metric::MAE]);
+    let history = nn.train_with_validation(training_data, Some(&validation_data), 2, 1, 0.0).unwrap();
+
+    for epoch in history.epochs() {
+        let train = epoch.metric(Metric::MAE).unwrap();
+        let validation = epoch.validation_metric(Metric::MAE).unwrap();
+        assert!((train - validation).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn invalid_batch_size_test() {
+    let mut nn = NeuralNetwork::new(2, 1, vec![], ActivationFunction::SIGMOID);
+
+    // A batch larger than the training data would train nothing every epoch
+    for batch_size in [0, 2] {
+        let result = nn.train(vec![(cvec![1, 0], cvec![1])], 1, batch_size, 0.1);
+        assert!(matches!(result, Err(GraymatError::InvalidArgument(_))));
+    }
+}
+
+#[test]
+fn history_csv_test() {
+    assert_eq!(history().to_csv(),
+               "epoch,train_loss,validation_loss,learning_rate,accuracy,validation_accuracy\n\
+                1,0.5,,0.1,0.25,\n\
+                2,0.25,NaN,0.05,0.75,0.5\n");
+}
+
+#[test]
+fn history_json_test() {
+    assert_eq!(history().to_json(),
+               "{\"epochs\":[\
+                {\"epoch\":1,\"train_loss\":0.5,\"validation_loss\":null,\"learning_rate\":0.1,\"metrics\":{\"accuracy\":0.25},\"validation_metrics\":{}},\
+                {\"epoch\":2,\"train_loss\":0.25,\"validation_loss\":null,\"learning_rate\":0.05,\"metrics\":{\"accuracy\":0.75},\"validation_metrics\":{\"accuracy\":0.5}}\
+                ]}");
+}